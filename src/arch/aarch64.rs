@@ -0,0 +1,216 @@
+use super::{Arch, RelocationInputs, RelocationType, truncate};
+use std::error::Error;
+
+pub struct Aarch64;
+
+// Indices into the registers as laid out in user_pt_regs, see
+// https://elixir.bootlin.com/linux/v6.6/source/arch/arm64/include/uapi/asm/ptrace.h#L88
+const FP: usize = 29;
+const LR: usize = 30;
+const SP: usize = 31;
+const PC: usize = 32;
+
+impl Arch for Aarch64 {
+    fn name(&self) -> &'static str {
+        "aarch64"
+    }
+
+    fn num_registers(&self) -> usize {
+        34 // x0-x30, sp, pc, pstate
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        const NAMES: [&str; 34] = [
+            "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13",
+            "x14", "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25",
+            "x26", "x27", "x28", "x29", "x30", "sp", "pc", "pstate",
+        ];
+        NAMES.get(index).unwrap_or(&"?")
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        index == 33 // pstate
+    }
+
+    fn ip_index(&self) -> usize {
+        PC
+    }
+
+    fn sp_index(&self) -> usize {
+        SP
+    }
+
+    fn fp_index(&self) -> usize {
+        FP
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        Some(LR)
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        // see https://github.com/ARM-software/abi-aa/blob/main/aadwarf64/aadwarf64.rst
+        match dwarf {
+            0..=31 => Some(dwarf as usize), // x0-x30 and sp
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::Aarch64(RelocationAarch64::from_u32(rtype)?))
+    }
+
+    fn strip_pac(&self, addr: u64, mask: u64) -> u64 {
+        // Bit 55 selects between user and kernel address ranges.
+        if addr & (1 << 55) == 0 {
+            addr & !mask
+        } else {
+            addr | mask
+        }
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("pc", "points to the instruction currently being executed"),
+            (
+                "sp",
+                "points to the bottom of the stack, local variables appear after this",
+            ),
+            (
+                "x29",
+                "the frame pointer, points to the saved x29 and x30 for the caller",
+            ),
+            ("x30", "the link register, contains the return address"),
+        ]
+    }
+}
+
+// See https://github.com/ARM-software/abi-aa/blob/main/aaelf64/aaelf64.rst#relocation
+#[derive(Debug)]
+pub enum RelocationAarch64 {
+    // name                 val     calculation
+    None,             // 0
+    Abs64,            // 257     S + A
+    Abs32,            // 258     S + A
+    Abs16,            // 259     S + A
+    Prel64,           // 260     S + A - P
+    Prel32,           // 261     S + A - P
+    Prel16,           // 262     S + A - P
+    AdrPrelPgHi21,    // 275     Page(S+A) - Page(P)
+    AddAbsLo12Nc,     // 277     S + A
+    LdSt8AbsLo12Nc,   // 278     S + A
+    TstBr14,          // 279     S + A - P
+    CondBr19,         // 280     S + A - P
+    Jump26,           // 282     S + A - P
+    Call26,           // 283     S + A - P
+    LdSt16AbsLo12Nc,  // 284     S + A
+    LdSt32AbsLo12Nc,  // 285     S + A
+    LdSt64AbsLo12Nc,  // 286     S + A
+    LdSt128AbsLo12Nc, // 299     S + A
+    AdrGotPage,       // 311     Page(G(GDAT(S+A))) - Page(P)
+    Ld64GotLo12Nc,    // 312     G(GDAT(S+A))
+    Copy,             // 1024
+    GlobDat,          // 1025    S + A
+    JumpSlot,         // 1026    S + A
+    Relative,         // 1027    Delta(S) + A
+    TlsDtpMod,        // 1028    DTPMOD(S)
+    TlsDtpRel,        // 1029    DTPREL(S+A)
+    TlsTpRel,         // 1030    TPREL(S+A)
+    TlsDesc,          // 1031    TLSDESC(S+A)
+    IRelative,        // 1032    Indirect(Delta(S) + A)
+}
+
+impl RelocationAarch64 {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationAarch64::None),
+            257 => Ok(RelocationAarch64::Abs64),
+            258 => Ok(RelocationAarch64::Abs32),
+            259 => Ok(RelocationAarch64::Abs16),
+            260 => Ok(RelocationAarch64::Prel64),
+            261 => Ok(RelocationAarch64::Prel32),
+            262 => Ok(RelocationAarch64::Prel16),
+            275 => Ok(RelocationAarch64::AdrPrelPgHi21),
+            277 => Ok(RelocationAarch64::AddAbsLo12Nc),
+            278 => Ok(RelocationAarch64::LdSt8AbsLo12Nc),
+            279 => Ok(RelocationAarch64::TstBr14),
+            280 => Ok(RelocationAarch64::CondBr19),
+            282 => Ok(RelocationAarch64::Jump26),
+            283 => Ok(RelocationAarch64::Call26),
+            284 => Ok(RelocationAarch64::LdSt16AbsLo12Nc),
+            285 => Ok(RelocationAarch64::LdSt32AbsLo12Nc),
+            286 => Ok(RelocationAarch64::LdSt64AbsLo12Nc),
+            299 => Ok(RelocationAarch64::LdSt128AbsLo12Nc),
+            311 => Ok(RelocationAarch64::AdrGotPage),
+            312 => Ok(RelocationAarch64::Ld64GotLo12Nc),
+            1024 => Ok(RelocationAarch64::Copy),
+            1025 => Ok(RelocationAarch64::GlobDat),
+            1026 => Ok(RelocationAarch64::JumpSlot),
+            1027 => Ok(RelocationAarch64::Relative),
+            1028 => Ok(RelocationAarch64::TlsDtpMod),
+            1029 => Ok(RelocationAarch64::TlsDtpRel),
+            1030 => Ok(RelocationAarch64::TlsTpRel),
+            1031 => Ok(RelocationAarch64::TlsDesc),
+            1032 => Ok(RelocationAarch64::IRelative),
+            _ => Err(format!("bad aarch64 relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes, truncated to the size of its field.
+    /// Relocations within instructions and those that need G or TLS offsets return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let p = inputs.p;
+        let (value, size) = match self {
+            RelocationAarch64::Abs64 => (inputs.s?.wrapping_add(a?), 8),
+            RelocationAarch64::Abs32 => (inputs.s?.wrapping_add(a?), 4),
+            RelocationAarch64::Abs16 => (inputs.s?.wrapping_add(a?), 2),
+            RelocationAarch64::Prel64 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 8),
+            RelocationAarch64::Prel32 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 4),
+            RelocationAarch64::Prel16 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 2),
+            RelocationAarch64::GlobDat | RelocationAarch64::JumpSlot => {
+                (inputs.s?.wrapping_add(a?), 8)
+            }
+            RelocationAarch64::Relative => (inputs.b.wrapping_add(a?), 8),
+            _ => return None,
+        };
+        Some(truncate(value, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocation_values() {
+        let mut inputs = RelocationInputs {
+            s: Some(0x7f00_0000_1000),
+            a: Some(8),
+            p: 0x5555_0000_2000,
+            b: 0x5555_0000_0000,
+            z: 0,
+            got: None,
+            addr_size: 8,
+        };
+        let value = |r: RelocationAarch64, inputs: &RelocationInputs| r.value(inputs);
+        assert_eq!(
+            value(RelocationAarch64::Abs64, &inputs),
+            Some(0x7f00_0000_1008)
+        );
+        assert_eq!(value(RelocationAarch64::Prel32, &inputs), Some(0xffff_f008));
+        assert_eq!(
+            value(RelocationAarch64::GlobDat, &inputs),
+            Some(0x7f00_0000_1008)
+        );
+        assert_eq!(
+            value(RelocationAarch64::Relative, &inputs),
+            Some(0x5555_0000_0008)
+        );
+        assert_eq!(value(RelocationAarch64::Call26, &inputs), None);
+
+        // REL relocations store the addend at the place.
+        inputs.a = None;
+        assert_eq!(value(RelocationAarch64::Abs64, &inputs), None);
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Arch, FrameLayout, RelocationInputs, RelocationType, truncate};
+use std::error::Error;
+
+pub struct Arm;
+
+// Indices into the registers as laid out in user_regs, see
+// https://elixir.bootlin.com/linux/v6.6/source/arch/arm/include/asm/user.h#L63
+const FP: usize = 11;
+const SP: usize = 13;
+const LR: usize = 14;
+const PC: usize = 15;
+
+impl Arch for Arm {
+    fn name(&self) -> &'static str {
+        "arm"
+    }
+
+    fn num_registers(&self) -> usize {
+        18 // r0-r15, cpsr, orig_r0
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        const NAMES: [&str; 17] = [
+            "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp",
+            "lr", "pc", "cpsr",
+        ];
+        NAMES.get(index).unwrap_or(&"?") // 17 is orig_r0
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        index == 16 // cpsr
+    }
+
+    fn ip_index(&self) -> usize {
+        PC
+    }
+
+    fn sp_index(&self) -> usize {
+        SP
+    }
+
+    fn fp_index(&self) -> usize {
+        FP
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        Some(LR)
+    }
+
+    fn frame_layout(&self, ptr_size: i64) -> FrameLayout {
+        // gcc emits `push {fp, lr}; add fp, sp, #4` so fp points at the saved lr.
+        FrameLayout::Record {
+            caller_fp: -ptr_size,
+            ret: 0,
+        }
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        // see https://github.com/ARM-software/abi-aa/blob/main/aadwarf32/aadwarf32.rst
+        match dwarf {
+            0..=15 => Some(dwarf as usize),
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::Arm(RelocationArm::from_u32(rtype)?))
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("pc", "points to the instruction currently being executed"),
+            (
+                "sp",
+                "points to the bottom of the stack, local variables appear after this",
+            ),
+            ("r11", "the frame pointer (for ARM code)"),
+            ("lr", "the link register, contains the return address"),
+        ]
+    }
+}
+
+// See https://github.com/ARM-software/abi-aa/blob/main/aaelf32/aaelf32.rst#relocation
+#[derive(Debug)]
+pub enum RelocationArm {
+    // name         val     calculation
+    None,        // 0
+    Abs32,       // 2       (S + A) | T
+    Rel32,       // 3       ((S + A) | T) - P
+    ThmCall,     // 10      ((S + A) | T) - P
+    TlsDtpMod32, // 17      Module[S]
+    TlsDtpOff32, // 18      S + A - TLS
+    TlsTpOff32,  // 19      S + A - tp
+    Copy,        // 20
+    GlobDat,     // 21      (S + A) | T
+    JumpSlot,    // 22      (S + A) | T
+    Relative,    // 23      B(S) + A
+    BasePrel,    // 25      B(S) + A - P
+    GotBrel,     // 26      GOT(S) + A - GOT_ORG
+    Call,        // 28      ((S + A) | T) - P
+    Jump24,      // 29      ((S + A) | T) - P
+    V4Bx,        // 40
+    Prel31,      // 42      ((S + A) | T) - P
+    MovwAbsNc,   // 43      (S + A) | T
+    MovtAbs,     // 44      S + A
+    IRelative,   // 160
+}
+
+impl RelocationArm {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationArm::None),
+            2 => Ok(RelocationArm::Abs32),
+            3 => Ok(RelocationArm::Rel32),
+            10 => Ok(RelocationArm::ThmCall),
+            17 => Ok(RelocationArm::TlsDtpMod32),
+            18 => Ok(RelocationArm::TlsDtpOff32),
+            19 => Ok(RelocationArm::TlsTpOff32),
+            20 => Ok(RelocationArm::Copy),
+            21 => Ok(RelocationArm::GlobDat),
+            22 => Ok(RelocationArm::JumpSlot),
+            23 => Ok(RelocationArm::Relative),
+            25 => Ok(RelocationArm::BasePrel),
+            26 => Ok(RelocationArm::GotBrel),
+            28 => Ok(RelocationArm::Call),
+            29 => Ok(RelocationArm::Jump24),
+            40 => Ok(RelocationArm::V4Bx),
+            42 => Ok(RelocationArm::Prel31),
+            43 => Ok(RelocationArm::MovwAbsNc),
+            44 => Ok(RelocationArm::MovtAbs),
+            160 => Ok(RelocationArm::IRelative),
+            _ => Err(format!("bad arm relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes. Symbols for Thumb functions already have
+    /// T set. Relocations within instructions and those that need G or TLS offsets
+    /// return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let value = match self {
+            RelocationArm::Abs32 => inputs.s?.wrapping_add(a?),
+            RelocationArm::Rel32 => inputs.s?.wrapping_add(a?).wrapping_sub(inputs.p),
+            // The dynamic linker ignores the addend for these.
+            RelocationArm::GlobDat | RelocationArm::JumpSlot => inputs.s?,
+            RelocationArm::Relative => inputs.b.wrapping_add(a?),
+            _ => return None,
+        };
+        Some(truncate(value, 4))
+    }
+}
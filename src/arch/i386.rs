@@ -0,0 +1,167 @@
+use super::{Arch, CallSite, RelocationInputs, RelocationType, truncate, x86_call_before};
+use std::error::Error;
+
+pub struct I386;
+
+// Indices into the registers as laid out in user_regs_struct, see
+// https://elixir.bootlin.com/linux/v6.6/source/arch/x86/include/asm/user_32.h#L78
+const EBP: usize = 5;
+const EIP: usize = 12;
+const ESP: usize = 15;
+
+impl Arch for I386 {
+    fn name(&self) -> &'static str {
+        "i386"
+    }
+
+    fn num_registers(&self) -> usize {
+        17
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        match index {
+            0 => "ebx",
+            1 => "ecx",
+            2 => "edx",
+            3 => "esi",
+            4 => "edi",
+            5 => "ebp",
+            6 => "eax",
+            7 => "ds",
+            8 => "es",
+            9 => "fs",
+            10 => "gs",
+            12 => "eip",
+            13 => "cs",
+            14 => "eflags",
+            15 => "esp",
+            16 => "ss",
+            _ => "?", // 11 is orig_eax
+        }
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        matches!(index, 7..=10 | 13 | 14 | 16)
+    }
+
+    fn ip_index(&self) -> usize {
+        EIP
+    }
+
+    fn sp_index(&self) -> usize {
+        ESP
+    }
+
+    fn fp_index(&self) -> usize {
+        EBP
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        None
+    }
+
+    fn decodes_calls(&self) -> bool {
+        true
+    }
+
+    fn call_before(&self, code: &[u8], ret: u64) -> Option<CallSite> {
+        x86_call_before(code, ret)
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        // see table 2.14 in https://www.uclibc.org/docs/psABI-i386.pdf
+        match dwarf {
+            0 => Some(6), // eax
+            1 => Some(1), // ecx
+            2 => Some(2), // edx
+            3 => Some(0), // ebx
+            4 => Some(ESP),
+            5 => Some(EBP),
+            6 => Some(3), // esi
+            7 => Some(4), // edi
+            8 => Some(EIP),
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::I386(RelocationI386::from_u32(rtype)?))
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("eip", "points to the instruction currently being executed"),
+            (
+                "esp",
+                "points to the bottom of the stack, local variables appear after this",
+            ),
+            (
+                "ebp",
+                "points to the top of the stack (depending on compiler options)",
+            ),
+        ]
+    }
+}
+
+// See https://www.uclibc.org/docs/psABI-i386.pdf
+#[derive(Debug)]
+pub enum RelocationI386 {
+    // name          val     calculation
+    None,        // 0
+    ThirtyTwo,   // 1       S + A
+    Pc32,        // 2       S + A - P
+    Got32,       // 3       G + A
+    Plt32,       // 4       L + A - P
+    Copy,        // 5
+    GlobDat,     // 6       S
+    JmpSlot,     // 7       S
+    Relative,    // 8       B + A
+    GotOff,      // 9       S + A - GOT
+    GotPc,       // 10      GOT + A - P
+    TlsTpOff,    // 14
+    TlsDtpMod32, // 35
+    TlsDtpOff32, // 36
+    TlsTpOff32,  // 37
+    IRelative,   // 42      indirect (B + A)
+}
+
+impl RelocationI386 {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationI386::None),
+            1 => Ok(RelocationI386::ThirtyTwo),
+            2 => Ok(RelocationI386::Pc32),
+            3 => Ok(RelocationI386::Got32),
+            4 => Ok(RelocationI386::Plt32),
+            5 => Ok(RelocationI386::Copy),
+            6 => Ok(RelocationI386::GlobDat),
+            7 => Ok(RelocationI386::JmpSlot),
+            8 => Ok(RelocationI386::Relative),
+            9 => Ok(RelocationI386::GotOff),
+            10 => Ok(RelocationI386::GotPc),
+            14 => Ok(RelocationI386::TlsTpOff),
+            35 => Ok(RelocationI386::TlsDtpMod32),
+            36 => Ok(RelocationI386::TlsDtpOff32),
+            37 => Ok(RelocationI386::TlsTpOff32),
+            42 => Ok(RelocationI386::IRelative),
+            _ => Err(format!("bad i386 relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes. G and L are not known so GOT and PLT
+    /// entry relocations return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let p = inputs.p;
+        let value = match self {
+            RelocationI386::ThirtyTwo => inputs.s?.wrapping_add(a?),
+            RelocationI386::Pc32 => inputs.s?.wrapping_add(a?).wrapping_sub(p),
+            RelocationI386::GlobDat | RelocationI386::JmpSlot => inputs.s?,
+            RelocationI386::Relative => inputs.b.wrapping_add(a?),
+            RelocationI386::GotOff => inputs.s?.wrapping_add(a?).wrapping_sub(inputs.got?),
+            RelocationI386::GotPc => inputs.got?.wrapping_add(a?).wrapping_sub(p),
+            _ => return None,
+        };
+        Some(truncate(value, 4))
+    }
+}
@@ -0,0 +1,156 @@
+use super::{Arch, FrameLayout, RelocationInputs, RelocationType, truncate};
+use std::error::Error;
+
+/// 32-bit MIPS (o32).
+pub struct Mips;
+
+// Indices into the registers as laid out in elf_gregset_t, see
+// https://elixir.bootlin.com/linux/v6.6/source/arch/mips/include/asm/reg.h#L20
+const R0: usize = 6;
+const SP: usize = R0 + 29;
+const FP: usize = R0 + 30;
+const RA: usize = R0 + 31;
+const EPC: usize = 40;
+
+impl Arch for Mips {
+    fn name(&self) -> &'static str {
+        "mips"
+    }
+
+    fn num_registers(&self) -> usize {
+        45 // ELF_NGREG
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        const NAMES: [&str; 32] = [
+            "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5",
+            "t6", "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1",
+            "gp", "sp", "fp", "ra",
+        ];
+        match index {
+            R0..=RA => NAMES[index - R0],
+            38 => "lo",
+            39 => "hi",
+            EPC => "pc",
+            41 => "badvaddr",
+            42 => "status",
+            43 => "cause",
+            _ => "?", // padding
+        }
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        matches!(index, 38 | 39 | 42 | 43) || index == R0 + 26 || index == R0 + 27 // k0, k1
+    }
+
+    fn ip_index(&self) -> usize {
+        EPC
+    }
+
+    fn sp_index(&self) -> usize {
+        SP
+    }
+
+    fn fp_index(&self) -> usize {
+        FP
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        Some(RA)
+    }
+
+    fn frame_layout(&self, _ptr_size: i64) -> FrameLayout {
+        // Where ra is saved depends on the function's prologue so walking the stack
+        // needs DWARF CFI.
+        FrameLayout::None
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        match dwarf {
+            0..=31 => Some(R0 + dwarf as usize),
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::Mips(RelocationMips::from_u32(rtype)?))
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("pc", "points to the instruction currently being executed"),
+            (
+                "sp",
+                "points to the bottom of the stack, local variables appear after this",
+            ),
+            ("ra", "the return address register"),
+            (
+                "badvaddr",
+                "the address that caused the last address exception",
+            ),
+        ]
+    }
+}
+
+// See https://refspecs.linuxfoundation.org/elf/mipsabi.pdf
+#[derive(Debug)]
+pub enum RelocationMips {
+    // name          val     calculation
+    None,        // 0
+    Sixteen,     // 1       S + sign_extend(A)
+    ThirtyTwo,   // 2       S + A
+    Rel32,       // 3       A - EA + S
+    TwentySix,   // 4       (((A << 2) | (P & 0xf0000000)) + S) >> 2
+    Hi16,        // 5       %high(AHL + S)
+    Lo16,        // 6       AHL + S
+    GpRel16,     // 7       sign_extend(A) + S + GP0 - GP
+    Literal,     // 8       sign_extend(A) + L
+    Got16,       // 9       G
+    Pc16,        // 10      sign_extend(A) + S - P
+    Call16,      // 11      G
+    GpRel32,     // 12      A + S + GP0 - GP
+    TlsDtpMod32, // 38
+    TlsDtpRel32, // 39
+    TlsTpRel32,  // 47
+    Copy,        // 126
+    JumpSlot,    // 127
+}
+
+impl RelocationMips {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationMips::None),
+            1 => Ok(RelocationMips::Sixteen),
+            2 => Ok(RelocationMips::ThirtyTwo),
+            3 => Ok(RelocationMips::Rel32),
+            4 => Ok(RelocationMips::TwentySix),
+            5 => Ok(RelocationMips::Hi16),
+            6 => Ok(RelocationMips::Lo16),
+            7 => Ok(RelocationMips::GpRel16),
+            8 => Ok(RelocationMips::Literal),
+            9 => Ok(RelocationMips::Got16),
+            10 => Ok(RelocationMips::Pc16),
+            11 => Ok(RelocationMips::Call16),
+            12 => Ok(RelocationMips::GpRel32),
+            38 => Ok(RelocationMips::TlsDtpMod32),
+            39 => Ok(RelocationMips::TlsDtpRel32),
+            47 => Ok(RelocationMips::TlsTpRel32),
+            126 => Ok(RelocationMips::Copy),
+            127 => Ok(RelocationMips::JumpSlot),
+            _ => Err(format!("bad mips relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes, truncated to the size of its field.
+    /// Relocations within instructions and those that need G or GP return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let (value, size) = match self {
+            RelocationMips::Sixteen => (inputs.s?.wrapping_add(a?), 2),
+            RelocationMips::ThirtyTwo => (inputs.s?.wrapping_add(a?), 4),
+            RelocationMips::JumpSlot => (inputs.s?, 4),
+            _ => return None,
+        };
+        Some(truncate(value, size))
+    }
+}
@@ -0,0 +1,422 @@
+//! Details that depend upon the CPU architecture the core was produced on, e.g.
+//! register names and the layout of the general purpose registers in NT_PRSTATUS.
+pub mod aarch64;
+pub mod arm;
+pub mod i386;
+pub mod mips;
+pub mod powerpc;
+pub mod x86_64;
+
+pub use aarch64::*;
+pub use arm::*;
+pub use i386::*;
+pub use mips::*;
+pub use powerpc::*;
+pub use x86_64::*;
+
+use std::error::Error;
+use std::fmt;
+
+/// Implemented for each supported architecture. Register indices are indices into the
+/// general purpose registers saved in NT_PRSTATUS (i.e. PrStatus::registers).
+pub trait Arch: Sync {
+    /// Short name, e.g. "x86_64".
+    fn name(&self) -> &'static str;
+
+    /// Number of general purpose registers in NT_PRSTATUS.
+    fn num_registers(&self) -> usize;
+
+    /// Returns "?" for registers that shouldn't be shown to users.
+    fn register_name(&self, index: usize) -> &'static str;
+
+    /// Returns true for stuff like segment registers.
+    fn is_rare_register(&self, index: usize) -> bool;
+
+    /// Instruction pointer, e.g. rip or pc.
+    fn ip_index(&self) -> usize;
+
+    /// Stack pointer, e.g. rsp or sp.
+    fn sp_index(&self) -> usize;
+
+    /// Frame pointer, e.g. rbp or x29.
+    fn fp_index(&self) -> usize;
+
+    /// Link register (holds the return address), None if the architecture uses the stack
+    /// for return addresses.
+    fn lr_index(&self) -> Option<usize>;
+
+    /// How to walk the frame pointer chain.
+    fn frame_layout(&self, ptr_size: i64) -> FrameLayout {
+        FrameLayout::Record {
+            caller_fp: 0,
+            ret: ptr_size,
+        }
+    }
+
+    /// Maps a DWARF register number to a register index.
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize>;
+
+    /// Converts the type field of a relocation entry.
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>>;
+
+    /// Removes pointer authentication bits from a code address, mask comes from
+    /// NT_ARM_PAC_MASK.
+    fn strip_pac(&self, addr: u64, _mask: u64) -> u64 {
+        addr
+    }
+
+    /// Register index of the thread pointer and the offset from the thread pointer to
+    /// the DTV pointer in the thread control block. None if TLS isn't supported.
+    fn thread_pointer(&self) -> Option<(usize, i64)> {
+        None
+    }
+
+    /// Returns the syscall the thread was in, None if it wasn't in a syscall or syscalls
+    /// aren't supported for the architecture.
+    fn syscall(&self, _registers: &[u64]) -> Option<Syscall> {
+        None
+    }
+
+    /// Returns the name of a syscall number, None if it's not known.
+    fn syscall_name(&self, _number: u64) -> Option<&'static str> {
+        None
+    }
+
+    /// True if call_before is implemented for the architecture.
+    fn decodes_calls(&self) -> bool {
+        false
+    }
+
+    /// Checks whether the instruction ending at ret (code holds the bytes just before
+    /// ret) is a call, i.e. whether ret looks like a return address. None if it isn't a
+    /// call or decoding calls isn't supported for the architecture.
+    fn call_before(&self, _code: &[u8], _ret: u64) -> Option<CallSite> {
+        None
+    }
+
+    /// Used with --explain for info registers.
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)>;
+}
+
+/// Returns the architecture for ELF's e_machine and EI_CLASS fields or None if the
+/// combination isn't supported (e.g. x32 which is x86_64 with 32-bit pointers).
+pub fn find_arch(machine: u16, class: u8) -> Option<&'static dyn Arch> {
+    const CLASS32: u8 = 1;
+    const CLASS64: u8 = 2;
+    match (machine, class) {
+        (0x3E, CLASS64) => Some(&X86_64),
+        (0x03, CLASS32) => Some(&I386),
+        (0x08, CLASS32) => Some(&Mips),
+        (0x14, CLASS32) | (0x15, CLASS64) => Some(&PowerPc),
+        (0x28, CLASS32) => Some(&Arm),
+        (0xB7, CLASS64) => Some(&Aarch64),
+        _ => None,
+    }
+}
+
+/// How frames are linked together on the stack.
+pub enum FrameLayout {
+    /// The frame pointer points into a record with the caller's frame pointer and the
+    /// return address. Values are offsets from the frame pointer.
+    Record { caller_fp: i64, ret: i64 },
+
+    /// The frame pointer points to the caller's frame pointer and the return address is
+    /// saved at an offset within the caller's frame (as on PowerPC).
+    BackChain { ret: i64 },
+
+    /// Frames aren't linked so only the link register can be used.
+    None,
+}
+
+/// A call instruction found before a return address.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CallSite {
+    /// Call to a fixed address.
+    Direct { target: u64 },
+
+    /// Call through a register or memory, we don't know where it went.
+    Indirect,
+}
+
+/// A syscall a thread was blocked in (or making) when the core was written.
+pub struct Syscall {
+    pub number: u64,
+
+    /// None if we don't know the name.
+    pub name: Option<&'static str>,
+
+    /// Register values for the arguments, not all of these are used by every syscall.
+    pub args: [u64; 6],
+
+    /// Return register, a negative errno (like -ERESTARTSYS) if the syscall was
+    /// interrupted.
+    pub result: i64,
+}
+
+impl fmt::Display for Syscall {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(fmt, "{name}(")?,
+            None => write!(fmt, "syscall_{}(", self.number)?,
+        }
+        let args: Vec<String> = self.args.iter().map(|a| format!("0x{a:x}")).collect();
+        write!(fmt, "{})", args.join(", "))
+    }
+}
+
+/// Values used by the relocation calculations in the psABI documents. None if the
+/// value isn't known.
+pub struct RelocationInputs {
+    /// Value of the symbol (with the load bias applied).
+    pub s: Option<u64>,
+
+    /// The addend, None for REL relocations which store it at the place.
+    pub a: Option<i64>,
+
+    /// Address of the storage unit being relocated (with the load bias applied).
+    pub p: u64,
+
+    /// The load bias.
+    pub b: u64,
+
+    /// Size of the symbol.
+    pub z: u64,
+
+    /// Address of the global offset table.
+    pub got: Option<u64>,
+
+    /// Size of an address in bytes, used by relocations that write an address.
+    pub addr_size: u64,
+}
+
+#[derive(Debug)]
+pub enum RelocationType {
+    Aarch64(RelocationAarch64),
+    Arm(RelocationArm),
+    I386(RelocationI386),
+    Mips(RelocationMips),
+    PowerPc(RelocationPowerPc),
+    X86_64(RelocationX86_64),
+}
+
+impl RelocationType {
+    /// Returns the value the relocation writes, None if it can't be computed.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        match self {
+            RelocationType::Aarch64(r) => r.value(inputs),
+            RelocationType::Arm(r) => r.value(inputs),
+            RelocationType::I386(r) => r.value(inputs),
+            RelocationType::Mips(r) => r.value(inputs),
+            RelocationType::PowerPc(r) => r.value(inputs),
+            RelocationType::X86_64(r) => r.value(inputs),
+        }
+    }
+}
+
+/// Truncates a relocated value to the size of its field.
+fn truncate(value: u64, size: u64) -> u64 {
+    if size >= 8 {
+        value
+    } else {
+        value & ((1 << (8 * size)) - 1)
+    }
+}
+
+impl fmt::Display for RelocationType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelocationType::Aarch64(r) => write!(fmt, "{r:?}"),
+            RelocationType::Arm(r) => write!(fmt, "{r:?}"),
+            RelocationType::I386(r) => write!(fmt, "{r:?}"),
+            RelocationType::Mips(r) => write!(fmt, "{r:?}"),
+            RelocationType::PowerPc(r) => write!(fmt, "{r:?}"),
+            RelocationType::X86_64(r) => write!(fmt, "{r:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_arch;
+    use crate::commands::{backtrace, hexdump, info_registers};
+    use crate::repl::{FilterArgs, HexdumpArgs, HexdumpLabels, RegistersArgs};
+    use crate::utils::core_builder::ArchCore;
+    use std::io::Write;
+
+    fn arch_cores() -> Vec<ArchCore> {
+        vec![
+            ArchCore {
+                name: "arm",
+                machine: 0x28,
+                sixty_four_bit: false,
+                little_endian: true,
+                signal: 6,
+                code: 0,
+                pid: 7,
+                registers: ArchCore::registers(
+                    18,
+                    0x10,
+                    &[
+                        (11, 0xbeff000c), // fp
+                        (13, 0xbeff0000), // sp
+                        (14, 0x10200),    // lr
+                        (15, 0x10100),    // pc
+                        (16, 0x60000010), // cpsr
+                    ],
+                ),
+                notes: Vec::new(),
+                // Two frames, fp points at the saved lr and the caller's fp is just before it.
+                stack_addr: 0xbeff0000,
+                stack: vec![0, 0, 0xbeff0014, 0x10200, 0, 0x10300],
+            },
+            ArchCore {
+                name: "i386",
+                machine: 0x03,
+                sixty_four_bit: false,
+                little_endian: true,
+                signal: 11,
+                code: 1,
+                pid: 42,
+                registers: ArchCore::registers(
+                    17,
+                    0x10,
+                    &[
+                        (5, 0xbfff0008),  // ebp
+                        (12, 0x8048100),  // eip
+                        (15, 0xbfff0000), // esp
+                    ],
+                ),
+                notes: Vec::new(),
+                // Two frames, each is the saved ebp followed by the return address.
+                stack_addr: 0xbfff0000,
+                stack: vec![0, 0, 0xbfff0018, 0x8048200, 0, 0, 0, 0x8048300],
+            },
+            ArchCore {
+                name: "mips",
+                machine: 0x08,
+                sixty_four_bit: false,
+                little_endian: false,
+                signal: 10,
+                code: 1,
+                pid: 1234,
+                registers: ArchCore::registers(
+                    45,
+                    0x10,
+                    &[
+                        (35, 0x7fff0000), // sp
+                        (37, 0x400200),   // ra
+                        (40, 0x400100),   // pc
+                        (41, 0xdeadbeef), // badvaddr
+                    ],
+                ),
+                notes: Vec::new(),
+                stack_addr: 0x7fff0000,
+                stack: vec![0, 0],
+            },
+            ArchCore {
+                name: "powerpc",
+                machine: 0x14,
+                sixty_four_bit: false,
+                little_endian: false,
+                signal: 7,
+                code: 2,
+                pid: 99,
+                registers: ArchCore::registers(
+                    48,
+                    0x10,
+                    &[
+                        (1, 0x7fff0000),  // sp
+                        (32, 0x10000100), // pc
+                        (36, 0x10000200), // lr
+                    ],
+                ),
+                notes: Vec::new(),
+                // Three frames, each starts with the back chain and the LR save word.
+                stack_addr: 0x7fff0000,
+                stack: vec![
+                    0x7fff0010, 0, 0, 0, 0x7fff0020, 0x10000200, 0, 0, 0, 0x10000300,
+                ],
+            },
+            ArchCore {
+                name: "aarch64",
+                machine: 0xB7,
+                sixty_four_bit: true,
+                little_endian: true,
+                signal: 11,
+                code: 1,
+                pid: 42,
+                registers: ArchCore::registers(
+                    34,
+                    0x100,
+                    &[
+                        (29, 0x7fff0010),         // fp
+                        (30, 0x0055000000400200), // lr with PAC bits
+                        (31, 0x7fff0000),         // sp
+                        (32, 0x400100),           // pc
+                        (33, 0x60000000),         // pstate
+                    ],
+                ),
+                // NT_ARM_PAC_MASK
+                notes: vec![("LINUX", 0x406, vec![0x007f000000000000, 0x007f000000000000])],
+                // Two frame records, the return addresses are signed.
+                stack_addr: 0x7fff0000,
+                stack: vec![
+                    0,
+                    0,
+                    0x7fff0030,
+                    0x0023000000400200,
+                    0,
+                    0,
+                    0,
+                    0x0011000000400300,
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn arch_cores_snapshots() {
+        for arch in arch_cores() {
+            let files = arch.files();
+            let mut v: Vec<u8> = Vec::new();
+            backtrace(&mut v, &files);
+            writeln!(&mut v).unwrap();
+
+            let args = RegistersArgs {
+                all: true,
+                exe: false,
+                explain: false,
+                titles: true,
+                filter: FilterArgs::default(),
+            };
+            info_registers(&mut v, &files, &args);
+            writeln!(&mut v).unwrap();
+
+            let width = if arch.sixty_four_bit { 8 } else { 4 };
+            let args = HexdumpArgs {
+                exe: false,
+                count: arch.stack.len() * width,
+                labels: HexdumpLabels::Addr,
+                offset: false,
+                width,
+                value: arch.stack_addr,
+            };
+            hexdump(&mut v, &files, &args);
+
+            let s = String::from_utf8(v).unwrap();
+            let s = crate::utils::strip_escapes(&s);
+            insta::assert_snapshot!(arch.name, s);
+        }
+    }
+
+    #[test]
+    fn find_arch_checks_class() {
+        let name = |machine, class| find_arch(machine, class).map(|a| a.name());
+        assert_eq!(name(0x3E, 2), Some("x86_64"));
+        assert_eq!(name(0x3E, 1), None); // x32
+        assert_eq!(name(0x03, 1), Some("i386"));
+        assert_eq!(name(0x03, 2), None);
+        assert_eq!(name(0x15, 2), Some("powerpc"));
+        assert_eq!(name(0xB7, 1), None);
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Arch, FrameLayout, RelocationInputs, RelocationType, truncate};
+use std::error::Error;
+
+/// Both 32 and 64-bit PowerPC, these use the same register layout.
+pub struct PowerPc;
+
+// Indices into the registers as laid out in pt_regs, see
+// https://elixir.bootlin.com/linux/v6.6/source/arch/powerpc/include/uapi/asm/ptrace.h#L31
+const SP: usize = 1;
+const NIP: usize = 32;
+const LINK: usize = 36;
+
+impl Arch for PowerPc {
+    fn name(&self) -> &'static str {
+        "powerpc"
+    }
+
+    fn num_registers(&self) -> usize {
+        48 // ELF_NGREG
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        const NAMES: [&str; 32] = [
+            "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "r13",
+            "r14", "r15", "r16", "r17", "r18", "r19", "r20", "r21", "r22", "r23", "r24", "r25",
+            "r26", "r27", "r28", "r29", "r30", "r31",
+        ];
+        match index {
+            0..32 => NAMES[index],
+            32 => "pc",
+            33 => "msr",
+            35 => "ctr",
+            36 => "lr",
+            37 => "xer",
+            38 => "cr",
+            41 => "dar",
+            42 => "dsisr",
+            _ => "?", // orig_gpr3, softe, trap, result, and padding
+        }
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        matches!(index, 33 | 37 | 38 | 41 | 42)
+    }
+
+    fn ip_index(&self) -> usize {
+        NIP
+    }
+
+    fn sp_index(&self) -> usize {
+        SP
+    }
+
+    fn fp_index(&self) -> usize {
+        SP // r1 points to the back chain
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        Some(LINK)
+    }
+
+    fn frame_layout(&self, ptr_size: i64) -> FrameLayout {
+        // The LR save word is one word into the caller's frame for 32-bit and two
+        // doublewords in for 64-bit.
+        let ret = if ptr_size == 8 { 16 } else { 4 };
+        FrameLayout::BackChain { ret }
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        // see https://refspecs.linuxfoundation.org/ELF/ppc64/PPC-elf64abi.html#DW-REG
+        match dwarf {
+            0..=31 => Some(dwarf as usize),
+            65 => Some(LINK),
+            66 => Some(35), // ctr
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::PowerPc(RelocationPowerPc::from_u32(rtype)?))
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("pc", "points to the instruction currently being executed"),
+            (
+                "r1",
+                "the stack pointer, points to the back chain word for the caller's frame",
+            ),
+            (
+                "r2",
+                "the TOC pointer (64-bit) or the small data area (32-bit)",
+            ),
+            ("lr", "the link register, contains the return address"),
+        ]
+    }
+}
+
+// See https://refspecs.linuxfoundation.org/ELF/ppc64/PPC-elf64abi.html#RELOC-TYPE
+// Most of the numbers are shared between 32 and 64-bit.
+#[derive(Debug)]
+pub enum RelocationPowerPc {
+    // name        val     calculation
+    None,      // 0
+    Addr32,    // 1       S + A
+    Addr24,    // 2       (S + A) >> 2
+    Addr16Lo,  // 4       #lo(S + A)
+    Addr16Hi,  // 5       #hi(S + A)
+    Addr16Ha,  // 6       #ha(S + A)
+    Rel24,     // 10      (S + A - P) >> 2
+    Rel14,     // 11      (S + A - P) >> 2
+    Got16,     // 14      G
+    PltRel24,  // 18      (L + A - P) >> 2
+    Copy,      // 19
+    GlobDat,   // 20      S + A
+    JmpSlot,   // 21
+    Relative,  // 22      B + A
+    Rel32,     // 26      S + A - P
+    Addr64,    // 38      S + A
+    Rel64,     // 44      S + A - P
+    Toc16,     // 47      S + A - .TOC.
+    Toc,       // 51      .TOC.
+    DtpMod,    // 68
+    TpRel,     // 73
+    DtpRel,    // 78
+    IRelative, // 248
+}
+
+impl RelocationPowerPc {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationPowerPc::None),
+            1 => Ok(RelocationPowerPc::Addr32),
+            2 => Ok(RelocationPowerPc::Addr24),
+            4 => Ok(RelocationPowerPc::Addr16Lo),
+            5 => Ok(RelocationPowerPc::Addr16Hi),
+            6 => Ok(RelocationPowerPc::Addr16Ha),
+            10 => Ok(RelocationPowerPc::Rel24),
+            11 => Ok(RelocationPowerPc::Rel14),
+            14 => Ok(RelocationPowerPc::Got16),
+            18 => Ok(RelocationPowerPc::PltRel24),
+            19 => Ok(RelocationPowerPc::Copy),
+            20 => Ok(RelocationPowerPc::GlobDat),
+            21 => Ok(RelocationPowerPc::JmpSlot),
+            22 => Ok(RelocationPowerPc::Relative),
+            26 => Ok(RelocationPowerPc::Rel32),
+            38 => Ok(RelocationPowerPc::Addr64),
+            44 => Ok(RelocationPowerPc::Rel64),
+            47 => Ok(RelocationPowerPc::Toc16),
+            51 => Ok(RelocationPowerPc::Toc),
+            68 => Ok(RelocationPowerPc::DtpMod),
+            73 => Ok(RelocationPowerPc::TpRel),
+            78 => Ok(RelocationPowerPc::DtpRel),
+            248 => Ok(RelocationPowerPc::IRelative),
+            _ => Err(format!("bad powerpc relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes, truncated to the size of its field.
+    /// Relocations within instructions and those that need G, L, the TOC, or TLS
+    /// offsets return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let p = inputs.p;
+        let (value, size) = match self {
+            RelocationPowerPc::Addr32 => (inputs.s?.wrapping_add(a?), 4),
+            RelocationPowerPc::Addr64 => (inputs.s?.wrapping_add(a?), 8),
+            RelocationPowerPc::Addr16Lo => (inputs.s?.wrapping_add(a?), 2),
+            RelocationPowerPc::Addr16Hi => (inputs.s?.wrapping_add(a?) >> 16, 2),
+            RelocationPowerPc::Addr16Ha => {
+                (inputs.s?.wrapping_add(a?).wrapping_add(0x8000) >> 16, 2)
+            }
+            RelocationPowerPc::Rel32 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 4),
+            RelocationPowerPc::Rel64 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 8),
+            RelocationPowerPc::GlobDat => (inputs.s?.wrapping_add(a?), inputs.addr_size),
+            RelocationPowerPc::Relative => (inputs.b.wrapping_add(a?), inputs.addr_size),
+            _ => return None,
+        };
+        Some(truncate(value, size))
+    }
+}
@@ -0,0 +1,721 @@
+use super::{Arch, CallSite, RelocationInputs, RelocationType, Syscall, truncate};
+use std::error::Error;
+
+pub struct X86_64;
+
+// Indices into the registers as laid out in pt_regs, see
+// https://elixir.bootlin.com/linux/v4.9/source/arch/x86/include/uapi/asm/ptrace.h#L60
+const RBP: usize = 4;
+const ORIG_RAX: usize = 15;
+const RIP: usize = 16;
+const RSP: usize = 19;
+const FS_BASE: usize = 21;
+
+impl Arch for X86_64 {
+    fn name(&self) -> &'static str {
+        "x86_64"
+    }
+
+    fn num_registers(&self) -> usize {
+        27
+    }
+
+    fn register_name(&self, index: usize) -> &'static str {
+        match index {
+            0 => "r15",
+            1 => "r14",
+            2 => "r13",
+            3 => "r12",
+            4 => "rbp",
+            5 => "rbx",
+            6 => "r11",
+            7 => "r10",
+            8 => "r9",
+            9 => "r8",
+            10 => "rax",
+            11 => "rcx",
+            12 => "rdx",
+            13 => "rsi",
+            14 => "rdi",
+            16 => "rip",
+            17 => "cs",
+            18 => "eflags",
+            19 => "rsp",
+            20 => "ss",
+            FS_BASE => "fs_base",
+            22 => "gs_base",
+            23 => "ds",
+            24 => "es",
+            25 => "fs",
+            26 => "gs",
+            ORIG_RAX => "orig_rax",
+            _ => "?",
+        }
+    }
+
+    fn is_rare_register(&self, index: usize) -> bool {
+        matches!(index, ORIG_RAX | 17 | 18 | 20..=26)
+    }
+
+    fn ip_index(&self) -> usize {
+        RIP
+    }
+
+    fn sp_index(&self) -> usize {
+        RSP
+    }
+
+    fn fp_index(&self) -> usize {
+        RBP
+    }
+
+    fn lr_index(&self) -> Option<usize> {
+        None
+    }
+
+    fn thread_pointer(&self) -> Option<(usize, i64)> {
+        Some((FS_BASE, 8)) // tcbhead_t starts with tcb and then dtv
+    }
+
+    fn syscall(&self, registers: &[u64]) -> Option<Syscall> {
+        // orig_rax is -1 if the thread wasn't in a syscall.
+        let number = *registers.get(ORIG_RAX)?;
+        if number as i64 == -1 {
+            return None;
+        }
+        Some(Syscall {
+            number,
+            name: syscall_name(number),
+            args: [14, 13, 12, 7, 9, 8].map(|i| registers[i]), // rdi, rsi, rdx, r10, r8, r9
+            result: registers[10] as i64,                      // rax
+        })
+    }
+
+    fn syscall_name(&self, number: u64) -> Option<&'static str> {
+        syscall_name(number)
+    }
+
+    fn decodes_calls(&self) -> bool {
+        true
+    }
+
+    fn call_before(&self, code: &[u8], ret: u64) -> Option<CallSite> {
+        x86_call_before(code, ret)
+    }
+
+    fn dwarf_register(&self, dwarf: u16) -> Option<usize> {
+        // see figure 3.36 in https://refspecs.linuxbase.org/elf/x86_64-abi-0.99.pdf
+        match dwarf {
+            0 => Some(10), // rax
+            1 => Some(12), // rdx
+            2 => Some(11), // rcx
+            3 => Some(5),  // rbx
+            4 => Some(13), // rsi
+            5 => Some(14), // rdi
+            6 => Some(RBP),
+            7 => Some(RSP),
+            8 => Some(9), // r8
+            9 => Some(8), // r9
+            10 => Some(7),
+            11 => Some(6),
+            12 => Some(3),
+            13 => Some(2),
+            14 => Some(1),
+            15 => Some(0), // r15
+            16 => Some(RIP),
+            _ => None,
+        }
+    }
+
+    fn relocation_type(&self, rtype: u32) -> Result<RelocationType, Box<dyn Error>> {
+        Ok(RelocationType::X86_64(RelocationX86_64::from_u32(rtype)?))
+    }
+
+    fn explain_registers(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            (
+                "rip",
+                "points to the instruction pointer currently being executed",
+            ),
+            (
+                "rsp",
+                "points to the bottom of the stack, local variables appear after this",
+            ),
+            (
+                "rbp",
+                "points to the top of the stack (depending on compiler options)",
+            ),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub enum RelocationX86_64 {
+    // name        val  field   calculation
+    None,       // 0	None	None
+    SixtyFour,  // 1	qword	S + A
+    Pc32,       // 2	dword	S + A – P
+    Got32,      // 3	dword	G + A
+    Plt32,      // 4	dword	L + A – P
+    Copy,       // 5	None	Value is copied directly from shared object
+    GlobDat,    // 6	qword	S
+    JumpSlot,   // 7	qword	S
+    Relative,   // 8	qword	B + A
+    GotPcRel,   // 9	dword	G + GOT + A – P
+    ThirtyTwo,  // 10	dword	S + A
+    ThirtyTwoS, // 11	dword	S + A
+    Sixteen,    // 12	word	S + A
+    Pc16,       // 13	word	S + A – P
+    Eight,      // 14	word8	S + A
+    Pc8,        // 15	word8	S + A – P
+    Pc64,       // 24	qword	S + A – P
+    GoTOoff64,  // 25	qword	S + A – GOT
+    GotPc32,    // 26	dword	GOT + A – P
+    Size32,     // 32	dword	Z + A
+    Size64,     // 33	qword	Z + A
+}
+
+impl RelocationX86_64 {
+    fn from_u32(rtype: u32) -> Result<Self, Box<dyn Error>> {
+        match rtype {
+            0 => Ok(RelocationX86_64::None),
+            1 => Ok(RelocationX86_64::SixtyFour),
+            2 => Ok(RelocationX86_64::Pc32),
+            3 => Ok(RelocationX86_64::Got32),
+            4 => Ok(RelocationX86_64::Plt32),
+            5 => Ok(RelocationX86_64::Copy),
+            6 => Ok(RelocationX86_64::GlobDat),
+            7 => Ok(RelocationX86_64::JumpSlot),
+            8 => Ok(RelocationX86_64::Relative),
+            9 => Ok(RelocationX86_64::GotPcRel),
+            10 => Ok(RelocationX86_64::ThirtyTwo),
+            11 => Ok(RelocationX86_64::ThirtyTwoS),
+            12 => Ok(RelocationX86_64::Sixteen),
+            13 => Ok(RelocationX86_64::Pc16),
+            14 => Ok(RelocationX86_64::Eight),
+            15 => Ok(RelocationX86_64::Pc8),
+            24 => Ok(RelocationX86_64::Pc64),
+            25 => Ok(RelocationX86_64::GoTOoff64),
+            26 => Ok(RelocationX86_64::GotPc32),
+            32 => Ok(RelocationX86_64::Size32),
+            33 => Ok(RelocationX86_64::Size64),
+            _ => Err(format!("bad x86 64 relocation type: {rtype}").into()),
+        }
+    }
+
+    /// Returns the value the relocation writes, truncated to the size of its field.
+    /// G and L are not known so GOT and PLT entry relocations return None.
+    pub fn value(&self, inputs: &RelocationInputs) -> Option<u64> {
+        let a = inputs.a.map(|a| a as u64);
+        let p = inputs.p;
+        let (value, size) = match self {
+            RelocationX86_64::SixtyFour => (inputs.s?.wrapping_add(a?), 8),
+            RelocationX86_64::Pc32 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 4),
+            RelocationX86_64::GlobDat | RelocationX86_64::JumpSlot => (inputs.s?, 8),
+            RelocationX86_64::Relative => (inputs.b.wrapping_add(a?), 8),
+            RelocationX86_64::ThirtyTwo | RelocationX86_64::ThirtyTwoS => {
+                (inputs.s?.wrapping_add(a?), 4)
+            }
+            RelocationX86_64::Sixteen => (inputs.s?.wrapping_add(a?), 2),
+            RelocationX86_64::Pc16 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 2),
+            RelocationX86_64::Eight => (inputs.s?.wrapping_add(a?), 1),
+            RelocationX86_64::Pc8 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 1),
+            RelocationX86_64::Pc64 => (inputs.s?.wrapping_add(a?).wrapping_sub(p), 8),
+            RelocationX86_64::GoTOoff64 => {
+                (inputs.s?.wrapping_add(a?).wrapping_sub(inputs.got?), 8)
+            }
+            RelocationX86_64::GotPc32 => (inputs.got?.wrapping_add(a?).wrapping_sub(p), 4),
+            RelocationX86_64::Size32 => (inputs.z.wrapping_add(a?), 4),
+            RelocationX86_64::Size64 => (inputs.z.wrapping_add(a?), 8),
+            RelocationX86_64::None
+            | RelocationX86_64::Got32
+            | RelocationX86_64::Plt32
+            | RelocationX86_64::Copy
+            | RelocationX86_64::GotPcRel => return None,
+        };
+        Some(truncate(value, size))
+    }
+}
+
+/// Decodes the call instructions that could end just before ret: E8 rel32 and the FF /2
+/// forms (with an optional REX prefix which doesn't change the length of the rest).
+/// Also used for i386.
+pub fn x86_call_before(code: &[u8], ret: u64) -> Option<CallSite> {
+    let n = code.len();
+    if n >= 5 && code[n - 5] == 0xe8 {
+        let rel = i32::from_le_bytes(code[n - 4..].try_into().unwrap());
+        return Some(CallSite::Direct {
+            target: ret.wrapping_add(rel as i64 as u64),
+        });
+    }
+    for len in [2, 3, 4, 6, 7] {
+        if n < len || code[n - len] != 0xff {
+            continue;
+        }
+        let modrm = code[n - len + 1];
+        let sib = code.get(n - len + 2).copied().unwrap_or(0);
+        if (modrm >> 3) & 7 == 2 && indirect_call_len(modrm, sib) == len {
+            return Some(CallSite::Indirect);
+        }
+    }
+    None
+}
+
+/// Length of an FF /2 call given its ModRM and (possible) SIB bytes.
+fn indirect_call_len(modrm: u8, sib: u8) -> usize {
+    let mode = modrm >> 6;
+    let rm = modrm & 7;
+    if mode == 3 {
+        return 2; // call reg
+    }
+    let sib_len = if rm == 4 { 1 } else { 0 };
+    let disp_len = match mode {
+        0 if rm == 5 => 4,                 // rip relative
+        0 if rm == 4 && sib & 7 == 5 => 4, // SIB with no base
+        0 => 0,
+        1 => 1,
+        _ => 4,
+    };
+    2 + sib_len + disp_len
+}
+
+/// Returns the name of an x86_64 syscall, see arch/x86/entry/syscalls/syscall_64.tbl in
+/// the kernel.
+fn syscall_name(number: u64) -> Option<&'static str> {
+    let name = match number {
+        0 => "read",
+        1 => "write",
+        2 => "open",
+        3 => "close",
+        4 => "stat",
+        5 => "fstat",
+        6 => "lstat",
+        7 => "poll",
+        8 => "lseek",
+        9 => "mmap",
+        10 => "mprotect",
+        11 => "munmap",
+        12 => "brk",
+        13 => "rt_sigaction",
+        14 => "rt_sigprocmask",
+        15 => "rt_sigreturn",
+        16 => "ioctl",
+        17 => "pread64",
+        18 => "pwrite64",
+        19 => "readv",
+        20 => "writev",
+        21 => "access",
+        22 => "pipe",
+        23 => "select",
+        24 => "sched_yield",
+        25 => "mremap",
+        26 => "msync",
+        27 => "mincore",
+        28 => "madvise",
+        29 => "shmget",
+        30 => "shmat",
+        31 => "shmctl",
+        32 => "dup",
+        33 => "dup2",
+        34 => "pause",
+        35 => "nanosleep",
+        36 => "getitimer",
+        37 => "alarm",
+        38 => "setitimer",
+        39 => "getpid",
+        40 => "sendfile",
+        41 => "socket",
+        42 => "connect",
+        43 => "accept",
+        44 => "sendto",
+        45 => "recvfrom",
+        46 => "sendmsg",
+        47 => "recvmsg",
+        48 => "shutdown",
+        49 => "bind",
+        50 => "listen",
+        51 => "getsockname",
+        52 => "getpeername",
+        53 => "socketpair",
+        54 => "setsockopt",
+        55 => "getsockopt",
+        56 => "clone",
+        57 => "fork",
+        58 => "vfork",
+        59 => "execve",
+        60 => "exit",
+        61 => "wait4",
+        62 => "kill",
+        63 => "uname",
+        64 => "semget",
+        65 => "semop",
+        66 => "semctl",
+        67 => "shmdt",
+        68 => "msgget",
+        69 => "msgsnd",
+        70 => "msgrcv",
+        71 => "msgctl",
+        72 => "fcntl",
+        73 => "flock",
+        74 => "fsync",
+        75 => "fdatasync",
+        76 => "truncate",
+        77 => "ftruncate",
+        78 => "getdents",
+        79 => "getcwd",
+        80 => "chdir",
+        81 => "fchdir",
+        82 => "rename",
+        83 => "mkdir",
+        84 => "rmdir",
+        85 => "creat",
+        86 => "link",
+        87 => "unlink",
+        88 => "symlink",
+        89 => "readlink",
+        90 => "chmod",
+        91 => "fchmod",
+        92 => "chown",
+        93 => "fchown",
+        94 => "lchown",
+        95 => "umask",
+        96 => "gettimeofday",
+        97 => "getrlimit",
+        98 => "getrusage",
+        99 => "sysinfo",
+        100 => "times",
+        101 => "ptrace",
+        102 => "getuid",
+        103 => "syslog",
+        104 => "getgid",
+        105 => "setuid",
+        106 => "setgid",
+        107 => "geteuid",
+        108 => "getegid",
+        109 => "setpgid",
+        110 => "getppid",
+        111 => "getpgrp",
+        112 => "setsid",
+        113 => "setreuid",
+        114 => "setregid",
+        115 => "getgroups",
+        116 => "setgroups",
+        117 => "setresuid",
+        118 => "getresuid",
+        119 => "setresgid",
+        120 => "getresgid",
+        121 => "getpgid",
+        122 => "setfsuid",
+        123 => "setfsgid",
+        124 => "getsid",
+        125 => "capget",
+        126 => "capset",
+        127 => "rt_sigpending",
+        128 => "rt_sigtimedwait",
+        129 => "rt_sigqueueinfo",
+        130 => "rt_sigsuspend",
+        131 => "sigaltstack",
+        132 => "utime",
+        133 => "mknod",
+        134 => "uselib",
+        135 => "personality",
+        136 => "ustat",
+        137 => "statfs",
+        138 => "fstatfs",
+        139 => "sysfs",
+        140 => "getpriority",
+        141 => "setpriority",
+        142 => "sched_setparam",
+        143 => "sched_getparam",
+        144 => "sched_setscheduler",
+        145 => "sched_getscheduler",
+        146 => "sched_get_priority_max",
+        147 => "sched_get_priority_min",
+        148 => "sched_rr_get_interval",
+        149 => "mlock",
+        150 => "munlock",
+        151 => "mlockall",
+        152 => "munlockall",
+        153 => "vhangup",
+        154 => "modify_ldt",
+        155 => "pivot_root",
+        156 => "_sysctl",
+        157 => "prctl",
+        158 => "arch_prctl",
+        159 => "adjtimex",
+        160 => "setrlimit",
+        161 => "chroot",
+        162 => "sync",
+        163 => "acct",
+        164 => "settimeofday",
+        165 => "mount",
+        166 => "umount2",
+        167 => "swapon",
+        168 => "swapoff",
+        169 => "reboot",
+        170 => "sethostname",
+        171 => "setdomainname",
+        172 => "iopl",
+        173 => "ioperm",
+        174 => "create_module",
+        175 => "init_module",
+        176 => "delete_module",
+        177 => "get_kernel_syms",
+        178 => "query_module",
+        179 => "quotactl",
+        180 => "nfsservctl",
+        181 => "getpmsg",
+        182 => "putpmsg",
+        183 => "afs_syscall",
+        184 => "tuxcall",
+        185 => "security",
+        186 => "gettid",
+        187 => "readahead",
+        188 => "setxattr",
+        189 => "lsetxattr",
+        190 => "fsetxattr",
+        191 => "getxattr",
+        192 => "lgetxattr",
+        193 => "fgetxattr",
+        194 => "listxattr",
+        195 => "llistxattr",
+        196 => "flistxattr",
+        197 => "removexattr",
+        198 => "lremovexattr",
+        199 => "fremovexattr",
+        200 => "tkill",
+        201 => "time",
+        202 => "futex",
+        203 => "sched_setaffinity",
+        204 => "sched_getaffinity",
+        205 => "set_thread_area",
+        206 => "io_setup",
+        207 => "io_destroy",
+        208 => "io_getevents",
+        209 => "io_submit",
+        210 => "io_cancel",
+        211 => "get_thread_area",
+        212 => "lookup_dcookie",
+        213 => "epoll_create",
+        214 => "epoll_ctl_old",
+        215 => "epoll_wait_old",
+        216 => "remap_file_pages",
+        217 => "getdents64",
+        218 => "set_tid_address",
+        219 => "restart_syscall",
+        220 => "semtimedop",
+        221 => "fadvise64",
+        222 => "timer_create",
+        223 => "timer_settime",
+        224 => "timer_gettime",
+        225 => "timer_getoverrun",
+        226 => "timer_delete",
+        227 => "clock_settime",
+        228 => "clock_gettime",
+        229 => "clock_getres",
+        230 => "clock_nanosleep",
+        231 => "exit_group",
+        232 => "epoll_wait",
+        233 => "epoll_ctl",
+        234 => "tgkill",
+        235 => "utimes",
+        236 => "vserver",
+        237 => "mbind",
+        238 => "set_mempolicy",
+        239 => "get_mempolicy",
+        240 => "mq_open",
+        241 => "mq_unlink",
+        242 => "mq_timedsend",
+        243 => "mq_timedreceive",
+        244 => "mq_notify",
+        245 => "mq_getsetattr",
+        246 => "kexec_load",
+        247 => "waitid",
+        248 => "add_key",
+        249 => "request_key",
+        250 => "keyctl",
+        251 => "ioprio_set",
+        252 => "ioprio_get",
+        253 => "inotify_init",
+        254 => "inotify_add_watch",
+        255 => "inotify_rm_watch",
+        256 => "migrate_pages",
+        257 => "openat",
+        258 => "mkdirat",
+        259 => "mknodat",
+        260 => "fchownat",
+        261 => "futimesat",
+        262 => "newfstatat",
+        263 => "unlinkat",
+        264 => "renameat",
+        265 => "linkat",
+        266 => "symlinkat",
+        267 => "readlinkat",
+        268 => "fchmodat",
+        269 => "faccessat",
+        270 => "pselect6",
+        271 => "ppoll",
+        272 => "unshare",
+        273 => "set_robust_list",
+        274 => "get_robust_list",
+        275 => "splice",
+        276 => "tee",
+        277 => "sync_file_range",
+        278 => "vmsplice",
+        279 => "move_pages",
+        280 => "utimensat",
+        281 => "epoll_pwait",
+        282 => "signalfd",
+        283 => "timerfd_create",
+        284 => "eventfd",
+        285 => "fallocate",
+        286 => "timerfd_settime",
+        287 => "timerfd_gettime",
+        288 => "accept4",
+        289 => "signalfd4",
+        290 => "eventfd2",
+        291 => "epoll_create1",
+        292 => "dup3",
+        293 => "pipe2",
+        294 => "inotify_init1",
+        295 => "preadv",
+        296 => "pwritev",
+        297 => "rt_tgsigqueueinfo",
+        298 => "perf_event_open",
+        299 => "recvmmsg",
+        300 => "fanotify_init",
+        301 => "fanotify_mark",
+        302 => "prlimit64",
+        303 => "name_to_handle_at",
+        304 => "open_by_handle_at",
+        305 => "clock_adjtime",
+        306 => "syncfs",
+        307 => "sendmmsg",
+        308 => "setns",
+        309 => "getcpu",
+        310 => "process_vm_readv",
+        311 => "process_vm_writev",
+        312 => "kcmp",
+        313 => "finit_module",
+        314 => "sched_setattr",
+        315 => "sched_getattr",
+        316 => "renameat2",
+        317 => "seccomp",
+        318 => "getrandom",
+        319 => "memfd_create",
+        320 => "kexec_file_load",
+        321 => "bpf",
+        322 => "execveat",
+        323 => "userfaultfd",
+        324 => "membarrier",
+        325 => "mlock2",
+        326 => "copy_file_range",
+        327 => "preadv2",
+        328 => "pwritev2",
+        329 => "pkey_mprotect",
+        330 => "pkey_alloc",
+        331 => "pkey_free",
+        332 => "statx",
+        333 => "io_pgetevents",
+        334 => "rseq",
+        424 => "pidfd_send_signal",
+        425 => "io_uring_setup",
+        426 => "io_uring_enter",
+        427 => "io_uring_register",
+        428 => "open_tree",
+        429 => "move_mount",
+        430 => "fsopen",
+        431 => "fsconfig",
+        432 => "fsmount",
+        433 => "fspick",
+        434 => "pidfd_open",
+        435 => "clone3",
+        436 => "close_range",
+        437 => "openat2",
+        438 => "pidfd_getfd",
+        439 => "faccessat2",
+        440 => "process_madvise",
+        441 => "epoll_pwait2",
+        442 => "mount_setattr",
+        443 => "quotactl_fd",
+        444 => "landlock_create_ruleset",
+        445 => "landlock_add_rule",
+        446 => "landlock_restrict_self",
+        447 => "memfd_secret",
+        448 => "process_mrelease",
+        449 => "futex_waitv",
+        450 => "set_mempolicy_home_node",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocation_values() {
+        let inputs = RelocationInputs {
+            s: Some(0x7f00_0000_1000),
+            a: Some(-4),
+            p: 0x5555_0000_2000,
+            b: 0x5555_0000_0000,
+            z: 0x18,
+            got: None,
+            addr_size: 8,
+        };
+        let value = |r: RelocationX86_64| r.value(&inputs);
+        assert_eq!(value(RelocationX86_64::SixtyFour), Some(0x7f00_0000_0ffc));
+        assert_eq!(value(RelocationX86_64::Pc32), Some(0xffff_effc));
+        assert_eq!(value(RelocationX86_64::JumpSlot), Some(0x7f00_0000_1000));
+        assert_eq!(value(RelocationX86_64::Relative), Some(0x5554_ffff_fffc));
+        assert_eq!(value(RelocationX86_64::Size32), Some(0x14));
+        assert_eq!(value(RelocationX86_64::GotPc32), None);
+        assert_eq!(value(RelocationX86_64::Plt32), None);
+    }
+
+    #[test]
+    fn call_before() {
+        // call 0x1000 from 0x2000
+        let rel = (0x1000i32 - 0x2005i32).to_le_bytes();
+        let code = [0x90, 0xe8, rel[0], rel[1], rel[2], rel[3]];
+        assert_eq!(
+            x86_call_before(&code, 0x2005),
+            Some(CallSite::Direct { target: 0x1000 })
+        );
+
+        let calls: [&[u8]; 5] = [
+            &[0x90, 0xff, 0xd0],                         // call rax
+            &[0x41, 0xff, 0xd4],                         // call r12
+            &[0xff, 0x50, 0x08],                         // call [rax+8]
+            &[0xff, 0x15, 0x10, 0x20, 0x00, 0x00],       // call [rip+0x2010]
+            &[0xff, 0x14, 0x25, 0x00, 0x10, 0x00, 0x00], // call [0x1000]
+        ];
+        for code in calls {
+            assert_eq!(x86_call_before(code, 0x2000), Some(CallSite::Indirect));
+        }
+
+        assert_eq!(x86_call_before(&[0x48, 0x89, 0xe5], 0x2000), None); // mov rbp, rsp
+        assert_eq!(x86_call_before(&[0x90, 0xff, 0xe0], 0x2000), None); // jmp rax
+    }
+
+    #[test]
+    fn syscalls() {
+        let mut registers = vec![0; X86_64.num_registers()];
+        registers[ORIG_RAX] = u64::MAX; // not in a syscall
+        assert!(X86_64.syscall(&registers).is_none());
+
+        registers[ORIG_RAX] = 202;
+        registers[14] = 0x7f00_0000_1000; // rdi
+        registers[13] = 0x80; // rsi, FUTEX_WAIT_PRIVATE
+        registers[10] = -512i64 as u64; // rax, ERESTARTSYS
+        let syscall = X86_64.syscall(&registers).unwrap();
+        assert_eq!(syscall.name, Some("futex"));
+        assert_eq!(syscall.result, -512);
+        assert_eq!(
+            syscall.to_string(),
+            "futex(0x7f0000001000, 0x80, 0x0, 0x0, 0x0, 0x0)"
+        );
+    }
+}
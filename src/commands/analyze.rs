@@ -0,0 +1,366 @@
+use super::tables::add_simple;
+use crate::commands::tables::SimpleTableBuilder;
+use crate::commands::{find_variable, raw_frames};
+use crate::elf::{
+    EXECUTE_FLAG, ElfFile, ElfFiles, MemoryRegion, PrStatus, READ_FLAG, RegionKind, SignalDetails,
+    VirtualAddr, WRITE_FLAG,
+};
+use crate::repl::AnalyzeArgs;
+use crate::utils::{Styling, uwriteln};
+use std::fmt;
+use std::io::Write;
+
+/// Linux doesn't allow mappings below vm.mmap_min_addr (64K by default) so faults below
+/// this are almost certainly from NULL pointers.
+const NULL_PAGES: u64 = 0x10000;
+
+/// The kernel keeps a gap of 256 pages (stack_guard_gap) below stacks that grow down.
+const STACK_GUARD_GAP: u64 = 256 * 4096;
+
+/// Faults this close to a thread's stack pointer are assumed to be stack accesses.
+const NEAR_SP: u64 = 0x10000;
+
+const SIGABRT: i32 = 6;
+const SIGBUS: i32 = 7;
+const SIGSEGV: i32 = 11;
+
+/// Why the process crashed.
+pub enum CrashCause {
+    /// Load or store using a NULL pointer, offset is usually the offset of a field.
+    NullDeref { offset: u64 },
+
+    /// A thread ran off the end of its stack.
+    StackOverflow { tid: i32 },
+
+    /// Store to memory that is mapped but isn't writeable.
+    ReadOnlyWrite,
+
+    /// Jump to memory that is mapped but isn't executable.
+    NonExecutable,
+
+    /// Jump to an address that isn't mapped.
+    UnmappedJump { addr: u64 },
+
+    /// Load or store using an address that isn't mapped.
+    UnmappedAccess,
+
+    /// SIGABRT from an assert.
+    Assert,
+
+    /// SIGABRT from a call to abort.
+    Abort,
+
+    /// Couldn't figure out what happened.
+    Unknown,
+}
+
+impl CrashCause {
+    /// Plain English explanation used with --explain.
+    fn explain(&self) -> &'static str {
+        match self {
+            CrashCause::NullDeref { .. } => {
+                "The code read or wrote memory using a NULL pointer. The fault address is the \
+                 offset from NULL so it's typically the offset of the struct field that was \
+                 accessed. Check where the pointer came from, e.g. a failed lookup or an \
+                 uninitialized variable."
+            }
+            CrashCause::StackOverflow { .. } => {
+                "The thread used all of its stack and hit the guard area below it. This is \
+                 usually caused by unbounded recursion or very large local variables (e.g. \
+                 big arrays on the stack). The backtrace will often show the same functions \
+                 repeated."
+            }
+            CrashCause::ReadOnlyWrite => {
+                "The code wrote to memory that is mapped read-only, e.g. a string literal, \
+                 const data, or code. This is often a cast that discards const or a write \
+                 through a stale pointer that now points into a read-only mapping."
+            }
+            CrashCause::NonExecutable => {
+                "The instruction pointer is in memory that isn't executable, e.g. the heap or \
+                 the stack. This usually means a function pointer or return address was \
+                 overwritten with a pointer to data."
+            }
+            CrashCause::UnmappedJump { .. } => {
+                "The instruction pointer is at an address that isn't mapped. A function \
+                 pointer was garbage (or NULL) or a return address on the stack was \
+                 overwritten, e.g. by a buffer overflow. The backtrace may be unreliable."
+            }
+            CrashCause::UnmappedAccess => {
+                "The code read or wrote an address that isn't mapped. This is a wild \
+                 pointer: uninitialized, already freed and unmapped, or computed from a \
+                 corrupted value."
+            }
+            CrashCause::Assert => {
+                "An assert failed. The assert message was written to stderr before abort \
+                 was called, the caller of __assert_fail has the failing check."
+            }
+            CrashCause::Abort => {
+                "The process called abort. Libraries do this when they detect a fatal \
+                 problem, e.g. glibc's malloc aborts when it finds heap corruption and C++ \
+                 calls abort for uncaught exceptions."
+            }
+            CrashCause::Unknown => {
+                "The crash doesn't match any of the patterns we look for, use the signal, \
+                 fault address, and backtrace to see what happened."
+            }
+        }
+    }
+}
+
+impl fmt::Display for CrashCause {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrashCause::NullDeref { offset } => {
+                write!(fmt, "NULL pointer dereference (offset 0x{offset:x})")
+            }
+            CrashCause::StackOverflow { tid } => write!(fmt, "stack overflow in thread {tid}"),
+            CrashCause::ReadOnlyWrite => write!(fmt, "write to read-only memory"),
+            CrashCause::NonExecutable => write!(fmt, "execution of non-executable memory"),
+            CrashCause::UnmappedJump { addr } if *addr < NULL_PAGES => {
+                write!(fmt, "call through a NULL function pointer")
+            }
+            CrashCause::UnmappedJump { .. } => write!(
+                fmt,
+                "jump to an unmapped address (corrupted return address or function pointer)"
+            ),
+            CrashCause::UnmappedAccess => write!(fmt, "access of an unmapped address"),
+            CrashCause::Assert => write!(fmt, "assert failed"),
+            CrashCause::Abort => write!(fmt, "abort was called"),
+            CrashCause::Unknown => write!(fmt, "unknown"),
+        }
+    }
+}
+
+pub fn analyze(mut out: impl Write, files: &ElfFiles, args: &AnalyzeArgs) {
+    let Some(core) = files.core() else {
+        uwriteln!(out, "Need a core to analyze");
+        return;
+    };
+    let Some(status) = core.find_prstatus() else {
+        uwriteln!(out, "Couldn't find prstatus");
+        return;
+    };
+    let info = core.find_signal_info();
+    let regions = files.regions(core);
+    let ip = status.get_ip();
+    let fault_addr = match info.as_ref().map(|i| &i.details) {
+        Some(SignalDetails::Fault(details)) => Some(details.fault_addr),
+        _ => None,
+    };
+
+    let cause = match (status.signal_num, fault_addr) {
+        (SIGABRT, _) => abort_cause(files, core, &status),
+        (SIGSEGV | SIGBUS, Some(addr)) => fault_cause(core, &regions, ip.0, addr),
+        _ => CrashCause::Unknown,
+    };
+    uwriteln!(out, "{cause}");
+
+    let mut b = SimpleTableBuilder::new();
+    let signal = match &info {
+        Some(info) => info.signal(),
+        None => status.signal().to_string(),
+    };
+    add_simple!(b, "signal", signal, "the signal that killed the process");
+    add_simple!(b, "thread", status.pid, "the thread that crashed");
+    add_simple!(
+        b,
+        "ip",
+        describe(files, core, ip.0),
+        "the instruction that crashed"
+    );
+    if let Some(addr) = fault_addr {
+        add_simple!(
+            b,
+            "fault addr",
+            describe(files, core, addr),
+            "the address the instruction tried to use"
+        );
+        let region = match find_region(&regions, addr) {
+            Some(r) => format!("0x{:x} {} {}", r.vbytes.start.0, r.flags(), r.kind),
+            None => "not mapped".to_string(),
+        };
+        add_simple!(
+            b,
+            "fault region",
+            region,
+            "start, permissions, and kind of the memory the fault address is within"
+        );
+    }
+    b.writeln(&mut out, args.explain);
+
+    if args.explain {
+        uwriteln!(out);
+        uwriteln!(out, "{}", cause.explain().explain_text());
+    }
+}
+
+fn describe(files: &ElfFiles, core: &ElfFile, addr: u64) -> String {
+    match files.describe_addr(core, VirtualAddr(addr)) {
+        Some(name) => format!("0x{addr:x} {name}"),
+        None => format!("0x{addr:x}"),
+    }
+}
+
+fn find_region(regions: &[MemoryRegion], addr: u64) -> Option<&MemoryRegion> {
+    regions
+        .iter()
+        .find(|r| r.vbytes.contains(VirtualAddr(addr)))
+}
+
+fn fault_cause(core: &ElfFile, regions: &[MemoryRegion], ip: u64, addr: u64) -> CrashCause {
+    // Mappings with no permissions, like guard pages, are as good as unmapped.
+    let region = find_region(regions, addr).filter(|r| r.flags != 0);
+    if ip == addr {
+        // The fault happened fetching the instruction.
+        return match region {
+            Some(r) if r.flags & EXECUTE_FLAG == 0 => CrashCause::NonExecutable,
+            Some(_) => CrashCause::Unknown,
+            None => CrashCause::UnmappedJump { addr },
+        };
+    }
+    if region.is_none() && addr < NULL_PAGES {
+        return CrashCause::NullDeref { offset: addr };
+    }
+    if region.is_none()
+        && let Some(tid) = overflowed_thread(core, regions, addr)
+    {
+        return CrashCause::StackOverflow { tid };
+    }
+    match region {
+        Some(r) if r.flags & READ_FLAG != 0 && r.flags & WRITE_FLAG == 0 => {
+            CrashCause::ReadOnlyWrite // a read would have worked so it must've been a write
+        }
+        Some(_) => CrashCause::Unknown,
+        None => CrashCause::UnmappedAccess,
+    }
+}
+
+/// Returns the thread whose stack addr is just below (or whose stack pointer is near).
+fn overflowed_thread(core: &ElfFile, regions: &[MemoryRegion], addr: u64) -> Option<i32> {
+    for r in regions {
+        if let RegionKind::Stack(tid) = r.kind
+            && addr < r.vbytes.start.0
+            && r.vbytes.start.0 - addr <= STACK_GUARD_GAP
+        {
+            return Some(tid);
+        }
+    }
+
+    // If the stack pointer is also in the guard area the stack region won't be known.
+    core.find_prstatuses()
+        .iter()
+        .find(|s| s.get_frame_stack_bottom().0.abs_diff(addr) <= NEAR_SP)
+        .map(|s| s.pid)
+}
+
+fn abort_cause(files: &ElfFiles, core: &ElfFile, status: &PrStatus) -> CrashCause {
+    let ips: Vec<u64> = match raw_frames(core, status) {
+        Ok(frames) => frames.iter().map(|f| f.ip.0).collect(),
+        Err(_) => vec![status.get_ip().0],
+    };
+    let within = |name: &str| {
+        let Some((symbol, bias)) = find_variable(files, core, name) else {
+            return false;
+        };
+        let start = symbol.value.wrapping_add(bias);
+        ips.iter()
+            .any(|ip| *ip >= start && *ip < start + symbol.size.max(1))
+    };
+    if within("__assert_fail") || within("__assert_perror_fail") {
+        CrashCause::Assert
+    } else if within("abort") {
+        CrashCause::Abort
+    } else {
+        CrashCause::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::find_regions;
+    use crate::utils::core_builder::{CoreBuilder, ObjectBuilder};
+    use crate::utils::{debug_results, do_test, release_results};
+
+    #[test]
+    fn null_deref() {
+        let args = AnalyzeArgs { explain: true };
+        do_test!(analyze, &args);
+    }
+
+    #[test]
+    fn fault_causes() {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[19] = 0x7ff100; // rsp
+        builder.prstatus(11, 1, 100, &regs);
+        builder.load(0x400000, vec![0; 0x1000], 5);
+        builder.load(0x500000, vec![0; 0x1000], 6);
+        builder.load(0x600000, vec![0; 0x1000], 4);
+        builder.load(0x7ff000, vec![0; 0x1000], 6);
+        let core = ElfFile::new(builder.write("analyze")).unwrap();
+        let regions = find_regions(&core, None);
+
+        let cause = |ip, addr| fault_cause(&core, &regions, ip, addr).to_string();
+        assert_eq!(
+            cause(0x400100, 0x18),
+            "NULL pointer dereference (offset 0x18)"
+        );
+        assert_eq!(cause(0x400100, 0x7fe800), "stack overflow in thread 100");
+        assert_eq!(cause(0x400100, 0x600010), "write to read-only memory");
+        assert_eq!(
+            cause(0x500010, 0x500010),
+            "execution of non-executable memory"
+        );
+        assert_eq!(cause(0, 0), "call through a NULL function pointer");
+        assert!(cause(0xdead0000, 0xdead0000).starts_with("jump to an unmapped address"));
+        assert_eq!(cause(0x400100, 0xdead0000), "access of an unmapped address");
+    }
+
+    /// Returns the cause of a SIGABRT in abort which was called from the functions in
+    /// callers.
+    fn abort_core(name: &str, callers: &[&str]) -> String {
+        const EXE: u64 = 0x400000;
+        const STACK: u64 = 0x7ff000;
+        let exe = ObjectBuilder::new(0)
+            .function("main", 0x20)
+            .function("abort", 0x40)
+            .function("__assert_fail", 0x40)
+            .write(&format!("{name}-exe"));
+        let file = ElfFile::new(exe.clone()).unwrap();
+        let within = |name| EXE + file.find_symbol(name).unwrap().value + 4;
+
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[4] = STACK; // rbp
+        regs[16] = within("abort"); // rip
+        regs[19] = STACK; // rsp
+        builder.prstatus(6, 0, 100, &regs);
+
+        // Frame records are the caller's rbp followed by the return address.
+        let mut e = builder.encoder();
+        for (i, caller) in callers.iter().enumerate() {
+            e.xword(STACK + 16 * (i as u64 + 1)).xword(within(caller));
+        }
+        e.xword(0).xword(0);
+        builder.load(STACK, e.bytes, 6);
+
+        let exe_name = exe.to_string_lossy().to_string();
+        builder.mapped_files(&[(EXE, EXE + 0x1000, 0, &exe_name)]);
+        builder.load(EXE, std::fs::read(&exe).unwrap(), 5);
+        let files = ElfFiles::new(vec![builder.write(name), exe]).unwrap();
+
+        let core = files.core().unwrap();
+        let status = core.find_prstatus().unwrap();
+        abort_cause(&files, core, &status).to_string()
+    }
+
+    #[test]
+    fn abort_causes() {
+        assert_eq!(
+            abort_core("assert", &["__assert_fail", "main"]),
+            "assert failed"
+        );
+        assert_eq!(abort_core("abort", &["main"]), "abort was called");
+    }
+}
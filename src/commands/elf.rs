@@ -1,31 +1,36 @@
 use super::tables::{add_field, add_simple};
+use crate::arch::RelocationInputs;
 use crate::commands::tables::{SimpleTableBuilder, TableBuilder};
-use crate::debug::{Abbreviation, ParseTypes, SymbolIndex, Type};
+use crate::debug::{Abbreviation, ParseTypes, SymbolIndex, SymbolType, Type};
 use crate::elf::{
-    LoadSegment, MemoryMappedFile, ProgramHeader, SectionHeader, SectionType, Stream, StringIndex,
+    Endian, LayeredMemory, Memory, ProgramHeader, RelocationOffset, SectionHeader, SectionType,
     VirtualAddr,
 };
 use crate::repl::{ElfLineArgs, EntriesArgs, ExplainArgs, StringsArgs};
 use crate::utils;
 use crate::utils::{Styling, uwriteln};
 use crate::{elf::ElfFile, elf::ElfFiles, repl::TableArgs};
+use std::collections::HashMap;
 use std::io::Write;
 
-fn get_file(files: &ElfFiles, exe: bool) -> &ElfFile {
-    if exe {
-        match &files.exe {
-            Some(file) => file,
+fn get_file(files: &ElfFiles, exe: bool) -> Option<&ElfFile> {
+    let file = if exe {
+        match files.exe() {
+            Some(file) => Some(file),
             None => {
-                utils::warn("--exe was used but there is no exe: using core instead");
-                files.core.as_ref().unwrap()
+                if files.core().is_some() {
+                    utils::warn("--exe was used but there is no exe: using core instead");
+                }
+                files.core()
             }
         }
     } else {
-        match &files.core {
-            Some(file) => file,
-            None => files.exe.as_ref().unwrap(),
-        }
+        files.core().or(files.exe())
+    };
+    if file.is_none() {
+        utils::warn("no files are loaded: use the file or core commands to load them");
     }
+    file
 }
 
 pub fn elf_abbreviations(mut out: impl Write, files: &ElfFiles, args: &EntriesArgs) {
@@ -38,7 +43,9 @@ pub fn elf_abbreviations(mut out: impl Write, files: &ElfFiles, args: &EntriesAr
         }
     }
 
-    let file = get_file(files, true);
+    let Some(file) = get_file(files, true) else {
+        return;
+    };
     let mut offset = 0;
     loop {
         let mut count = 0;
@@ -59,7 +66,9 @@ pub fn elf_abbreviations(mut out: impl Write, files: &ElfFiles, args: &EntriesAr
 }
 
 pub fn elf_line(mut out: impl Write, files: &ElfFiles, args: &ElfLineArgs) {
-    let file = get_file(files, true);
+    let Some(file) = get_file(files, true) else {
+        return;
+    };
     match file.get_lines() {
         Some(lines) => {
             for (i, unit) in lines.units.iter().enumerate() {
@@ -95,26 +104,48 @@ pub fn elf_line(mut out: impl Write, files: &ElfFiles, args: &ElfLineArgs) {
     }
 }
 
+pub fn elf_dynamic(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
+    let Some(entries) = file.find_dynamic() else {
+        uwriteln!(out, "No dynamic segment found.");
+        return;
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l("tag", "the type of the entry");
+    builder.add_col_l(
+        "value",
+        "the string for tags like Needed, otherwise the value or address (in hex)",
+    );
+    for (entry, string) in entries {
+        add_field!(builder, "tag", entry.tag);
+        match string {
+            Some(s) => {
+                add_field!(builder, "value", s);
+            }
+            None => {
+                add_field!(builder, "value", "{:x}", entry.value);
+            }
+        }
+    }
+
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
+}
+
 pub fn elf_header(out: impl Write, files: &ElfFiles, args: &ExplainArgs) {
     let mut b = SimpleTableBuilder::new();
 
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     add_simple!(b, "type", file.header.stype(), "type of ELF file");
-    if file.reader.little_endian {
-        add_simple!(
-            b,
-            "little endian",
-            file.reader.little_endian,
-            "words are being laid out in memory with the most significant byte last"
-        );
-    } else {
-        add_simple!(
-            b,
-            "little endian",
-            file.reader.little_endian,
-            "words are being laid out out in memory with the most significant byte first"
-        );
-    }
+    let explain = match file.reader.endian {
+        Endian::Little => "words are being laid out in memory with the most significant byte last",
+        Endian::Big => "words are being laid out in memory with the most significant byte first",
+    };
+    add_simple!(b, "endian", file.reader.endian, explain);
     if file.reader.sixty_four_bit {
         add_simple!(
             b,
@@ -173,26 +204,6 @@ pub fn elf_header(out: impl Write, files: &ElfFiles, args: &ExplainArgs) {
 }
 
 pub fn elf_loads(out: impl Write, files: &ElfFiles, args: &TableArgs) {
-    pub fn find_file(
-        files: &Option<Vec<MemoryMappedFile>>,
-        vaddr: VirtualAddr,
-    ) -> Option<&MemoryMappedFile> {
-        if let Some(maps) = files {
-            maps.iter().find(|m| m.vbytes.contains(vaddr))
-        } else {
-            None
-        }
-    }
-
-    pub fn is_stack(file: &ElfFile, segment: &LoadSegment) -> bool {
-        if let Some(status) = file.find_prstatus() {
-            let bottom = status.get_frame_stack_bottom();
-            segment.vbytes.contains(bottom)
-        } else {
-            false
-        }
-    }
-
     let mut builder = TableBuilder::new();
     builder.add_col_l("vaddr", "the virtual address the segment starts at");
     builder.add_col_r("vbytes", "the size of the segment in memory");
@@ -207,20 +218,32 @@ pub fn elf_loads(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         "path to memory mapped file or how the segment is used",
     );
 
-    let file = get_file(files, args.exe);
-    let files = file.get_memory_mapped_files();
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
+    let regions = if file.is_core() {
+        files.regions(file)
+    } else {
+        Vec::new()
+    };
     for segment in file.loads.iter() {
-        let mut note = String::new();
-        if let Some(file) = find_file(files, segment.vbytes.start) {
-            note.push_str(&format!("{} ", file.file_name));
-        } else if is_stack(file, segment) {
-            note.push_str("[stack] ");
-        } else if !segment.executable() && segment.writeable() && segment.readable() {
-            note.push_str("[data] ");
-        } else if !segment.executable() && !segment.writeable() && segment.readable() {
-            // TODO may also want to check that the first bytes are '.ELF'
-            note.push_str("[text] ");
-        }
+        let note = match regions
+            .iter()
+            .find(|r| r.vbytes.start == segment.vbytes.start)
+        {
+            Some(region) => match &region.file {
+                Some((name, _)) => name.clone(),
+                None => region.kind.to_string(),
+            },
+            None if !segment.executable() && segment.writeable() && segment.readable() => {
+                "[data]".to_string()
+            }
+            None if !segment.executable() && !segment.writeable() && segment.readable() => {
+                // TODO may also want to check that the first bytes are '.ELF'
+                "[text]".to_string()
+            }
+            None => String::new(),
+        };
 
         add_field!(builder, "vaddr", "{:x}", segment.vbytes.start.0);
         add_field!(builder, "vbytes", "{:x}", segment.vbytes.size);
@@ -230,7 +253,7 @@ pub fn elf_loads(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         add_field!(builder, "note", note);
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
 }
 
 pub fn elf_notes(out: impl Write, files: &ElfFiles, args: &TableArgs) {
@@ -240,7 +263,9 @@ pub fn elf_notes(out: impl Write, files: &ElfFiles, args: &TableArgs) {
     builder.add_col_r("offset", "offset into the ELF file (hex)");
     builder.add_col_r("size", "size of the note");
 
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     for note in file.notes.iter() {
         add_field!(builder, "name", note.name);
         add_field!(builder, "type", "{:?}", note.ntype);
@@ -248,87 +273,162 @@ pub fn elf_notes(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         add_field!(builder, "size", note.contents.size);
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
 }
 
 pub fn elf_relocations(out: impl Write, files: &ElfFiles, args: &TableArgs) {
     // TODO probably should use an arg w/o --exe
+    let Some(file) = get_file(files, true) else {
+        return;
+    };
+    let relocatable = file.header.etype == 1; // ET_REL
+    let loaded = files
+        .core()
+        .and_then(|core| ElfFiles::load_bias(core, file).map(|bias| (core, bias)));
+    let bias = loaded.map_or(0, |(_, bias)| bias);
+
     let mut builder = TableBuilder::new();
-    builder.add_col_r("symbol", "name of the symbol to relocate");
-    builder.add_col_r("index", "index of the symbol to relocate"); // TODO get rid of this
-    builder.add_col_r("string", "index of the symbol string"); // TODO get rid of this
-    builder.add_col_r("dynamic", "true if the symbol is from a shared library");
-    builder.add_col_r("offset", "vaddr for exe or shared object");
+    builder.add_col_l("symbol", "name of the symbol to relocate");
+    builder.add_col_l("section", "the section the relocation is applied to");
+    if relocatable {
+        builder.add_col_r("offset", "offset into the section (hex)");
+    } else {
+        builder.add_col_r("vaddr", "address the relocation is applied to (hex)");
+    }
     builder.add_col_l("type", "how to apply the relocation (arch specific)");
     builder.add_col_r("addend", "optional constant applied during relocation");
+    builder.add_col_l(
+        "dynamic",
+        "true if the symbol is from the dynamic symbol table",
+    );
+    builder.add_col_r(
+        "value",
+        "value the relocation writes (hex), blank if it can't be computed",
+    );
+    if loaded.is_some() {
+        builder.add_col_l(
+            "slot",
+            "the contents of the relocated word in the core, e.g. a GOT entry",
+        );
+    }
 
-    let file = get_file(files, true);
-    let symbols = file.find_symbols();
-    let dynamic_symbols = file.find_dynamic_symbols();
-
-    let rels = files.find_relocations();
-    for r in rels.iter() {
-        // TODO names aren't great for static relocation entries. They do match what
-        // `readelf --syms` reports but they are sucky names. For example,
-        // `readelf --relocs` will report "printf@GLIBC_2.2.5" but we say
-        // "deregister_tm_clones".
-        let name = if r.dynamic {
-            match &dynamic_symbols {
-                Some(t) => {
-                    let e = t.entries.get(r.symbol as usize);
-                    e.map(|ue| file.find_string(t.section.link, ue.name))
-                }
-                None => None,
+    let got = file
+        .find_symbol("_GLOBAL_OFFSET_TABLE_")
+        .map(|e| e.value.wrapping_add(bias));
+    let mut tables = HashMap::new();
+    for r in file.find_relocations() {
+        let table = tables
+            .entry(r.symbol_table)
+            .or_insert_with(|| file.symbol_table_at(r.symbol_table));
+        let entry = table
+            .as_ref()
+            .and_then(|t| t.entries.get(r.symbol as usize).map(|e| (t, e)));
+        let name = match entry {
+            Some((_, e)) if matches!(e.stype, SymbolType::Section) => index_to_str(file, e.index),
+            Some((t, e)) => file.find_string(t.section.link, e.name).unwrap_or_default(),
+            None => format!("index {}", r.symbol),
+        };
+
+        let (section, place) = match r.offset {
+            RelocationOffset::Section(offset) => {
+                let section = r.target.and_then(|i| file.find_section_name(i));
+                (section, offset)
             }
-        } else {
-            match &symbols {
-                Some(t) => {
-                    let e = t.entries.get(r.symbol as usize);
-                    e.map(|ue| file.find_string(t.section.link, ue.name))
-                }
-                None => None,
+            RelocationOffset::Vaddr(vaddr) => {
+                let section = file
+                    .sections
+                    .iter()
+                    .find(|s| s.is_alloc() && s.vbytes.start.0 != 0 && s.vbytes.contains(vaddr))
+                    .and_then(|s| file.find_default_string(s.name));
+                (section, vaddr.0)
             }
-        }
-        .flatten()
-        .unwrap_or(format!("index {}", r.symbol));
-
-        let string = if r.dynamic {
-            match &dynamic_symbols {
-                Some(t) => {
-                    let e = t.entries.get(r.symbol as usize);
-                    e.map(|ue| ue.name)
+        };
+
+        let s = match entry {
+            None => None,
+            Some((_, e)) if r.symbol == 0 => Some(e.value),
+            Some((_, e)) if matches!(e.index, SymbolIndex::Abs) => Some(e.value),
+            Some((_, e)) if !matches!(e.index, SymbolIndex::Undef) => {
+                if relocatable {
+                    None // only have an offset into the symbol's section
+                } else {
+                    Some(e.value.wrapping_add(bias))
                 }
-                None => None,
             }
+            Some(_) => loaded.and_then(|(core, _)| resolve_symbol(files, core, file, &name)),
+        };
+        let inputs = RelocationInputs {
+            s,
+            a: r.addend,
+            p: place.wrapping_add(bias),
+            b: bias,
+            z: entry.map_or(0, |(_, e)| e.size),
+            got,
+            addr_size: file.reader.addr_size() as u64,
+        };
+
+        add_field!(builder, "symbol", name);
+        add_field!(builder, "section", section.unwrap_or_default());
+        if relocatable {
+            add_field!(builder, "offset", "{:x}", place);
         } else {
-            match &symbols {
-                Some(t) => {
-                    let e = t.entries.get(r.symbol as usize);
-                    e.map(|ue| ue.name)
-                }
-                None => None,
+            add_field!(builder, "vaddr", "{:x}", place);
+        }
+        add_field!(builder, "type", r.rtype);
+        match r.addend {
+            Some(a) => {
+                add_field!(builder, "addend", a);
+            }
+            None => {
+                add_field!(builder, "addend", "none");
             }
         }
-        .unwrap_or(StringIndex(0));
-
-        let addend = match r.addend {
-            Some(a) => format!("{}", a),
-            None => "none".to_string(),
-        };
-        add_field!(builder, "symbol", name);
         add_field!(builder, "dynamic", r.dynamic);
-        add_field!(builder, "index", r.symbol);
-        add_field!(builder, "string", string.0);
-        add_field!(builder, "offset", "{:x}", r.offset);
-        add_field!(builder, "type", "{:?}", r.rtype);
-        add_field!(builder, "addend", addend);
+        match r.rtype.value(&inputs) {
+            Some(value) => {
+                add_field!(builder, "value", "{:x}", value);
+            }
+            None => {
+                add_field!(builder, "value", "");
+            }
+        }
+        if let Some((core, _)) = loaded {
+            let addr = VirtualAddr(inputs.p);
+            let slot = match LayeredMemory::new(files, core).read_ptr(addr) {
+                Ok(value) => match files.describe_addr(core, VirtualAddr(value)) {
+                    Some(name) => format!("{value:x} {name}"),
+                    None => format!("{value:x}"),
+                },
+                Err(err) => err.to_string(),
+            };
+            add_field!(builder, "slot", slot);
+        }
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
+}
+
+/// Returns the address of an undefined symbol using the other objects in the core,
+/// including the shared libraries in its link map.
+fn resolve_symbol(files: &ElfFiles, core: &ElfFile, file: &ElfFile, name: &str) -> Option<u64> {
+    if name.is_empty() {
+        return None;
+    }
+    files
+        .searched_objects(Some(core))
+        .into_iter()
+        .filter(|(obj, _)| !std::ptr::eq(*obj, file))
+        .find_map(|(obj, bias)| {
+            obj.find_symbol(name)
+                .filter(|e| !matches!(e.index, SymbolIndex::Undef))
+                .map(|e| e.value.wrapping_add(bias))
+        })
 }
 
 pub fn elf_sections(out: impl Write, files: &ElfFiles, args: &TableArgs) {
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     let sections = file.get_sections();
 
     let mut builder = TableBuilder::new();
@@ -373,12 +473,14 @@ pub fn elf_sections(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         add_field!(builder, "info", section.info);
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
 }
 
 pub fn elf_segments(out: impl Write, files: &ElfFiles, args: &TableArgs) {
-    let file = get_file(files, args.exe);
-    let segments = ElfFile::find_segments(file.reader, &file.header);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
+    let segments = ElfFile::find_segments(&file.reader, &file.header);
 
     let mut builder = TableBuilder::new();
     builder.add_col_l("type", "the segment type");
@@ -408,7 +510,7 @@ pub fn elf_segments(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         add_field!(builder, "flags", "{}", ProgramHeader::flags(segment.flags));
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
     if args.explain {
         println!();
         println!("Numeric fields are all in hex. Usually it's more informative to use");
@@ -417,7 +519,9 @@ pub fn elf_segments(out: impl Write, files: &ElfFiles, args: &TableArgs) {
 }
 
 pub fn elf_strings(mut out: impl Write, files: &ElfFiles, args: &StringsArgs) {
-    let file = get_file(files, true);
+    let Some(file) = get_file(files, true) else {
+        return;
+    };
     let num_sections = file.get_sections().len();
 
     let mut found = false;
@@ -461,11 +565,9 @@ pub fn elf_symbols(out: impl Write, files: &ElfFiles, args: &TableArgs) {
     );
 
     // TODO double check that function pointers are legit
-    // TODO sort rows? provide some sort of generic table sort support?
-    // TODO maybe also filtering options, eg max-results and filter by col value
-    //      two options for filter by col? or something like --filter="type=Func"?
-    //      maybe also a complement option
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     let tables = [file.find_dynamic_symbols(), file.find_symbols()];
     let tables = tables.iter().flatten().collect::<Vec<_>>();
     for table in tables.iter() {
@@ -488,7 +590,7 @@ pub fn elf_symbols(out: impl Write, files: &ElfFiles, args: &TableArgs) {
         }
     }
 
-    builder.writeln(out, args.titles, args.explain);
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
 }
 
 pub fn elf_types(mut out: impl Write, files: &ElfFiles, args: &EntriesArgs) {
@@ -504,7 +606,9 @@ pub fn elf_types(mut out: impl Write, files: &ElfFiles, args: &EntriesArgs) {
         uwriteln!(out);
     }
 
-    let file = get_file(files, true);
+    let Some(file) = get_file(files, true) else {
+        return;
+    };
     match ParseTypes::new(file) {
         Ok(parser) => {
             let types = parser.parse();
@@ -535,6 +639,7 @@ fn index_to_str(file: &ElfFile, index: SymbolIndex) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repl::FilterArgs;
     use crate::utils::{debug_results, do_test, release_results};
 
     #[test]
@@ -555,12 +660,24 @@ mod tests {
         do_test!(elf_header, &args);
     }
 
+    #[test]
+    fn exe_dynamic() {
+        let args = TableArgs {
+            exe: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(elf_dynamic, &args);
+    }
+
     #[test]
     fn core_loads() {
         let args = TableArgs {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_loads, &args);
     }
@@ -571,6 +688,7 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_loads, &args);
     }
@@ -581,6 +699,7 @@ mod tests {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_notes, &args);
     }
@@ -591,6 +710,7 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_notes, &args);
     }
@@ -602,6 +722,7 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_relocations, &args);
     }
@@ -613,6 +734,7 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_sections, &args);
     }
@@ -623,6 +745,7 @@ mod tests {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_segments, &args);
     }
@@ -633,6 +756,7 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(elf_segments, &args);
     }
@@ -663,10 +787,44 @@ mod tests {
             exe: true,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(elf_symbols, &args);
+    }
+
+    #[test]
+    fn symbols_filtered() {
+        let args = TableArgs {
+            exe: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs {
+                stype: Some("func".to_string()),
+                section: Some(".text".to_string()),
+                sort: Some("value".to_string()),
+                reverse: true,
+                limit: Some(4),
+                ..FilterArgs::default()
+            },
         };
         do_test!(elf_symbols, &args);
     }
 
+    #[test]
+    fn sections_range() {
+        let args = TableArgs {
+            exe: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs {
+                name: Some(regex::Regex::new("^\\.(text|data|bss)").unwrap()),
+                range: Some((0x1000, 0x5000)),
+                ..FilterArgs::default()
+            },
+        };
+        do_test!(elf_sections, &args);
+    }
+
     #[test]
     fn line() {
         let args = ElfLineArgs { max_lines: 5 };
@@ -0,0 +1,904 @@
+use super::tables::add_field;
+use crate::commands::raw_frames;
+use crate::commands::tables::TableBuilder;
+use crate::elf::{
+    ElfFile, ElfFiles, Endian, LayeredMemory, LoadSegment, Memory, Offset, VirtualAddr, hex_dump,
+};
+use crate::repl::{FindArgs, HexdumpLabels};
+use crate::utils::{self, Styling, uwrite, uwriteln};
+use memchr::memmem;
+use std::error::Error;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Regions are split into chunks of this size which are searched in parallel.
+const CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Progress is only reported for searches at least this big.
+const PROGRESS_SIZE: usize = 256 * 1024 * 1024;
+
+/// Memory that can't be read is skipped a page at a time.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// What find searches for.
+enum Needle {
+    Bytes(Vec<u8>),
+
+    /// ASCII case insensitive match.
+    NoCase(Vec<u8>),
+
+    /// A 4 or 8 byte float within tolerance of value.
+    Float {
+        size: usize,
+        value: f64,
+        tolerance: f64,
+    },
+
+    /// An aligned pointer into start..end.
+    Pointer {
+        size: usize,
+        start: u64,
+        end: u64,
+    },
+}
+
+impl Needle {
+    fn new(args: &FindArgs, file: &ElfFile) -> Result<Self, Box<dyn Error>> {
+        let endian = file.reader.endian;
+        let int = |value: u64, size: usize| {
+            if size < 8 && value >> (8 * size) != 0 {
+                Err(format!("0x{value:x} doesn't fit into {size} bytes").into())
+            } else {
+                Ok(Needle::Bytes(endian.encode(value, size)))
+            }
+        };
+        let string = |bytes: Vec<u8>| {
+            if bytes.is_empty() {
+                Err("nothing to search for".into())
+            } else if args.ignore_case {
+                Ok(Needle::NoCase(bytes))
+            } else {
+                Ok(Needle::Bytes(bytes))
+            }
+        };
+
+        if let Some(s) = &args.hex {
+            string(byte_str_to_vec(s)?)
+        } else if let Some(s) = &args.string {
+            string(ascii_str_to_vec(s))
+        } else if let Some(s) = &args.utf16 {
+            let bytes = s
+                .encode_utf16()
+                .flat_map(|u| endian.encode(u as u64, 2))
+                .collect();
+            string(bytes)
+        } else if let Some(value) = args.u16 {
+            int(value, 2)
+        } else if let Some(value) = args.u32 {
+            int(value, 4)
+        } else if let Some(value) = args.u64 {
+            int(value, 8)
+        } else if let Some(value) = args.i64 {
+            int(value as u64, 8)
+        } else if let Some(value) = args.ptr {
+            int(value, file.reader.addr_size())
+        } else if let Some((start, end)) = args.refs_to {
+            Ok(Needle::Pointer {
+                size: file.reader.addr_size(),
+                start,
+                end,
+            })
+        } else if let Some(value) = args.float {
+            Ok(Needle::Float {
+                size: 4,
+                value: value as f64,
+                tolerance: args.tolerance,
+            })
+        } else if let Some(value) = args.double {
+            Ok(Needle::Float {
+                size: 8,
+                value,
+                tolerance: args.tolerance,
+            })
+        } else {
+            Err("nothing to search for".into())
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Needle::Bytes(bytes) | Needle::NoCase(bytes) => bytes.len(),
+            Needle::Float { size, .. } | Needle::Pointer { size, .. } => *size,
+        }
+    }
+
+    /// Returns the indexes of non-overlapping matches within bytes. If limit is non-zero
+    /// then at most limit indexes are returned. Pointers are only matched at multiples of
+    /// their size so bytes should start at an aligned address.
+    fn find_all(&self, bytes: &[u8], endian: Endian, limit: usize) -> Vec<usize> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        match self {
+            Needle::Bytes(needle) => memmem::find_iter(bytes, needle).take(limit).collect(),
+            Needle::NoCase(needle) => {
+                let lower = needle[0].to_ascii_lowercase();
+                let upper = needle[0].to_ascii_uppercase();
+                let mut next = 0;
+                memchr::memchr2_iter(lower, upper, bytes)
+                    .filter(|&i| {
+                        let found = i >= next
+                            && bytes
+                                .get(i..i + needle.len())
+                                .is_some_and(|b| b.eq_ignore_ascii_case(needle));
+                        if found {
+                            next = i + needle.len();
+                        }
+                        found
+                    })
+                    .take(limit)
+                    .collect()
+            }
+            Needle::Float {
+                size,
+                value,
+                tolerance,
+            } => {
+                let mut result = Vec::new();
+                let mut i = 0;
+                while i + size <= bytes.len() && result.len() < limit {
+                    let bits = endian.decode(&bytes[i..i + size]);
+                    let actual = if *size == 4 {
+                        f32::from_bits(bits as u32) as f64
+                    } else {
+                        f64::from_bits(bits)
+                    };
+                    if (actual - value).abs() <= *tolerance {
+                        result.push(i);
+                        i += size;
+                    } else {
+                        i += 1;
+                    }
+                }
+                result
+            }
+            Needle::Pointer { size, start, end } => bytes
+                .chunks_exact(*size)
+                .enumerate()
+                .filter(|(_, b)| (*start..*end).contains(&endian.decode(b)))
+                .map(|(i, _)| i * size)
+                .take(limit)
+                .collect(),
+        }
+    }
+}
+
+/// Part of a region that is searched by one thread. Matches may start anywhere within
+/// start..end and may extend past end (but not past the end of the region).
+struct Chunk {
+    region: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Returns the offsets of the non-overlapping matches within each region. If limit is
+/// non-zero then at most limit offsets are returned. Errors if the user hit Ctrl-C.
+fn search(
+    regions: &[&[u8]],
+    endian: Endian,
+    needle: &Needle,
+    limit: usize,
+) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    let mut chunks = Vec::new();
+    for (i, region) in regions.iter().enumerate() {
+        for start in (0..region.len()).step_by(CHUNK_SIZE) {
+            chunks.push(Chunk {
+                region: i,
+                start,
+                end: region.len().min(start + CHUNK_SIZE),
+            });
+        }
+    }
+
+    let total: usize = regions.iter().map(|r| r.len()).sum();
+    let results: Mutex<Vec<Option<Vec<usize>>>> = Mutex::new(vec![None; chunks.len()]);
+    let next = AtomicUsize::new(0);
+    let searched = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+    utils::clear_interrupt();
+
+    let search_chunks = || {
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= chunks.len() || done.load(Ordering::Relaxed) || utils::interrupted() {
+                break;
+            }
+
+            let chunk = &chunks[i];
+            let region = regions[chunk.region];
+            let end = (chunk.end + needle.len() - 1).min(region.len());
+            let found = needle
+                .find_all(&region[chunk.start..end], endian, limit)
+                .iter()
+                .map(|j| chunk.start + j)
+                .collect();
+            searched.fetch_add(chunk.end - chunk.start, Ordering::Relaxed);
+
+            let mut results = results.lock().unwrap();
+            results[i] = Some(found);
+            if limit > 0 {
+                // We can stop once the chunks before the remaining chunks have enough matches.
+                let count: usize = results
+                    .iter()
+                    .map_while(|r| r.as_ref().map(|v| v.len()))
+                    .sum();
+                if count >= limit {
+                    done.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    };
+
+    thread::scope(|scope| {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let workers: Vec<_> = (0..threads.min(chunks.len()))
+            .map(|_| scope.spawn(search_chunks))
+            .collect();
+
+        let mut progress = false;
+        if total >= PROGRESS_SIZE && std::io::stderr().is_terminal() {
+            while !workers.iter().all(|w| w.is_finished()) {
+                let mb = searched.load(Ordering::Relaxed) / (1024 * 1024);
+                eprint!("\rsearched {mb} of {} MiB", total / (1024 * 1024));
+                progress = true;
+                thread::sleep(Duration::from_millis(250));
+            }
+        }
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        if progress {
+            eprint!("\r\x1b[K");
+        }
+    });
+
+    if utils::interrupted() {
+        return Err("search was interrupted".into());
+    }
+
+    // A chunk may have found a match that overlaps one found at the end of the previous
+    // chunk.
+    let mut result: Vec<Vec<usize>> = vec![Vec::new(); regions.len()];
+    let mut count = 0;
+    for (chunk, found) in chunks.iter().zip(results.into_inner().unwrap()) {
+        for offset in found.unwrap_or_default() {
+            if count == limit && limit > 0 {
+                return Ok(result);
+            }
+            let found = &mut result[chunk.region];
+            if found
+                .last()
+                .is_none_or(|last| offset >= last + needle.len())
+            {
+                found.push(offset);
+                count += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+pub fn find(mut out: impl Write, files: &ElfFiles, args: &FindArgs) {
+    /// Returns the part of the load segment that should be searched.
+    fn search_range(load: &LoadSegment, args: &FindArgs) -> Option<(u64, u64)> {
+        let writable = args.writable || args.refs_to.is_some();
+        if (writable && !load.writeable()) || (args.executable && !load.executable()) {
+            return None;
+        }
+        if let Some(addr) = args.segment
+            && !load.vbytes.contains(VirtualAddr(addr))
+        {
+            return None;
+        }
+
+        let start = load.vbytes.start.0;
+        let end = load.vbytes.end().0;
+        let (start, end) = match args.range {
+            Some((lower, upper)) => (start.max(lower), end.min(upper)),
+            None => (start, end),
+        };
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    fn search_load_segments(
+        mut out: impl Write,
+        files: &ElfFiles,
+        core: &ElfFile,
+        args: &FindArgs,
+        needle: &Needle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Bytes that aren't in the core are searched using the files that were mapped
+        // into the process.
+        let memory = LayeredMemory::new(files, core);
+        let align = match needle {
+            Needle::Pointer { size, .. } => *size as u64,
+            _ => 1,
+        };
+        let mut starts = Vec::new();
+        let mut regions = Vec::new();
+        for load in core.loads.iter() {
+            let Some((start, end)) = search_range(load, args) else {
+                continue;
+            };
+            let mut addr = start.next_multiple_of(align);
+            while addr < end {
+                match memory.slice(VirtualAddr(addr)) {
+                    Ok((bytes, _)) => {
+                        let size = bytes.len().min((end - addr) as usize);
+                        starts.push(addr);
+                        regions.push((bytes, size));
+                        addr = (addr + size as u64).next_multiple_of(align);
+                    }
+                    Err(_) => addr = (addr + 1).next_multiple_of(PAGE_SIZE),
+                }
+            }
+        }
+
+        let regions: Vec<&[u8]> = regions
+            .iter()
+            .map(|(bytes, size)| &bytes[..*size])
+            .collect();
+        let found = search(&regions, core.reader.endian, needle, args.max_results)?;
+        let mut matches = Vec::new();
+        for (start, offsets) in starts.iter().zip(found) {
+            for offset in offsets {
+                matches.push(VirtualAddr(start + offset as u64));
+            }
+        }
+        if let Needle::Pointer { start, end, .. } = needle {
+            write_refs(out, files, core, args, (*start, *end), &matches);
+            return Ok(());
+        }
+
+        for (count, addr) in matches.iter().enumerate() {
+            match files.describe_addr(core, *addr) {
+                Some(name) => uwriteln!(out, "0x{:x} {name}", addr.0),
+                None => uwriteln!(out, "0x{:x}", addr.0),
+            }
+            if args.count > 0 {
+                let read = memory.read_partial(*addr, args.count);
+                hex_dump(
+                    &mut out,
+                    core.reader.endian,
+                    addr.0,
+                    &read.bytes,
+                    1,
+                    HexdumpLabels::None,
+                );
+                uwriteln!(out);
+            }
+            if count + 1 == args.max_results {
+                uwriteln!(out, "...");
+            }
+        }
+        Ok(())
+    }
+
+    fn search_all(
+        out: &mut impl Write,
+        prefix: &str,
+        file: &ElfFile,
+        args: &FindArgs,
+        needle: &Needle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Bytes within load segments are reported as addresses and the rest as offsets.
+        let mut loads: Vec<&LoadSegment> = if file.is_core() {
+            file.loads.iter().filter(|l| l.obytes.size > 0).collect()
+        } else {
+            Vec::new()
+        };
+        loads.sort_by_key(|l| l.obytes.start.0);
+        let mut gaps = Vec::new();
+        let mut offset = 0;
+        for load in loads.iter() {
+            let start = load.obytes.start.0 as usize;
+            if offset < start {
+                gaps.push((offset, start - offset));
+            }
+            offset = offset.max(start + load.obytes.size);
+        }
+        if offset < file.reader.len() {
+            gaps.push((offset, file.reader.len() - offset));
+        }
+
+        let regions = loads
+            .iter()
+            .map(|l| file.reader.slice(l.obytes.start, l.obytes.size))
+            .collect::<Result<Vec<_>, _>>()?;
+        let found = search(&regions, file.reader.endian, needle, args.max_results)?;
+        let mut count = 0;
+        for (load, offsets) in loads.iter().zip(found) {
+            for offset in offsets {
+                if count == 0 {
+                    uwriteln!(out, "{prefix}Addresses:");
+                }
+                let addr = load.vbytes.start + offset as i64;
+                uwriteln!(out, "   0x{:x}", addr.0);
+
+                if args.count > 0 {
+                    uwrite!(out, "   ");
+                    let read = file.read_partial(addr, args.count);
+                    hex_dump(
+                        out,
+                        file.reader.endian,
+                        addr.0,
+                        &read.bytes,
+                        1,
+                        HexdumpLabels::None,
+                    );
+                    uwriteln!(out);
+                }
+                count += 1;
+                if count == args.max_results {
+                    uwriteln!(out, "   ...");
+                    return Ok(());
+                }
+            }
+        }
+
+        let regions = gaps
+            .iter()
+            .map(|(offset, size)| file.reader.slice(Offset::from_raw(*offset as u64), *size))
+            .collect::<Result<Vec<_>, _>>()?;
+        let found = search(&regions, file.reader.endian, needle, args.max_results)?;
+        let mut count = 0;
+        for ((start, _), offsets) in gaps.iter().zip(found) {
+            for offset in offsets {
+                if count == 0 {
+                    uwriteln!(out, "{prefix}Offsets:");
+                }
+                let offset = Offset::from_raw((start + offset) as u64);
+                uwriteln!(out, "   0x{:x}", offset.0);
+
+                if args.count > 0 {
+                    uwrite!(out, "   ");
+                    file.reader
+                        .hex_dump(out, 0, offset, args.count, 1, HexdumpLabels::None);
+                    uwriteln!(out);
+                }
+                count += 1;
+                if count == args.max_results {
+                    uwriteln!(out, "   ...");
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn search_file(out: &mut impl Write, prefix: &str, file: &ElfFile, args: &FindArgs) {
+        let result =
+            Needle::new(args, file).and_then(|needle| search_all(out, prefix, file, args, &needle));
+        if let Err(err) = result {
+            utils::warn(&err.to_string());
+        }
+    }
+
+    if args.refs_to.is_some() && files.core().is_none() {
+        utils::warn("--refs-to needs a core file");
+    } else if args.all {
+        if let Some(core) = files.core()
+            && let Some(exe) = files.exe()
+        {
+            search_file(&mut out, "Core ", core, args);
+            search_file(&mut out, "Exe ", exe, args);
+        } else if let Some(core) = files.core() {
+            search_file(&mut out, "", core, args);
+        } else if let Some(exe) = files.exe() {
+            search_file(&mut out, "", exe, args);
+        } else {
+            utils::warn("no files are loaded");
+        }
+    } else if let Some(core) = files.core() {
+        let result = Needle::new(args, core)
+            .and_then(|needle| search_load_segments(out, files, core, args, &needle));
+        if let Err(err) = result {
+            utils::warn(&err.to_string());
+        }
+    } else if let Some(exe) = files.exe() {
+        // Technically we should only do this if --all is used but it's kind of
+        // silly to not do a search if all we have is an exe.
+        search_file(&mut out, "", exe, args);
+    } else {
+        utils::warn("no files are loaded");
+    }
+}
+
+/// Prints a table of the registers and memory locations that point into target.
+fn write_refs(
+    mut out: impl Write,
+    files: &ElfFiles,
+    core: &ElfFile,
+    args: &FindArgs,
+    target: (u64, u64),
+    matches: &[VirtualAddr],
+) {
+    let mut builder = TableBuilder::new();
+    builder.add_col_l("location", "register or address of the pointer");
+    builder.add_col_l("value", "the pointer, with its offset when a range is used");
+    builder.add_col_l(
+        "in",
+        "thread and stack frame (and variable), symbol, or mapped file the location is in",
+    );
+    let describe = |v: u64| {
+        if target.1 - target.0 > 1 {
+            format!("0x{v:x} (+0x{:x})", v - target.0)
+        } else {
+            format!("0x{v:x}")
+        }
+    };
+
+    let threads = core.find_prstatuses();
+    for status in threads.iter() {
+        for (i, r) in status.registers.iter().enumerate() {
+            if (target.0..target.1).contains(r) {
+                add_field!(builder, "location", status.register_name(i));
+                add_field!(builder, "value", describe(*r));
+                add_field!(builder, "in", "thread {}", status.pid);
+            }
+        }
+    }
+
+    let frames: Vec<_> = threads
+        .iter()
+        .map(|status| raw_frames(core, status).unwrap_or_default())
+        .collect();
+    let ptr = core.reader.addr_size() as u64;
+    let memory = LayeredMemory::new(files, core);
+    for addr in matches.iter() {
+        let Ok(value) = memory.read_ptr(*addr) else {
+            continue;
+        };
+        add_field!(builder, "location", "0x{:x}", addr.0);
+        add_field!(builder, "value", describe(value));
+
+        // Stack slots are above sp and belong to the first frame whose saved frame pointer
+        // and return address are above the slot.
+        let stack = threads.iter().zip(frames.iter()).find(|(status, _)| {
+            let sp = status.get_frame_stack_bottom();
+            core.find_load_segment(sp)
+                .is_some_and(|l| l.vbytes.contains(*addr) && *addr >= sp)
+        });
+        let location = match stack {
+            Some((status, frames)) => match frames
+                .iter()
+                .enumerate()
+                .find(|(_, f)| addr.0 < f.fp.0.saturating_add(2 * ptr))
+            {
+                Some((i, frame)) => {
+                    let mut location = match files.describe_addr(core, frame.ip) {
+                        Some(name) => format!("thread {} frame {i} in {name}", status.pid),
+                        None => format!("thread {} frame {i}", status.pid),
+                    };
+                    if let Some(var) =
+                        files.find_stack_variable(core, status.arch, frame.ip, frame.fp, *addr)
+                    {
+                        location += &format!(" ({var})");
+                    }
+                    location
+                }
+                None => format!("thread {} stack", status.pid),
+            },
+            None => files.describe_addr(core, *addr).unwrap_or_default(),
+        };
+        add_field!(builder, "in", location);
+    }
+
+    builder.writeln(&mut out, true, false);
+    if matches.len() == args.max_results {
+        uwriteln!(out, "...");
+    }
+}
+
+fn ascii_str_to_vec(str: &str) -> Vec<u8> {
+    let mut result = Vec::new();
+
+    for ch in str.chars() {
+        let mut buffer = [0; 4]; // 4 is always large enough
+        let n = ch.encode_utf8(&mut buffer).len();
+        result.extend(buffer.iter().take(n));
+    }
+
+    result
+}
+
+fn byte_str_to_vec(str: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut result = Vec::new();
+
+    let mut i = 0;
+    let chars: Vec<char> = str.chars().collect();
+    while i < chars.len() {
+        if chars[i] == ' ' {
+            // ignore spaces
+            i += 1;
+        } else if i + 1 < chars.len()
+            && chars[i].is_ascii_hexdigit()
+            && chars[i + 1].is_ascii_hexdigit()
+        {
+            let s = format!("{}{}", chars[i], chars[i + 1]);
+            let byte = u8::from_str_radix(&s, 16).unwrap();
+            result.push(byte);
+            i += 2;
+        } else {
+            return Err("Expected a string of hex bytes with optional spaces between bytes".into());
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::core_builder::CoreBuilder;
+    use crate::utils::{debug_results, do_test, release_results};
+
+    #[test]
+    fn find_default_str() {
+        let args = FindArgs {
+            all: false,
+            string: Some("apple".to_string()),
+            count: 0,
+            hex: None,
+            max_results: 0,
+            ..Default::default()
+        };
+        do_test!(find, &args);
+    }
+
+    #[test]
+    fn find_all_str() {
+        let args = FindArgs {
+            all: true,
+            string: Some("count".to_string()),
+            count: 0,
+            hex: None,
+            max_results: 0,
+            ..Default::default()
+        };
+        do_test!(find, &args);
+    }
+
+    #[test]
+    fn find_hex() {
+        let args = FindArgs {
+            all: false,
+            string: None,
+            count: 0,
+            hex: Some("20".to_string()),
+            max_results: 10,
+            ..Default::default()
+        };
+        do_test!(find, &args);
+    }
+
+    #[test]
+    fn find_all_hex() {
+        let args = FindArgs {
+            all: true,
+            string: None,
+            count: 0,
+            hex: Some("20".to_string()),
+            max_results: 10,
+            ..Default::default()
+        };
+        do_test!(find, &args);
+    }
+
+    #[test]
+    fn find_str_dump() {
+        let args = FindArgs {
+            all: false,
+            string: Some("count".to_string()),
+            count: 25,
+            hex: None,
+            max_results: 0,
+            ..Default::default()
+        };
+        do_test!(find, &args);
+    }
+
+    #[test]
+    fn find_across_chunks() {
+        // The a's straddle a chunk boundary and the last match found in the second chunk
+        // overlaps the last one found in the first.
+        let mut bytes = vec![0; CHUNK_SIZE + 0x1000];
+        bytes[CHUNK_SIZE - 3..CHUNK_SIZE + 2].copy_from_slice(b"aaaaa");
+        bytes[2 * CHUNK_SIZE / 3..][..6].copy_from_slice(b"ApPle!");
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        builder.load(0x10000000, bytes, 6);
+        let files = ElfFiles::new(vec![builder.write("find-chunks")]).unwrap();
+
+        let mut v: Vec<u8> = Vec::new();
+        let args = FindArgs {
+            hex: Some("6161".to_string()),
+            ..Default::default()
+        };
+        find(&mut v, &files, &args);
+        let args = FindArgs {
+            string: Some("apple".to_string()),
+            ignore_case: true,
+            ..Default::default()
+        };
+        find(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn find_refs() {
+        const TARGET: u64 = 0x600000;
+        const STACK: u64 = 0x7ff000;
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[0] = TARGET + 0x8; // r15
+        regs[4] = STACK + 0x200; // rbp
+        regs[16] = 0x401000; // rip
+        regs[19] = STACK + 0x100; // rsp
+        builder.prstatus(11, 1, 100, &regs);
+        let mut regs = vec![0; 27];
+        regs[5] = TARGET + 0x10; // rbx
+        builder.prstatus(0, 0, 101, &regs);
+
+        let mut e = builder.encoder();
+        e.xword(0).xword(0x1234);
+        e.bytes.resize(0x40, 0);
+        e.xword(TARGET + 0x8).xword(TARGET + 0x20);
+        builder.load(TARGET, e.bytes, 6);
+
+        let mut e = builder.encoder();
+        e.xword(TARGET);
+        builder.load(0x400000, e.bytes, 5); // not writable
+
+        let mut slots = vec![0; 0x1000];
+        let mut put = |addr: u64, value: u64| {
+            let i = (addr - STACK) as usize;
+            slots[i..i + 8].copy_from_slice(&value.to_le_bytes());
+        };
+        put(STACK + 0x150, TARGET); // frame 0 local
+        put(STACK + 0x200, STACK + 0x300); // frame records
+        put(STACK + 0x208, 0x401100);
+        put(STACK + 0x250, TARGET + 0x18); // frame 1 local
+        put(STACK + 0x308, 0x401200);
+        put(STACK + 0x311, TARGET); // unaligned
+        put(STACK + 0x320, TARGET); // past the last frame
+        builder.load(STACK, slots, 6);
+        let files = ElfFiles::new(vec![builder.write("find-refs")]).unwrap();
+
+        let mut v: Vec<u8> = Vec::new();
+        let args = FindArgs {
+            refs_to: Some((TARGET, TARGET + 0x20)),
+            max_results: 0,
+            ..Default::default()
+        };
+        find(&mut v, &files, &args);
+        writeln!(&mut v).unwrap();
+        let args = FindArgs {
+            refs_to: Some((TARGET, TARGET + 1)),
+            max_results: 2,
+            ..Default::default()
+        };
+        find(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn find_refs_variables() {
+        let paths = vec![
+            std::path::PathBuf::from("cores/shopping-debug/app-debug"),
+            std::path::PathBuf::from("cores/shopping-debug/app-debug.core"),
+        ];
+        let files = ElfFiles::new(paths).unwrap();
+
+        // add_to_cart stores its id parameter (1) just below the saved rbp.
+        let core = files.core().unwrap();
+        let status = core.find_prstatus().unwrap();
+        let frame = &raw_frames(core, &status).unwrap()[0];
+        let slot = frame.fp + -0x18;
+        let var = files.find_stack_variable(core, status.arch, frame.ip, frame.fp, slot);
+        assert_eq!(var.as_deref(), Some("id"));
+        assert_eq!(core.read_ptr(slot).unwrap(), 1);
+
+        let mut v: Vec<u8> = Vec::new();
+        let args = FindArgs {
+            refs_to: Some((1, 2)),
+            max_results: 0,
+            ..Default::default()
+        };
+        find(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        let line = s
+            .lines()
+            .find(|l| l.starts_with(&format!("0x{:x} ", slot.0)))
+            .unwrap();
+        assert!(
+            line.trim_end()
+                .ends_with("thread 34741 frame 0 in add_to_cart+0x17 (id)"),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn find_typed() {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut e = builder.encoder();
+        e.word(0xdeadbeef).raw(b"Hello").align(8);
+        e.xword(2.5f64.to_bits()).word(1.25f32.to_bits());
+        builder.load(0x10000, e.bytes, 5);
+
+        let mut e = builder.encoder();
+        e.raw(b"HELLO").align(8).word(0xdeadbeef);
+        for u in "hi".encode_utf16() {
+            e.half(u);
+        }
+        builder.load(0x20000, e.bytes, 6);
+        let files = ElfFiles::new(vec![builder.write("find")]).unwrap();
+
+        let searches = [
+            FindArgs {
+                u32: Some(0xdeadbeef),
+                ..Default::default()
+            },
+            FindArgs {
+                string: Some("hello".to_string()),
+                ignore_case: true,
+                ..Default::default()
+            },
+            FindArgs {
+                utf16: Some("hi".to_string()),
+                ..Default::default()
+            },
+            FindArgs {
+                double: Some(2.4),
+                tolerance: 0.2,
+                ..Default::default()
+            },
+            FindArgs {
+                float: Some(1.25),
+                ..Default::default()
+            },
+            FindArgs {
+                u32: Some(0xdeadbeef),
+                writable: true,
+                ..Default::default()
+            },
+            FindArgs {
+                string: Some("hello".to_string()),
+                ignore_case: true,
+                range: Some((0x10001, 0x20000)),
+                ..Default::default()
+            },
+            FindArgs {
+                u32: Some(0xdeadbeef),
+                segment: Some(0x10004),
+                ..Default::default()
+            },
+        ];
+
+        let mut v: Vec<u8> = Vec::new();
+        for args in searches.iter() {
+            find(&mut v, &files, args);
+            writeln!(&mut v).unwrap();
+        }
+        let s = String::from_utf8(v).unwrap();
+        insta::assert_snapshot!(s);
+    }
+}
@@ -0,0 +1,321 @@
+use super::tables::add_field;
+use crate::commands::tables::TableBuilder;
+use crate::elf::{ElfFile, ElfFiles, Memory, VirtualAddr};
+use crate::heap::{self, ChunkState, Heap};
+use crate::repl::{HeapArgs, HeapChunkArgs};
+use crate::utils::{self, Styling, uwriteln};
+use std::io::Write;
+
+pub fn heap_arenas(mut out: impl Write, files: &ElfFiles, args: &HeapArgs) {
+    let Some((_, heap)) = load_heap(files, args.arena) else {
+        return;
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l("arena", "address of the malloc_state struct");
+    builder.add_col_l("main", "* for main_arena (which uses the sbrk heap)");
+    builder.add_col_l(
+        "top",
+        "address of the top chunk, new chunks are carved out of this",
+    );
+    builder.add_col_l(
+        "last remainder",
+        "the chunk most recently split to satisfy a small request",
+    );
+    builder.add_col_r("system mem", "bytes allocated from the OS (decimal)");
+    builder.add_col_r("chunks", "number of chunks in the arena's heap");
+    builder.add_col_r("free", "number of chunks in free lists");
+
+    for arena in heap.arenas.iter() {
+        add_field!(builder, "arena", "0x{:x}", arena.addr.0);
+        add_field!(builder, "main", if arena.main { "*" } else { "" });
+        add_field!(builder, "top", "0x{:x}", arena.top);
+        add_field!(builder, "last remainder", "0x{:x}", arena.last_remainder);
+        add_field!(builder, "system mem", arena.system_mem);
+        add_field!(
+            builder,
+            "chunks",
+            heap.chunks.iter().filter(|c| c.arena == arena.addr).count()
+        );
+        add_field!(
+            builder,
+            "free",
+            heap.lists
+                .iter()
+                .filter(|l| l.arena == arena.addr)
+                .map(|l| l.chunks.len())
+                .sum::<usize>()
+        );
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+    write_problems(out, &heap);
+}
+
+pub fn heap_bins(mut out: impl Write, files: &ElfFiles, args: &HeapArgs) {
+    let Some((core, heap)) = load_heap(files, args.arena) else {
+        return;
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l("arena", "address of the malloc_state struct the list is in");
+    builder.add_col_l("bin", "the free list");
+    builder.add_col_r("count", "number of chunks in the list");
+    builder.add_col_l("chunks", "addresses and sizes of the chunks in the list");
+
+    let ptr = core.reader.addr_size() as u64;
+    for list in heap.lists.iter() {
+        let chunks: Vec<String> = list
+            .chunks
+            .iter()
+            .map(|c| match core.read_ptr(VirtualAddr(c.0 + ptr)) {
+                Ok(size) => format!("0x{:x} (0x{:x})", c.0, size & !0x7),
+                Err(_) => format!("0x{:x}", c.0),
+            })
+            .collect();
+        add_field!(builder, "arena", "0x{:x}", list.arena.0);
+        add_field!(builder, "bin", list.bin);
+        add_field!(builder, "count", list.chunks.len());
+        add_field!(builder, "chunks", chunks.join(" "));
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+    write_problems(out, &heap);
+}
+
+pub fn heap_chunk(mut out: impl Write, files: &ElfFiles, args: &HeapChunkArgs) {
+    let Some((core, heap)) = load_heap(files, args.arena) else {
+        return;
+    };
+
+    let addr = VirtualAddr(args.addr);
+    let Some(chunk) = heap.find_chunk(addr) else {
+        uwriteln!(out, "0x{:x} isn't within a heap chunk", addr.0);
+        return;
+    };
+
+    // User data starts after the prev_size and size fields.
+    let header = 2 * core.reader.addr_size() as u64;
+    let offset = addr.0 - chunk.addr.0;
+    uwriteln!(
+        out,
+        "0x{:x} is in the chunk at 0x{:x} (size 0x{:x}, {})",
+        addr.0,
+        chunk.addr.0,
+        chunk.size(),
+        chunk.state
+    );
+    if offset < header {
+        uwriteln!(out, "the address is within the chunk header");
+    } else {
+        uwriteln!(
+            out,
+            "the allocation starts at 0x{:x}, offset {} into it",
+            chunk.addr.0 + header,
+            offset - header
+        );
+    }
+    if chunk.state == ChunkState::InUse
+        && let Some(next) = heap
+            .chunks
+            .iter()
+            .find(|c| c.addr.0 == chunk.addr.0 + chunk.size())
+        && next.size_field & 0x1 == 0
+    {
+        uwriteln!(out, "the next chunk says this chunk is free");
+    }
+}
+
+pub fn heap_chunks(mut out: impl Write, files: &ElfFiles, args: &HeapArgs) {
+    let Some((_, heap)) = load_heap(files, args.arena) else {
+        return;
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l(
+        "chunk",
+        "address of the chunk header, user data starts two words later",
+    );
+    builder.add_col_r("size", "size of the chunk including the header (hex)");
+    builder.add_col_l(
+        "flags",
+        "P if the previous chunk is in use, M if mmapped, A if not in the main arena",
+    );
+    builder.add_col_l("state", "in use, free (and which list it's in), or top");
+
+    for chunk in heap.chunks.iter() {
+        add_field!(builder, "chunk", "0x{:x}", chunk.addr.0);
+        add_field!(builder, "size", "0x{:x}", chunk.size());
+        add_field!(builder, "flags", chunk.flags());
+        add_field!(builder, "state", chunk.state);
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+    write_problems(out, &heap);
+}
+
+fn load_heap(files: &ElfFiles, arena: Option<u64>) -> Option<(&ElfFile, Heap)> {
+    let Some(core) = files.core() else {
+        utils::warn("heap commands need a core file");
+        return None;
+    };
+    let arena = match arena {
+        Some(addr) => VirtualAddr(addr),
+        None => match heap::find_main_arena(files, core) {
+            Ok(addr) => addr,
+            Err(err) => {
+                utils::warn(&err.to_string());
+                return None;
+            }
+        },
+    };
+    match Heap::new(core, arena) {
+        Ok(heap) => Some((core, heap)),
+        Err(err) => {
+            utils::warn(&err.to_string());
+            None
+        }
+    }
+}
+
+fn write_problems(mut out: impl Write, heap: &Heap) {
+    if !heap.problems.is_empty() {
+        uwriteln!(out);
+        uwriteln!(out, "Problems:");
+        for problem in heap.problems.iter() {
+            uwriteln!(out, "   {problem}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repl::FilterArgs;
+    use crate::utils::core_builder::{CoreBuilder, Encoder};
+
+    const HEAP: u64 = 0x555555559000;
+    const ARENA: u64 = 0x7ffff7e19100;
+
+    /// Builds a core with a main_arena and a small sbrk heap. If corrupt is set then a
+    /// chunk size is overwritten with it along with a fastbin link and a bk link.
+    fn heap_files(corrupt: Option<u64>) -> ElfFiles {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let bin_at = |i: u64| ARENA + 112 + (i - 1) * 16 - 16;
+        let tcache = HEAP;
+        let in_use = HEAP + 0x290;
+        let cached = HEAP + 0x2c0;
+        let fast = HEAP + 0x2e0;
+        let unsorted = HEAP + 0x300;
+        let last = HEAP + 0x390;
+        let top = HEAP + 0x3b0;
+
+        let mut e = builder.encoder();
+        e.word(0).word(0).word(1).word(0); // mutex, flags, have_fastchunks
+        e.xword(fast);
+        for _ in 1..10 {
+            e.xword(0);
+        }
+        e.xword(top).xword(0); // top, last_remainder
+        for i in 1..128 {
+            if i == 1 {
+                e.xword(unsorted).xword(unsorted);
+            } else {
+                e.xword(bin_at(i)).xword(bin_at(i));
+            }
+        }
+        e.raw(&[0; 16]); // binmap
+        e.xword(ARENA).xword(0).xword(1).xword(0x1000).xword(0x1000);
+        let mut arena = vec![0; (ARENA & 0xfff) as usize];
+        arena.extend(e.bytes);
+        builder.load(ARENA & !0xfff, arena, 6);
+
+        let mut e = builder.encoder();
+        fn chunk(e: &mut Encoder, addr: u64, prev: u64, size: u64) {
+            assert!(e.bytes.len() <= (addr - HEAP) as usize);
+            e.bytes.resize((addr - HEAP) as usize, 0);
+            e.xword(prev).xword(size);
+        }
+        chunk(&mut e, tcache, 0, 0x291);
+        e.half(1); // counts[0]
+        e.raw(&[0; 126]);
+        e.xword(cached + 0x10); // entries[0]
+        chunk(&mut e, in_use, 0, corrupt.unwrap_or(0x31));
+        e.raw(b"hello");
+        chunk(&mut e, cached, 0, 0x21);
+        e.xword((cached + 0x10) >> 12); // safe-linking for NULL
+        chunk(&mut e, fast, 0, 0x21);
+        e.xword(if corrupt.is_some() {
+            0x4242424242424242
+        } else {
+            (fast + 0x10) >> 12
+        });
+        chunk(&mut e, unsorted, 0, 0x91);
+        e.xword(bin_at(1));
+        e.xword(if corrupt.is_some() { in_use } else { bin_at(1) });
+        chunk(&mut e, last, 0x90, 0x20);
+        chunk(&mut e, top, 0, 0xc51);
+        e.bytes.resize(0x1000, 0);
+        builder.load(HEAP, e.bytes, 6);
+
+        let path = match corrupt {
+            Some(size) => builder.write(&format!("heap-corrupt-{size:x}")),
+            None => builder.write("heap"),
+        };
+        ElfFiles::new(vec![path]).unwrap()
+    }
+
+    #[test]
+    fn heap() {
+        let files = heap_files(None);
+        let args = HeapArgs {
+            arena: Some(ARENA),
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        let mut v: Vec<u8> = Vec::new();
+        heap_arenas(&mut v, &files, &args);
+        writeln!(&mut v).unwrap();
+        heap_chunks(&mut v, &files, &args);
+        writeln!(&mut v).unwrap();
+        heap_bins(&mut v, &files, &args);
+        writeln!(&mut v).unwrap();
+
+        let args = HeapChunkArgs {
+            arena: Some(ARENA),
+            addr: HEAP + 0x2a4,
+        };
+        heap_chunk(&mut v, &files, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn heap_corrupted() {
+        // The second size would overflow when added to the chunk's address.
+        let cases = [
+            ("heap_corrupted", 0x4141414141414141),
+            ("heap_corrupted_overflow", 0xffff_ffff_ffff_fff1),
+        ];
+        for (name, size) in cases {
+            let files = heap_files(Some(size));
+            let args = HeapArgs {
+                arena: Some(ARENA),
+                explain: false,
+                titles: true,
+                filter: FilterArgs::default(),
+            };
+            let mut v: Vec<u8> = Vec::new();
+            heap_chunks(&mut v, &files, &args);
+            writeln!(&mut v).unwrap();
+            heap_bins(&mut v, &files, &args);
+
+            let s = String::from_utf8(v).unwrap();
+            let s = crate::utils::strip_escapes(&s);
+            insta::assert_snapshot!(name, s);
+        }
+    }
+}
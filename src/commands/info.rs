@@ -1,39 +1,135 @@
 use super::tables::{add_field, add_simple};
 use crate::commands::tables::{SimpleTableBuilder, TableBuilder};
-use crate::elf::VirtualAddr;
-use crate::repl::{ExplainArgs, LineArgs, RegistersArgs};
+use crate::commands::{find_variable, thread_local_addr};
+use crate::debug::{SymbolIndex, SymbolType};
+use crate::elf::{LayeredMemory, Memory, SignalDetails, VirtualAddr, audit_arch_name};
+use crate::repl::{AddressArgs, ExplainArgs, LineArgs, RegistersArgs, TlsArgs};
 use crate::utils;
 use crate::utils::{Styling, uwriteln};
 use crate::{elf::ElfFile, elf::ElfFiles, repl::TableArgs};
+use regex::Regex;
 use std::cmp::Ordering;
 use std::io::Write;
 
-fn get_file(files: &ElfFiles, exe: bool) -> &ElfFile {
-    if exe {
-        match &files.exe {
-            Some(file) => file,
+fn get_file(files: &ElfFiles, exe: bool) -> Option<&ElfFile> {
+    let file = if exe {
+        match files.exe() {
+            Some(file) => Some(file),
             None => {
-                utils::warn("--exe was used but there is no exe: using core instead");
-                files.core.as_ref().unwrap()
+                if files.core().is_some() {
+                    utils::warn("--exe was used but there is no exe: using core instead");
+                }
+                files.core()
             }
         }
     } else {
-        match &files.core {
-            Some(file) => file,
-            None => files.exe.as_ref().unwrap(),
+        files.core().or(files.exe())
+    };
+    if file.is_none() {
+        utils::warn("no files are loaded: use the file or core commands to load them");
+    }
+    file
+}
+
+pub fn info_address(mut out: impl Write, files: &ElfFiles, args: &AddressArgs) {
+    let pattern = if args.regex {
+        args.name.clone()
+    } else {
+        glob_to_regex(&args.name)
+    };
+    let re = match Regex::new(&pattern) {
+        Ok(re) => re,
+        Err(err) => {
+            utils::warn(&format!("bad pattern: {err}"));
+            return;
+        }
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l("name", "the symbol name");
+    builder.add_col_l(
+        "address",
+        "runtime address (with the load bias applied) or the offset for TLS",
+    );
+    builder.add_col_r("size", "size of the value, 0 for unknown");
+    builder.add_col_l("type", "the symbol type");
+    builder.add_col_l("binding", "linkage visibility and behavior");
+    builder.add_col_l("object", "the exe, shared library, or vDSO with the symbol");
+
+    let core = files.core();
+    let mut count = 0;
+    for (file, bias) in files.searched_objects(core) {
+        let mut rows = Vec::new();
+        let tables = [file.find_symbols(), file.find_dynamic_symbols()];
+        for table in tables.iter().flatten() {
+            for e in table.entries.iter() {
+                if matches!(e.index, SymbolIndex::Undef)
+                    || matches!(e.stype, SymbolType::Section | SymbolType::File)
+                {
+                    continue;
+                }
+                let Some(name) = file.find_string(table.section.link, e.name) else {
+                    continue;
+                };
+                if name.is_empty() || !re.is_match(&name) {
+                    continue;
+                }
+                let addr = match (&e.stype, e.index) {
+                    (SymbolType::Tls, _) | (_, SymbolIndex::Abs) => e.value,
+                    _ => e.value.wrapping_add(bias),
+                };
+                // The dynamic symbols are usually also in the static symbol table.
+                if !rows.iter().any(|(n, a, _)| *n == name && *a == addr) {
+                    rows.push((name, addr, e));
+                }
+            }
+        }
+        rows.sort_by_key(|(_, addr, _)| *addr);
+
+        for (name, addr, e) in rows {
+            add_field!(builder, "name", name);
+            if matches!(e.stype, SymbolType::Tls) {
+                add_field!(builder, "address", "tls+0x{:x}", addr);
+            } else {
+                add_field!(builder, "address", "0x{:x}", addr);
+            }
+            add_field!(builder, "size", e.size);
+            add_field!(builder, "type", "{:?}", e.stype);
+            add_field!(builder, "binding", "{:?}", e.binding);
+            add_field!(builder, "object", object_name(file));
+            count += 1;
         }
     }
+
+    if count == 0 {
+        uwriteln!(out, "No symbols match {}", args.name);
+    } else {
+        builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+    }
 }
 
 pub fn info_line(mut out: impl Write, files: &ElfFiles, args: &LineArgs) {
-    match files.find_line(VirtualAddr(args.addr)) {
-        Ok((file, line, col)) => uwriteln!(out, "{file}:{line}:{col}"),
+    let Some(core) = files.core() else {
+        match files.find_line(VirtualAddr(args.addr)) {
+            Ok((file, line, col)) => uwriteln!(out, "{file}:{line}:{col}"),
+            Err(e) => uwriteln!(out, "{e}"),
+        }
+        return;
+    };
+    match files.find_frames_in(core, VirtualAddr(args.addr)) {
+        Ok(frames) => {
+            for frame in frames {
+                uwriteln!(out, "{frame}");
+            }
+        }
         Err(e) => uwriteln!(out, "{e}"),
     }
 }
 
 pub fn info_mapped(out: impl Write, files: &ElfFiles, args: &TableArgs) {
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     if let Some(files) = file.get_memory_mapped_files() {
         let mut builder = TableBuilder::new();
         builder.add_col_l(
@@ -54,14 +150,16 @@ pub fn info_mapped(out: impl Write, files: &ElfFiles, args: &TableArgs) {
             add_field!(builder, "file name", file.file_name);
         }
 
-        builder.writeln(out, args.titles, args.explain);
+        builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
     } else {
         println!("No memory mapped files found.");
     }
 }
 
 pub fn info_process(out: impl Write, files: &ElfFiles, args: &ExplainArgs) {
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     if let Some(status) = file.find_prstatus() {
         let mut b = SimpleTableBuilder::new();
 
@@ -77,6 +175,12 @@ pub fn info_process(out: impl Write, files: &ElfFiles, args: &ExplainArgs) {
             file.path.display(),
             "path to the ELF file that was loaded"
         );
+        add_simple!(
+            b,
+            "arch",
+            status.arch.name(),
+            "the CPU architecture the process ran on"
+        );
 
         b.writeln(out, args.explain);
     } else {
@@ -84,9 +188,61 @@ pub fn info_process(out: impl Write, files: &ElfFiles, args: &ExplainArgs) {
     }
 }
 
+pub fn info_proc_mappings(out: impl Write, files: &ElfFiles, args: &TableArgs) {
+    let Some(core) = files.core() else {
+        utils::warn("mappings need a core file");
+        return;
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l(
+        "start",
+        "the virtual address of the first byte in the region",
+    );
+    builder.add_col_l(
+        "end",
+        "the virtual address after the last byte in the region",
+    );
+    builder.add_col_r("size", "the size of the region (hex)");
+    builder.add_col_r("flags", "executable, writeable, and/or readable");
+    builder.add_col_r("offset", "offset into the mapped file (hex)");
+    builder.add_col_l(
+        "in core",
+        "whether all, some, or none of the region's bytes were written to the core",
+    );
+    builder.add_col_l(
+        "kind",
+        "file, [heap], [stack tid], [vdso], [vvar], [vsyscall], or anonymous",
+    );
+    builder.add_col_l("file", "path to the mapped file");
+
+    for region in files.regions(core) {
+        add_field!(builder, "start", "{:x}", region.vbytes.start.0);
+        add_field!(builder, "end", "{:x}", region.vbytes.end().0);
+        add_field!(builder, "size", "{:x}", region.vbytes.size);
+        add_field!(builder, "flags", region.flags());
+        add_field!(builder, "in core", region.presence());
+        add_field!(builder, "kind", region.kind);
+        match region.file {
+            Some((name, offset)) => {
+                add_field!(builder, "offset", "{:x}", offset);
+                add_field!(builder, "file", name);
+            }
+            None => {
+                add_field!(builder, "offset", "");
+                add_field!(builder, "file", "");
+            }
+        }
+    }
+
+    builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
+}
+
 pub fn info_registers(mut out: impl Write, files: &ElfFiles, args: &RegistersArgs) {
     // These come out in a really annoying order so we'll sort them.
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     if let Some(status) = file.find_prstatus() {
         let mut tuples: Vec<(&'static str, u64)> = status
             .registers
@@ -134,43 +290,119 @@ pub fn info_registers(mut out: impl Write, files: &ElfFiles, args: &RegistersArg
             add_field!(builder, "decimal", value);
         }
 
-        builder.writeln(out, args.titles, args.explain);
+        builder.writeln_filtered(out, args.titles, args.explain, &args.filter);
 
         if args.explain {
-            // TODO really these are x86 only
-            utils::explain(
-                "rip",
-                "points to the instruction pointer currently being executed",
-            );
-            utils::explain(
-                "rsp",
-                "points to the bottom of the stack, local variables appear after this",
-            );
-            utils::explain(
-                "rbp",
-                "points to the top of the stack (depending on compiler options)",
-            );
+            for (name, text) in status.arch.explain_registers() {
+                utils::explain(name, text);
+            }
         }
     } else {
         uwriteln!(out, "No prstatus found");
     }
 }
 
+pub fn info_shared_library(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
+    let Some(core) = files.core() else {
+        utils::warn("need a core to find the loaded libraries");
+        return;
+    };
+    let entries = match files.link_map(core) {
+        Ok(entries) => entries,
+        Err(err) => {
+            uwriteln!(out, "Couldn't read the link map: {err}");
+            return;
+        }
+    };
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l(
+        "link_map",
+        "address of the dynamic linker's link_map struct",
+    );
+    builder.add_col_r(
+        "bias",
+        "offset from the addresses in the file to where it was loaded (l_addr)",
+    );
+    builder.add_col_r("dynamic", "address of the object's dynamic section (l_ld)");
+    builder.add_col_l("name", "path to the object, [exe] for the main executable");
+    for entry in entries {
+        add_field!(builder, "link_map", "{:x}", entry.addr.0);
+        add_field!(builder, "bias", "{:x}", entry.bias);
+        add_field!(builder, "dynamic", "{:x}", entry.dynamic.0);
+        if entry.name.is_empty() {
+            add_field!(builder, "name", "[exe]");
+        } else {
+            add_field!(builder, "name", entry.name);
+        }
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+}
+
 pub fn info_signals(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
-    let file = get_file(files, args.exe);
+    let Some(file) = get_file(files, args.exe) else {
+        return;
+    };
     let maybe_status = file.find_prstatus();
     let maybe_signal = file.find_signal_info();
 
-    if let Some(status) = &maybe_status {
-        uwriteln!(out, "{}", status.signal()); // this one does a nice job formatting signal and code
-    } else {
+    if maybe_status.is_none() {
         utils::warn("Couldn't find prstatus note");
     }
+    if let Some(info) = &maybe_signal {
+        uwriteln!(out, "{}", info.signal()); // siginfo's code is better than prstatus's
+    } else if let Some(status) = &maybe_status {
+        uwriteln!(out, "{}", status.signal());
+    }
+
+    let mut b = SimpleTableBuilder::new();
+    if let Some(info) = &maybe_signal {
+        let sender = if info.is_user_sent() {
+            "process"
+        } else {
+            "kernel"
+        };
+        add_simple!(
+            b,
+            "sent by",
+            sender,
+            "whether a process sent the signal (e.g. with kill) or the kernel generated it"
+        );
+    }
+    let errno = maybe_signal
+        .as_ref()
+        .map(|info| info.errno)
+        .or(maybe_status.as_ref().map(|status| status.errno));
+    if let Some(errno) = errno {
+        add_simple!(
+            b,
+            "errno",
+            utils::describe_errno(errno),
+            "si_errno, usually zero"
+        );
+    }
+    if let Some(syscall) = maybe_status
+        .as_ref()
+        .and_then(|status| status.arch.syscall(&status.registers))
+    {
+        add_simple!(
+            b,
+            "syscall",
+            syscall,
+            "the syscall the crashing thread was in along with its arguments"
+        );
+        add_simple!(
+            b,
+            "result",
+            syscall_result(syscall.result),
+            "the syscall's return register, an errno if negative"
+        );
+    }
 
     if let Some(info) = &maybe_signal {
-        let mut b = SimpleTableBuilder::new();
         match &info.details {
-            crate::elf::SignalDetails::Fault(details) => {
+            SignalDetails::Fault(details) => {
                 add_simple!(
                     b,
                     "fault addr",
@@ -178,8 +410,32 @@ pub fn info_signals(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
                     details.fault_addr,
                     "the address that caused the core"
                 );
+                if let Some(lsb) = details.addr_lsb {
+                    add_simple!(
+                        b,
+                        "addr lsb",
+                        lsb,
+                        "least significant bit of the address, i.e. the extent of the corruption"
+                    );
+                }
+                if let Some((lower, upper)) = details.bounds {
+                    add_simple!(
+                        b,
+                        "bounds",
+                        format!("0x{lower:x} - 0x{upper:x}"),
+                        "the address bounds that were violated"
+                    );
+                }
+                if let Some(pkey) = details.pkey {
+                    add_simple!(
+                        b,
+                        "pkey",
+                        pkey,
+                        "the memory protection key that was violated"
+                    );
+                }
             }
-            crate::elf::SignalDetails::Kill(details) => {
+            SignalDetails::Kill(details) => {
                 add_simple!(
                     b,
                     "sender pid",
@@ -193,7 +449,7 @@ pub fn info_signals(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
                     "the uid of the process that sent the signal"
                 );
             }
-            crate::elf::SignalDetails::Posix(details) => {
+            SignalDetails::Posix(details) => {
                 add_simple!(
                     b,
                     "sender pid",
@@ -206,8 +462,15 @@ pub fn info_signals(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
                     details.sender_uid,
                     "the uid of the process that sent the signal"
                 );
+                add_simple!(
+                    b,
+                    "value",
+                    "0x{:x}",
+                    details.value,
+                    "the sigval passed by the sender"
+                );
             }
-            crate::elf::SignalDetails::Child(details) => {
+            SignalDetails::Child(details) => {
                 add_simple!(
                     b,
                     "child_pid",
@@ -227,17 +490,280 @@ pub fn info_signals(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
                     "exit code of the child process"
                 );
             }
-            _ => (),
+            SignalDetails::Poll(details) => {
+                add_simple!(
+                    b,
+                    "band",
+                    "0x{:x}",
+                    details.band,
+                    "the POLL flags for the event, e.g. POLLIN is 0x1"
+                );
+                add_simple!(b, "fd", details.fd, "the file descriptor for the event");
+            }
+            SignalDetails::Sys(details) => {
+                let call = VirtualAddr(details.call_addr);
+                let name = files
+                    .core()
+                    .and_then(|core| files.describe_addr(core, call));
+                add_simple!(
+                    b,
+                    "call addr",
+                    format!("0x{:x} {}", details.call_addr, name.unwrap_or_default()),
+                    "address of the syscall instruction"
+                );
+                let name = maybe_status
+                    .as_ref()
+                    .and_then(|status| status.arch.syscall_name(details.syscall as u64));
+                add_simple!(
+                    b,
+                    "blocked",
+                    format!("{} {}", details.syscall, name.unwrap_or_default()),
+                    "the syscall that was blocked"
+                );
+                add_simple!(
+                    b,
+                    "arch",
+                    format!(
+                        "0x{:x} {}",
+                        details.arch,
+                        audit_arch_name(details.arch).unwrap_or_default()
+                    ),
+                    "the AUDIT_ARCH for the syscall, differs from the core for 32-bit syscalls on 64-bit kernels"
+                );
+            }
+            SignalDetails::Timer(details) => {
+                add_simple!(b, "timer id", details.timer_id, "the kernel's timer id");
+                add_simple!(
+                    b,
+                    "overrun",
+                    details.overrun,
+                    "number of expirations that were missed"
+                );
+                add_simple!(
+                    b,
+                    "value",
+                    "0x{:x}",
+                    details.value,
+                    "the sigval from the timer's sigevent"
+                );
+            }
         }
-        b.writeln(out, args.explain);
     } else {
         utils::warn("Couldn't find signal note");
     }
+    b.writeln(out, args.explain);
+}
+
+pub fn info_symbol(mut out: impl Write, files: &ElfFiles, args: &LineArgs) {
+    let addr = VirtualAddr(args.addr);
+    let core = files.core();
+    let mut found = false;
+    for (file, bias) in files.searched_objects(core) {
+        let value = addr.0.wrapping_sub(bias);
+        let Some((name, e)) = file.find_symbol_containing(value) else {
+            continue;
+        };
+        let offset = value - e.value;
+        let section = match e.index {
+            SymbolIndex::Index(index) => file.find_section_name(index),
+            _ => None,
+        };
+        let name = if offset == 0 {
+            name
+        } else {
+            format!("{name}+0x{offset:x}")
+        };
+        uwriteln!(
+            out,
+            "{name} in {} (section {}, {:?} {:?})",
+            object_name(file),
+            section.as_deref().unwrap_or("?"),
+            e.binding,
+            e.stype
+        );
+        found = true;
+    }
+
+    if !found {
+        match core.and_then(|core| files.describe_addr(core, addr)) {
+            Some(name) => uwriteln!(out, "No symbol matches 0x{:x}, it's in {name}", addr.0),
+            None => uwriteln!(out, "No symbol matches 0x{:x}", addr.0),
+        }
+    }
+}
+
+/// Converts a pattern using * and ? wildcards into an anchored regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = "^".to_string();
+    for ch in glob.chars() {
+        match ch {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// File name of an exe, shared library, or the vDSO.
+fn object_name(file: &ElfFile) -> String {
+    file.path.file_name().map_or_else(
+        || file.path.to_string_lossy().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+pub fn info_tls(mut out: impl Write, files: &ElfFiles, args: &TlsArgs) {
+    let Some(core) = files.core() else {
+        utils::warn("need a core to find thread local storage");
+        return;
+    };
+    let Some(thread) = core.find_thread(args.tid) else {
+        utils::warn("couldn't find the thread");
+        return;
+    };
+    let modules = match files.tls_modules(core, &thread) {
+        Ok(modules) => modules,
+        Err(err) => {
+            uwriteln!(out, "Couldn't find the TLS blocks: {err}");
+            return;
+        }
+    };
+    if modules.is_empty() {
+        uwriteln!(out, "No objects use thread local storage.");
+        return;
+    }
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_r("module", "TLS module id, an index into the thread's DTV");
+    builder.add_col_l(
+        "address",
+        "address of the thread's block for the module, blank if it isn't allocated",
+    );
+    builder.add_col_r("size", "size of the block (hex)");
+    builder.add_col_r(
+        "init",
+        "bytes initialized from .tdata (hex), the rest is zeroed .tbss",
+    );
+    builder.add_col_r("align", "alignment of the block");
+    builder.add_col_l("name", "path to the object, [exe] for the main executable");
+    for module in modules {
+        add_field!(builder, "module", module.id);
+        match module.block {
+            Some(block) => {
+                add_field!(builder, "address", "{:x}", block.0);
+            }
+            None => {
+                add_field!(builder, "address", "");
+            }
+        }
+        add_field!(builder, "size", "{:x}", module.segment.mem_size);
+        add_field!(builder, "init", "{:x}", module.segment.file_size);
+        add_field!(builder, "align", module.segment.align);
+        if module.name.is_empty() {
+            add_field!(builder, "name", "[exe]");
+        } else {
+            add_field!(builder, "name", module.name);
+        }
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+}
+
+pub fn info_threads(mut out: impl Write, files: &ElfFiles, args: &TableArgs) {
+    let Some(core) = files.core() else {
+        utils::warn("need a core to show threads");
+        return;
+    };
+
+    // errno is a thread local in libc so we can only show it if we can find libc.
+    let errno = find_variable(files, core, "errno")
+        .filter(|(symbol, _)| matches!(symbol.stype, SymbolType::Tls));
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_r("tid", "thread id");
+    builder.add_col_l(
+        "signal",
+        "signal that the thread received, blank for most threads",
+    );
+    builder.add_col_l(
+        "ip",
+        "instruction pointer along with the function it's within",
+    );
+    builder.add_col_l(
+        "syscall",
+        "syscall the thread was in (or making) with the raw arguments",
+    );
+    builder.add_col_l(
+        "result",
+        "syscall's return register, -ERESTARTSYS and friends mean the syscall was interrupted",
+    );
+    if errno.is_some() {
+        builder.add_col_l("errno", "the thread's errno variable");
+    }
+    let memory = LayeredMemory::new(files, core);
+    for status in core.find_prstatuses() {
+        add_field!(builder, "tid", status.pid);
+        if status.signal_num != 0 {
+            add_field!(builder, "signal", status.signal());
+        } else {
+            add_field!(builder, "signal", "");
+        }
+        let ip = VirtualAddr(status.registers[status.arch.ip_index()]);
+        match files.describe_addr(core, ip) {
+            Some(name) => {
+                add_field!(builder, "ip", "{:x} {name}", ip.0);
+            }
+            None => {
+                add_field!(builder, "ip", "{:x}", ip.0);
+            }
+        }
+        match status.arch.syscall(&status.registers) {
+            Some(syscall) => {
+                add_field!(builder, "syscall", syscall);
+                add_field!(builder, "result", syscall_result(syscall.result));
+            }
+            None => {
+                add_field!(builder, "syscall", "");
+                add_field!(builder, "result", "");
+            }
+        }
+        if let Some((symbol, bias)) = &errno {
+            let value = thread_local_addr(files, core, &status, *bias, symbol.value)
+                .ok()
+                .flatten()
+                .and_then(|addr| memory.read_u32(addr).ok());
+            match value {
+                Some(value) => {
+                    add_field!(builder, "errno", utils::describe_errno(value as i32));
+                }
+                None => {
+                    add_field!(builder, "errno", "");
+                }
+            }
+        }
+    }
+
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+}
+
+/// Syscalls return small negative numbers for errors.
+fn syscall_result(result: i64) -> String {
+    if (-4095..0).contains(&result)
+        && let Some(name) = utils::errno_name(-result as i32)
+    {
+        format!("-{name}")
+    } else {
+        format!("0x{result:x}")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repl::FilterArgs;
+    use crate::utils::core_builder::CoreBuilder;
     use crate::utils::{debug_results, do_test, release_results};
 
     #[test]
@@ -256,16 +782,65 @@ mod tests {
         do_test!(info_line, &args);
     }
 
+    #[test]
+    fn line_inlined() {
+        let args = LineArgs {
+            addr: 0x55d1a782211c, // printf inlined into main
+        };
+        let mut v: Vec<u8> = Vec::new();
+        release_results!(v, info_line, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn line_library() {
+        let args = LineArgs {
+            addr: 0x7ff8fc2ceb25, // within ld.so so the exe's line info doesn't apply
+        };
+        let mut v: Vec<u8> = Vec::new();
+        debug_results!(v, info_line, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
     #[test]
     fn mapped() {
         let args = TableArgs {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(info_mapped, &args);
     }
 
+    #[test]
+    fn shared_library() {
+        let args = TableArgs {
+            exe: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(info_shared_library, &args);
+    }
+
+    #[test]
+    fn tls() {
+        let args = TlsArgs {
+            tid: None,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(info_tls, &args);
+    }
+
     #[test]
     fn process() {
         let args = ExplainArgs {
@@ -275,6 +850,17 @@ mod tests {
         do_test!(info_process, &args);
     }
 
+    #[test]
+    fn proc_mappings() {
+        let args = TableArgs {
+            exe: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(info_proc_mappings, &args);
+    }
+
     #[test]
     fn registers() {
         let args = RegistersArgs {
@@ -282,6 +868,7 @@ mod tests {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(info_registers, &args);
     }
@@ -293,6 +880,7 @@ mod tests {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(info_registers, &args);
     }
@@ -303,7 +891,133 @@ mod tests {
             exe: false,
             explain: false,
             titles: true,
+            filter: FilterArgs::default(),
         };
         do_test!(info_signals, &args);
     }
+
+    #[test]
+    fn signals_seccomp() {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut registers = vec![0; 27];
+        registers[10] = -38i64 as u64; // rax, ENOSYS
+        registers[15] = 59; // orig_rax, execve
+        registers[16] = 0x401000; // rip
+        builder.prstatus(31, 1, 42, &registers);
+
+        let mut e = builder.encoder();
+        e.word(31).word(0).word(1).word(0); // SIGSYS, errno, SYS_SECCOMP, padding
+        e.xword(0x400ffe).word(59).word(0xc000003e); // call_addr, syscall, AUDIT_ARCH_X86_64
+        e.raw(&[0; 96]);
+        builder.note("CORE", 0x53494749, e.bytes);
+        let files = ElfFiles::new(vec![builder.write("seccomp")]).unwrap();
+
+        let args = TableArgs {
+            exe: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        let mut v: Vec<u8> = Vec::new();
+        info_signals(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn threads() {
+        let args = TableArgs {
+            exe: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        do_test!(info_threads, &args);
+    }
+
+    #[test]
+    fn threads_errno() {
+        let files = crate::utils::core_builder::tls_files();
+        let args = TableArgs {
+            exe: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        let mut v: Vec<u8> = Vec::new();
+        info_threads(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn library_symbols() {
+        let files = crate::utils::core_builder::tls_files();
+        let mut v: Vec<u8> = Vec::new();
+        let args = AddressArgs {
+            name: "lib_*".to_string(),
+            regex: false,
+            explain: false,
+            titles: false,
+            filter: FilterArgs::default(),
+        };
+        info_address(&mut v, &files, &args);
+        info_symbol(
+            &mut v,
+            &files,
+            &LineArgs {
+                addr: 0x7f00000000b3,
+            },
+        );
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        let s = s.replace(&format!("udb-{}-", std::process::id()), "udb-PID-");
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn symbol() {
+        let mut v: Vec<u8> = Vec::new();
+        for addr in [
+            0x55957a4931c8,
+            0x55957a496018,
+            0x55957a4930c0,
+            0x55957a492010,
+        ] {
+            debug_results!(v, info_symbol, &LineArgs { addr });
+        }
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn address() {
+        let mut v: Vec<u8> = Vec::new();
+        let args = AddressArgs {
+            name: "*_cart".to_string(),
+            regex: false,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        debug_results!(v, info_address, &args);
+        writeln!(&mut v).unwrap();
+
+        let args = AddressArgs {
+            name: "^get_(cost|name)$".to_string(),
+            regex: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
+        };
+        debug_results!(v, info_address, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
 }
@@ -1,342 +1,412 @@
-use crate::elf::{ElfFile, LoadSegment, Offset, VirtualAddr};
-use crate::repl::HexdumpLabels;
-use crate::utils::{uwrite, uwriteln};
-use crate::{
-    elf::{ElfFiles, Reader},
-    repl::{FindArgs, HexdumpArgs},
-    utils,
+use super::tables::add_field;
+use crate::arch::{CallSite, FrameLayout};
+use crate::commands::tables::TableBuilder;
+use crate::debug::{SymbolIndex, SymbolTableEntry, SymbolType};
+use crate::elf::{
+    EXECUTE_FLAG, ElfFile, LayeredMemory, Memory, MemoryError, MemoryLayer, Offset, PrStatus,
+    VirtualAddr, hex_dump,
 };
+use crate::repl::{BacktraceArgs, HexdumpLabels, PrintArgs};
+use crate::utils::{Styling, uwriteln};
+use crate::{elf::ElfFiles, repl::HexdumpArgs, utils};
 use std::error::Error;
 use std::io::Write;
 
-/// Returns pointers to the instructions within the functions in the current call chain.
-fn raw_backtrace(files: &ElfFiles) -> Result<Vec<VirtualAddr>, Box<dyn Error>> {
-    // TODO move this into debug module
-    // see https://eli.thegreenplace.net/2011/09/06/stack-frame-layout-on-x86-64
-    let mut bt = Vec::new();
-    if let Some(status) = files.find_prstatus() {
-        let addr = status.get_ip();
-        bt.push(addr);
-
-        let mut rbp = status.get_frame_stack_top(); // TODO won't work for release
-        if let Some(load) = files.find_load_segment(rbp)
-            && load.writeable()
-        {
-            // we expect stack to be within one segment
-            // TODO could do some validation here but I think we want to be fairly permissive
-            while let Some(offset) = load.to_offset(rbp) {
-                rbp = VirtualAddr::from_raw(
-                    files
-                        .core
-                        .as_ref()
-                        .unwrap() // safe because find_prstatus worked
-                        .reader
-                        .read_xword(offset)
-                        .unwrap(),
-                );
+/// A stack frame found by following frame pointers.
+pub struct RawFrame {
+    /// The instruction being executed, or the return address for callers.
+    pub ip: VirtualAddr,
 
-                let addr = VirtualAddr::from_raw(
-                    files
-                        .core
-                        .as_ref()
-                        .unwrap()
-                        .reader
-                        .read_xword(offset + 8)
-                        .unwrap(),
-                );
-                bt.push(addr);
-            }
-        } else {
-            return Err("Couldn't find load segment".into());
-        }
-    } else {
-        return Err("Couldn't find prstatus".into());
-    }
-    Ok(bt)
+    /// The frame pointer for the function, e.g. rbp. The function's locals are below this.
+    pub fp: VirtualAddr,
 }
 
-pub fn backtrace(mut out: impl Write, files: &ElfFiles) {
-    match raw_backtrace(files) {
-        Ok(bt) => bt.iter().for_each(|a| match files.find_line(*a) {
-            Ok((file, line, col)) => uwriteln!(out, "0x{:x} {file}:{line}:{col}", a.0),
-            Err(_) => uwriteln!(out, "0x{:x}", a.0),
-        }),
-        Err(e) => uwriteln!(out, "{e}"),
-    }
+/// Returns pointers to the instructions within the functions in the current call chain.
+pub fn raw_backtrace(core: &ElfFile) -> Result<Vec<VirtualAddr>, Box<dyn Error>> {
+    let Some(status) = core.find_prstatus() else {
+        return Err("Couldn't find prstatus".into());
+    };
+    Ok(raw_frames(core, &status)?.iter().map(|f| f.ip).collect())
 }
 
-pub fn find(out: impl Write, files: &ElfFiles, args: &FindArgs) {
-    fn match_bytes(reader: &Reader, i: usize, bytes: &[u8]) -> bool {
-        for (j, byte) in bytes.iter().enumerate() {
-            let offset = Offset::from_raw((i + j) as u64);
-            match reader.read_byte(offset) {
-                Ok(b) => {
-                    if b != *byte {
-                        return false;
+/// Returns the call chain for a thread, starting with the innermost frame.
+pub fn raw_frames(core: &ElfFile, status: &PrStatus) -> Result<Vec<RawFrame>, Box<dyn Error>> {
+    // TODO move this into debug module
+    // see https://eli.thegreenplace.net/2011/09/06/stack-frame-layout-on-x86-64
+    let mut frames = Vec::new();
+    let mut rbp = status.get_frame_stack_top(); // TODO won't work for release
+    frames.push(RawFrame {
+        ip: status.get_ip(),
+        fp: rbp,
+    });
+
+    if let Some(load) = core.find_load_segment(rbp)
+        && load.writeable()
+    {
+        // we expect stack to be within one segment
+        // TODO could do some validation here but I think we want to be fairly permissive
+        let on_stack = |addr: VirtualAddr| load.vbytes.contains(addr);
+        let ptr_size = core.reader.addr_size() as i64;
+        match status.arch.frame_layout(ptr_size) {
+            FrameLayout::Record { caller_fp, ret } => {
+                while on_stack(rbp + caller_fp) && on_stack(rbp + ret) {
+                    let (Ok(fp), Ok(addr)) =
+                        (core.read_ptr(rbp + caller_fp), core.read_ptr(rbp + ret))
+                    else {
+                        break;
+                    };
+                    let prev = rbp;
+                    rbp = VirtualAddr::from_raw(fp);
+                    frames.push(RawFrame {
+                        ip: status.strip_pac(VirtualAddr::from_raw(addr)),
+                        fp: rbp,
+                    });
+                    if rbp <= prev {
+                        break; // the stack grows down so callers are above us
                     }
                 }
-                Err(_) => return false,
             }
-        }
-        true
-    }
-
-    fn search_load_segments(mut out: impl Write, core: &ElfFile, args: &FindArgs, bytes: &[u8]) {
-        let mut count = 0;
-        for load in core.loads.iter() {
-            let mut i = 0;
-            while i + bytes.len() < load.obytes.size {
-                if match_bytes(core.reader, i + load.obytes.start.0 as usize, bytes) {
-                    uwriteln!(out, "0x{:x}", i + load.vbytes.start.0 as usize);
-                    if args.count > 0 {
-                        hexdump_segment(
-                            &mut out,
-                            core,
-                            &HexdumpArgs {
-                                value: i as u64 + load.vbytes.start.0,
-                                offset: false,
-                                count: args.count,
-                                labels: HexdumpLabels::None,
-                                exe: false,
-                            },
-                            load,
-                        );
-                        uwriteln!(out);
+            FrameLayout::BackChain { ret } => {
+                while on_stack(rbp) {
+                    let Ok(fp) = core.read_ptr(rbp) else {
+                        break;
+                    };
+                    let prev = rbp;
+                    rbp = VirtualAddr::from_raw(fp);
+                    if !on_stack(rbp + ret) {
+                        break;
                     }
-                    i += bytes.len();
-                    count += 1;
-                    if count == args.max_results {
-                        uwriteln!(out, "...");
-                        return;
+                    let Ok(addr) = core.read_ptr(rbp + ret) else {
+                        break;
+                    };
+                    frames.push(RawFrame {
+                        ip: status.strip_pac(VirtualAddr::from_raw(addr)),
+                        fp: rbp,
+                    });
+                    if rbp <= prev {
+                        break;
                     }
-                } else {
-                    i += 1;
+                }
+            }
+            FrameLayout::None => {
+                if let Some(lr) = status.get_link_register() {
+                    frames.push(RawFrame { ip: lr, fp: rbp });
                 }
             }
         }
+    } else if let Some(lr) = status.get_link_register() {
+        // Probably a leaf function that didn't bother setting up a frame.
+        frames.push(RawFrame { ip: lr, fp: rbp });
+    } else {
+        return Err("Couldn't find load segment".into());
     }
+    Ok(frames)
+}
 
-    fn search_all(
-        out: &mut impl Write,
-        prefix: &str,
-        file: &ElfFile,
-        args: &FindArgs,
-        bytes: &[u8],
-    ) {
-        let mut count = 0;
-        let mut offset = Offset::from_raw(0);
-        let mut offsets = Vec::new(); // we'll print addresses first
-
-        let mut found_addr = false;
-        while offset.0 as usize + bytes.len() < file.reader.len() {
-            if match_bytes(file.reader, offset.0 as usize, bytes) {
-                match file.offset_to_vaddr(offset) {
-                    Some((load, addr)) => {
-                        if !found_addr {
-                            uwriteln!(out, "{prefix}Addresses:");
-                            found_addr = true;
-                        }
-                        uwriteln!(out, "   0x{:x}", addr.0);
-
-                        if args.count > 0 {
-                            uwrite!(out, "   ");
-                            hexdump_segment(
-                                out,
-                                file,
-                                &HexdumpArgs {
-                                    value: addr.0,
-                                    offset: false,
-                                    exe: false,
-                                    count: args.count,
-                                    labels: HexdumpLabels::None,
-                                },
-                                load,
-                            );
-                            uwriteln!(out);
-                        }
-                        count += 1;
-                        if count == args.max_results {
-                            uwriteln!(out, "   ...");
-                            return;
-                        }
+pub fn backtrace(mut out: impl Write, files: &ElfFiles) {
+    let Some(core) = files.core() else {
+        uwriteln!(out, "Couldn't find prstatus");
+        return;
+    };
+    match raw_backtrace(core) {
+        Ok(bt) => bt
+            .iter()
+            .for_each(|a| match files.find_frames_in(core, *a) {
+                Ok(frames) => {
+                    for frame in frames {
+                        uwriteln!(out, "0x{:x} {frame}", a.0);
                     }
-                    None => offsets.push(offset), // we'll print these later
                 }
-                offset = offset + bytes.len() as i64;
-            } else {
-                offset = offset + 1;
-            }
+                Err(_) => match core.describe_vdso_addr(*a) {
+                    Some(name) => uwriteln!(out, "0x{:x} {name}", a.0),
+                    None => uwriteln!(out, "0x{:x}", a.0),
+                },
+            }),
+        Err(e) => uwriteln!(out, "{e}"),
+    }
+}
+
+/// Used when frame pointers don't work: looks for return addresses on the stack, i.e.
+/// pointers into executable memory that are preceded by a call instruction.
+pub fn backtrace_scan(mut out: impl Write, files: &ElfFiles, args: &BacktraceArgs) {
+    let Some(core) = files.core() else {
+        uwriteln!(out, "Couldn't find prstatus");
+        return;
+    };
+    let Some(status) = core.find_prstatus() else {
+        uwriteln!(out, "Couldn't find prstatus");
+        return;
+    };
+    if !status.arch.decodes_calls() {
+        uwriteln!(out, "bt --scan is not supported on {}", status.arch.name());
+        return;
+    }
+    let sp = status.get_frame_stack_bottom();
+    let Some(stack) = core.find_load_segment(sp) else {
+        uwriteln!(out, "Couldn't find the stack for sp 0x{:x}", sp.0);
+        return;
+    };
+    let executable: Vec<_> = files
+        .regions(core)
+        .into_iter()
+        .filter(|r| r.flags & EXECUTE_FLAG != 0)
+        .map(|r| r.vbytes)
+        .collect();
+    let memory = LayeredMemory::new(files, core);
+    let ptr_size = core.reader.addr_size() as i64;
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_l(
+        "stack",
+        "address of the stack slot holding the return address",
+    );
+    builder.add_col_r("offset", "offset of the slot from the stack pointer (hex)");
+    builder.add_col_l(
+        "return",
+        "the return address along with the function it's within",
+    );
+    builder.add_col_l(
+        "confidence",
+        "high if a direct call targets the function of the frame below, medium for other \
+         direct calls, low for indirect calls",
+    );
+
+    let mut callee = function_start(files, core, status.get_ip());
+    let mut addr = sp;
+    while stack.vbytes.contains(addr + (ptr_size - 1)) {
+        let slot = addr;
+        addr = addr + ptr_size;
+        let Ok(value) = core.read_ptr(slot) else {
+            break;
+        };
+        let ret = status.strip_pac(VirtualAddr(value));
+        if !executable.iter().any(|r| r.contains(ret)) {
+            continue;
         }
+        let Some(call) = call_before(&memory, &status, ret) else {
+            continue;
+        };
+        let confidence = match call {
+            CallSite::Direct { target } if callee == Some(target) => "high",
+            CallSite::Direct { target }
+                if executable.iter().any(|r| r.contains(VirtualAddr(target))) =>
+            {
+                "medium"
+            }
+            CallSite::Direct { .. } => continue, // calls to garbage aren't calls
+            CallSite::Indirect => "low",
+        };
+        callee = function_start(files, core, ret);
 
-        if !offsets.is_empty() {
-            count = 0;
-            uwriteln!(out, "{prefix}Offsets:");
-            for offset in offsets.iter() {
-                uwriteln!(out, "   0x{:x}", offset.0);
-
-                if args.count > 0 {
-                    uwrite!(out, "   ");
-                    file.reader
-                        .hex_dump(out, 0, *offset, args.count, HexdumpLabels::None);
-                    uwriteln!(out);
-                }
-                count += 1;
-                if count == args.max_results {
-                    uwriteln!(out, "   ...");
-                    return;
-                }
+        add_field!(builder, "stack", "{:x}", slot.0);
+        add_field!(builder, "offset", "{:x}", slot.0 - sp.0);
+        match files.describe_addr(core, ret) {
+            Some(name) => {
+                add_field!(builder, "return", "{:x} {name}", ret.0);
+            }
+            None => {
+                add_field!(builder, "return", "{:x}", ret.0);
             }
         }
+        add_field!(builder, "confidence", confidence);
     }
+    builder.writeln_filtered(&mut out, args.titles, args.explain, &args.filter);
+}
 
-    fn find(mut out: impl Write, files: &ElfFiles, args: &FindArgs, bytes: &[u8]) {
-        if args.all {
-            if let Some(core) = &files.core
-                && let Some(exe) = &files.exe
-            {
-                search_all(&mut out, "Core ", core, args, bytes);
-                search_all(&mut out, "Exe ", exe, args, bytes);
-            } else if let Some(core) = &files.core {
-                search_all(&mut out, "", core, args, bytes);
-            } else {
-                search_all(&mut out, "", files.exe.as_ref().unwrap(), args, bytes); // safe because we'll always have either core or exe
+/// Checks whether the bytes before ret form a call instruction. Calls can be up to seven
+/// bytes on x86 so we read that many but fall back to fewer at the start of a mapping.
+fn call_before(memory: &impl Memory, status: &PrStatus, ret: VirtualAddr) -> Option<CallSite> {
+    (2..=7).rev().find_map(|len| {
+        let code = memory.read(ret + -(len as i64), len).ok()?;
+        status.arch.call_before(&code, ret.0)
+    })
+}
+
+/// Returns the address of the symbol addr is within.
+fn function_start(files: &ElfFiles, core: &ElfFile, addr: VirtualAddr) -> Option<u64> {
+    files
+        .symbol_objects(Some(core))
+        .into_iter()
+        .find_map(|(file, bias)| {
+            file.find_symbol_containing(addr.0.wrapping_sub(bias))
+                .map(|(_, symbol)| symbol.value.wrapping_add(bias))
+        })
+}
+
+pub fn print(mut out: impl Write, files: &ElfFiles, args: &PrintArgs) {
+    let Some(core) = files.core() else {
+        utils::warn("need a core to print variables");
+        return;
+    };
+    let Some((symbol, bias)) = find_variable(files, core, &args.name) else {
+        uwriteln!(out, "No symbol named {}", args.name);
+        return;
+    };
+
+    let addr = if matches!(symbol.stype, SymbolType::Tls) {
+        let Some(thread) = core.find_thread(args.tid) else {
+            utils::warn("couldn't find the thread");
+            return;
+        };
+        match thread_local_addr(files, core, &thread, bias, symbol.value) {
+            Ok(Some(addr)) => addr,
+            Ok(None) => {
+                uwriteln!(
+                    out,
+                    "The thread's TLS block for {} isn't allocated",
+                    args.name
+                );
+                return;
             }
-        } else if let Some(core) = &files.core {
-            search_load_segments(out, core, args, bytes);
-        } else {
-            // Technically we should only do this if --all is used but it's kind of
-            // silly to not do a search if all we have is an exe.
-            search_all(&mut out, "", files.exe.as_ref().unwrap(), args, bytes);
+            Err(err) => {
+                uwriteln!(out, "Couldn't find the TLS blocks: {err}");
+                return;
+            }
+        }
+    } else {
+        VirtualAddr(symbol.value.wrapping_add(bias))
+    };
+
+    let memory = LayeredMemory::new(files, core);
+    let value = format_value(&memory, addr, symbol.size);
+    uwriteln!(out, "{} = {value} (at 0x{:x})", args.name, addr.0);
+}
+
+/// Returns the address of a thread local variable given the load bias of its object and
+/// its offset within the object's TLS block. None if the thread's block wasn't allocated.
+pub fn thread_local_addr(
+    files: &ElfFiles,
+    core: &ElfFile,
+    thread: &PrStatus,
+    bias: u64,
+    offset: u64,
+) -> Result<Option<VirtualAddr>, Box<dyn Error>> {
+    let modules = files.tls_modules(core, thread)?;
+    let block = modules
+        .into_iter()
+        .find(|m| m.bias == bias)
+        .and_then(|m| m.block);
+    Ok(block.map(|b| b + offset as i64))
+}
+
+/// Finds a defined symbol in the loaded files or, failing that, in the libraries the
+/// process loaded. Also returns the load bias for the symbol's object.
+pub fn find_variable(
+    files: &ElfFiles,
+    core: &ElfFile,
+    name: &str,
+) -> Option<(SymbolTableEntry, u64)> {
+    let defined = |file: &ElfFile| {
+        file.find_symbol(name)
+            .filter(|e| !matches!(e.index, SymbolIndex::Undef))
+    };
+    for (file, bias) in files.symbol_objects(Some(core)) {
+        if let Some(symbol) = defined(file) {
+            return Some((symbol, bias));
         }
     }
 
-    // TODO there are probably crates with better substring algorithms
-    // TODO might also help to read words at a time where possible
-    if let Some(s) = &args.hex {
-        match byte_str_to_vec(s) {
-            Ok(bytes) => find(out, files, args, &bytes),
-            Err(err) => utils::warn(&err.to_string()),
+    files
+        .link_map_objects(core)
+        .into_iter()
+        .find_map(|(file, bias)| defined(file).map(|symbol| (symbol, bias)))
+}
+
+/// Formats a variable as an integer if it's a power of two up to eight bytes, otherwise
+/// as hex bytes. A size of zero is treated as a pointer.
+pub fn format_value(memory: &impl Memory, addr: VirtualAddr, size: u64) -> String {
+    let size = if size == 0 {
+        memory.addr_size()
+    } else {
+        size as usize
+    };
+    match memory.read(addr, size.min(16)) {
+        Ok(bytes) if size <= 8 && size.is_power_of_two() => {
+            format!("0x{:x}", memory.endian().decode(&bytes))
+        }
+        Ok(bytes) => {
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            let suffix = if size > bytes.len() { " ..." } else { "" };
+            format!("{}{suffix}", hex.join(" "))
         }
-    } else if let Some(s) = &args.string {
-        let bytes = ascii_str_to_vec(s);
-        find(out, files, args, &bytes);
+        Err(MemoryError::Unmapped(_)) => "not mapped".to_string(),
+        Err(MemoryError::NotInCore(_)) => "not in core".to_string(),
     }
 }
 
-pub fn hexdump(mut out: impl Write, files: &ElfFiles, args: &HexdumpArgs) {
+pub fn hexdump(out: impl Write, files: &ElfFiles, args: &HexdumpArgs) {
     if args.offset {
         if args.exe {
-            match &files.exe {
-                Some(file) => hexdump_any(out, file, Offset(args.value), args.count, args.labels),
+            match files.exe() {
+                Some(file) => hexdump_any(out, file, Offset(args.value), args),
                 None => utils::warn("--exe was used but there is no exe"),
             }
         } else {
-            match &files.core {
-                Some(file) => hexdump_any(out, file, Offset(args.value), args.count, args.labels),
-                None => hexdump_any(
-                    out,
-                    files.exe.as_ref().unwrap(),
-                    Offset(args.value),
-                    args.count,
-                    args.labels,
-                ),
+            match files.core().or(files.exe()) {
+                Some(file) => hexdump_any(out, file, Offset(args.value), args),
+                None => utils::warn("no files are loaded"),
             }
         }
+    } else if args.exe {
+        match files.exe() {
+            Some(exe) => hexdump_memory(out, exe, args),
+            None => utils::warn("--exe was used but there is no exe"),
+        }
+    } else if let Some(core) = files.core() {
+        hexdump_memory(out, &LayeredMemory::new(files, core), args);
     } else {
-        let vaddr = VirtualAddr::from_raw(args.value);
-        if args.exe {
-            match &files.exe {
-                Some(file) => match file.find_load_segment(vaddr) {
-                    Some(load) => hexdump_segment(&mut out, file, args, load),
-                    None => utils::warn("--couldn't find a load segment for the address"),
-                },
-                None => utils::warn("--exe was used but there is no exe"),
-            }
-        } else {
-            match &files.core {
-                Some(file) => match file.find_load_segment(vaddr) {
-                    Some(load) => hexdump_segment(&mut out, file, args, load),
-                    None => utils::warn("couldn't find a load segment for the address"),
-                },
-                None => {
-                    let file = files.exe.as_ref().unwrap();
-                    match file.find_load_segment(vaddr) {
-                        Some(load) => hexdump_segment(&mut out, file, args, load),
-                        None => utils::warn("couldn't find a load segment for the address"),
-                    }
-                }
-            }
+        match files.exe() {
+            Some(exe) => hexdump_memory(out, exe, args),
+            None => utils::warn("no files are loaded"),
         }
     }
 }
 
-pub fn hexdump_segment(
-    out: &mut impl Write,
-    file: &ElfFile,
-    args: &HexdumpArgs,
-    load: &LoadSegment,
-) {
-    let vaddr = VirtualAddr::from_raw(args.value);
-    if let Some(offset) = load.to_offset(vaddr) {
-        file.reader
-            .hex_dump(out, args.value, offset, args.count, args.labels);
+/// Dumps memory starting at an address. If some of the bytes weren't in the core then
+/// this also says where they came from.
+pub fn hexdump_memory(mut out: impl Write, memory: &impl Memory, args: &HexdumpArgs) {
+    let read = memory.read_partial(VirtualAddr(args.value), args.count);
+    hex_dump(
+        &mut out,
+        memory.endian(),
+        args.value,
+        &read.bytes,
+        args.width,
+        args.labels,
+    );
+
+    if read
+        .layers
+        .iter()
+        .any(|(_, layer)| *layer != MemoryLayer::Core)
+    {
+        uwriteln!(out);
+        uwriteln!(out, "Read from:");
+        let mut addr = args.value;
+        for (size, layer) in read.layers.iter() {
+            uwriteln!(out, "   0x{:x}..0x{:x} {layer}", addr, addr + *size as u64);
+            addr += *size as u64;
+        }
+    }
+    if let Some(err) = read.error {
+        utils::warn(&err.to_string());
     }
 }
 
-pub fn hexdump_any(
-    mut out: impl Write,
-    file: &ElfFile,
-    offset: Offset,
-    count: usize,
-    labels: HexdumpLabels,
-) {
-    if labels == HexdumpLabels::Addr {
+pub fn hexdump_any(mut out: impl Write, file: &ElfFile, offset: Offset, args: &HexdumpArgs) {
+    if args.labels == HexdumpLabels::Addr {
         utils::warn("Can't use --labels=address when dumping by offset");
     } else {
-        file.reader.hex_dump(&mut out, 0, offset, count, labels);
-    }
-}
-
-fn ascii_str_to_vec(str: &str) -> Vec<u8> {
-    let mut result = Vec::new();
-
-    for ch in str.chars() {
-        let mut buffer = [0; 4]; // 4 is always large enough
-        let n = ch.encode_utf8(&mut buffer).len();
-        result.extend(buffer.iter().take(n));
-    }
-
-    result
-}
-
-fn byte_str_to_vec(str: &str) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut result = Vec::new();
-
-    let mut i = 0;
-    let chars: Vec<char> = str.chars().collect();
-    while i < chars.len() {
-        if chars[i] == ' ' {
-            // ignore spaces
-            i += 1;
-        } else if i + 1 < chars.len()
-            && chars[i].is_ascii_hexdigit()
-            && chars[i + 1].is_ascii_hexdigit()
-        {
-            let s = format!("{}{}", chars[i], chars[i + 1]);
-            let byte = u8::from_str_radix(&s, 16).unwrap();
-            result.push(byte);
-            i += 2;
-        } else {
-            return Err("Expected a string of hex bytes with optional spaces between bytes".into());
-        }
+        file.reader
+            .hex_dump(&mut out, 0, offset, args.count, args.width, args.labels);
     }
-
-    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repl::FilterArgs;
+    use crate::utils::core_builder::CoreBuilder;
     use crate::utils::{debug_results, do_test, release_results};
 
     #[test]
@@ -345,63 +415,138 @@ mod tests {
     }
 
     #[test]
-    fn find_default_str() {
-        let args = FindArgs {
-            all: false,
-            string: Some("apple".to_string()),
-            count: 0,
-            hex: None,
-            max_results: 0,
+    fn bt_scan() {
+        let args = BacktraceArgs {
+            scan: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
         };
-        do_test!(find, &args);
+        do_test!(backtrace_scan, &args);
     }
 
     #[test]
-    fn find_all_str() {
-        let args = FindArgs {
-            all: true,
-            string: Some("count".to_string()),
-            count: 0,
-            hex: None,
-            max_results: 0,
+    fn bt_scan_filtered() {
+        let args = BacktraceArgs {
+            scan: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs {
+                sort: Some("offset".to_string()),
+                reverse: true,
+                limit: Some(1),
+                ..FilterArgs::default()
+            },
         };
-        do_test!(find, &args);
+        do_test!(backtrace_scan, &args);
     }
 
     #[test]
-    fn find_hex() {
-        let args = FindArgs {
-            all: false,
-            string: None,
-            count: 0,
-            hex: Some("20".to_string()),
-            max_results: 10,
+    fn bt_scan_unsupported() {
+        let mut builder = CoreBuilder::new(0xB7, true, true); // aarch64
+        builder.prstatus(11, 1, 42, &[0; 34]);
+        let files = ElfFiles::new(vec![builder.write("bt-scan-aarch64")]).unwrap();
+
+        let args = BacktraceArgs {
+            scan: true,
+            explain: false,
+            titles: true,
+            filter: FilterArgs::default(),
         };
-        do_test!(find, &args);
+        let mut v: Vec<u8> = Vec::new();
+        backtrace_scan(&mut v, &files, &args);
+        let s = String::from_utf8(v).unwrap();
+        assert_eq!(s, "bt --scan is not supported on aarch64\n");
     }
 
     #[test]
-    fn find_all_hex() {
-        let args = FindArgs {
-            all: true,
-            string: None,
-            count: 0,
-            hex: Some("20".to_string()),
-            max_results: 10,
-        };
-        do_test!(find, &args);
+    fn bt_cycle() {
+        // The saved frame pointer points back at its own frame.
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[4] = 0x7fff0010; // rbp
+        regs[16] = 0x400100; // rip
+        regs[19] = 0x7fff0000; // rsp
+        builder.prstatus(11, 1, 42, &regs);
+
+        let mut e = builder.encoder();
+        e.xword(0).xword(0);
+        e.xword(0x7fff0010).xword(0x400200);
+        builder.load(0x7fff0000, e.bytes, 6);
+        let files = ElfFiles::new(vec![builder.write("bt-cycle")]).unwrap();
+
+        let mut v: Vec<u8> = Vec::new();
+        backtrace(&mut v, &files);
+        let s = String::from_utf8(v).unwrap();
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn bt_vdso() {
+        // A crash in clock_gettime called from 0x400200.
+        const VDSO: u64 = 0x7fff2000;
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[4] = 0x7fff0010; // rbp
+        regs[16] = VDSO + 0xec2; // rip
+        regs[19] = 0x7fff0000; // rsp
+        builder.prstatus(11, 1, 42, &regs);
+        builder.auxv(&[(33, VDSO)]); // AT_SYSINFO_EHDR
+
+        let mut e = builder.encoder();
+        e.xword(0).xword(0);
+        e.xword(0).xword(0x400200);
+        builder.load(0x7fff0000, e.bytes, 6);
+        builder.load(
+            VDSO,
+            std::fs::read("cores/vdso/vdso-x86_64.elf").unwrap(),
+            5,
+        );
+        let files = ElfFiles::new(vec![builder.write("bt-vdso")]).unwrap();
+
+        let mut v: Vec<u8> = Vec::new();
+        backtrace(&mut v, &files);
+        let s = String::from_utf8(v).unwrap();
+        insta::assert_snapshot!(s);
     }
 
     #[test]
-    fn find_str_dump() {
-        let args = FindArgs {
-            all: false,
-            string: Some("count".to_string()),
-            count: 25,
-            hex: None,
-            max_results: 0,
+    fn print_global() {
+        let args = PrintArgs {
+            name: "shopping_cart".to_string(),
+            tid: None,
         };
-        do_test!(find, &args);
+        do_test!(print, &args);
+    }
+
+    #[test]
+    fn print_thread_locals() {
+        let files = crate::utils::core_builder::tls_files();
+        let mut v: Vec<u8> = Vec::new();
+        for (name, tid) in [
+            ("tls_var", None),
+            ("errno", None),
+            ("tls_var", Some(101)),
+            ("errno", Some(101)),
+        ] {
+            let name = name.to_string();
+            print(&mut v, &files, &PrintArgs { name, tid });
+        }
+        let s = String::from_utf8(v).unwrap();
+        assert_eq!(
+            s,
+            "tls_var = 0x1234 (at 0x600308)\n\
+             errno = 0x2 (at 0x600340)\n\
+             tls_var = 0x5678 (at 0x600388)\n\
+             The thread's TLS block for errno isn't allocated\n"
+        );
+
+        // The library is only parsed once.
+        let core = files.core().unwrap();
+        let first = files.link_map_objects(core);
+        let second = files.link_map_objects(core);
+        assert_eq!(first.len(), 1);
+        assert!(std::ptr::eq(first[0].0, second[0].0));
     }
 
     #[test]
@@ -411,11 +556,31 @@ mod tests {
             count: 16,
             labels: HexdumpLabels::None,
             offset: false,
+            width: 1,
             value: 0x7ff8fc2ceb25,
         };
         do_test!(hexdump, &args);
     }
 
+    #[test]
+    fn dump_mapped_file() {
+        // The core has the first page of app-debug but not the text that follows it.
+        let args = HexdumpArgs {
+            exe: false,
+            count: 32,
+            labels: HexdumpLabels::Addr,
+            offset: false,
+            width: 1,
+            value: 0x55957a492ff0,
+        };
+        let mut v: Vec<u8> = Vec::new();
+        debug_results!(v, hexdump, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
     #[test]
     fn dump_offset() {
         let args = HexdumpArgs {
@@ -423,6 +588,7 @@ mod tests {
             count: 32,
             labels: HexdumpLabels::Zero,
             offset: true,
+            width: 1,
             value: 0x3871,
         };
         do_test!(hexdump, &args);
@@ -435,6 +601,20 @@ mod tests {
             count: 34,
             labels: HexdumpLabels::Addr,
             offset: false,
+            width: 1,
+            value: 0x7ff8fc2ceb25,
+        };
+        do_test!(hexdump, &args);
+    }
+
+    #[test]
+    fn dump_words() {
+        let args = HexdumpArgs {
+            exe: false,
+            count: 20,
+            labels: HexdumpLabels::Zero,
+            offset: false,
+            width: 4,
             value: 0x7ff8fc2ceb25,
         };
         do_test!(hexdump, &args);
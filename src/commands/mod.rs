@@ -1,9 +1,17 @@
 //! Handlers for the commands users type, e.g. `bt`.
+pub mod analyze;
 pub mod elf;
+pub mod find;
+pub mod heap;
 pub mod info;
 pub mod misc;
+pub mod session;
 pub mod tables;
 
+pub use analyze::*;
 pub use elf::*;
+pub use find::*;
+pub use heap::*;
 pub use info::*;
 pub use misc::*;
+pub use session::*;
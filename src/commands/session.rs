@@ -0,0 +1,351 @@
+use super::misc::{format_value, raw_backtrace};
+use super::tables::add_field;
+use crate::commands::tables::TableBuilder;
+use crate::elf::{ElfFile, ElfFiles, LayeredMemory, SignalDetails, VirtualAddr};
+use crate::repl::{AddSymbolFileArgs, CompareArgs, CoreArgs, FileArgs, UnloadArgs};
+use crate::utils::{self, Styling, uwriteln};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub fn add_symbol_file(mut out: impl Write, files: &mut ElfFiles, args: &AddSymbolFileArgs) {
+    match files.add_symbol_file(args.path.clone(), args.addr) {
+        Ok(()) => uwriteln!(out, "Loaded symbols from {}", args.path.display()),
+        Err(err) => utils::warn(&format!("couldn't load {}: {err}", args.path.display())),
+    }
+}
+
+pub fn core(out: impl Write, files: &mut ElfFiles, args: &CoreArgs) {
+    match &args.target {
+        // A number selects a loaded core unless there is a file with that name.
+        Some(target) if target.parse::<usize>().is_ok() && !Path::new(target).exists() => {
+            let index = target.parse::<usize>().unwrap();
+            if let Err(err) = files.select_core(index) {
+                utils::warn(&err.to_string());
+            } else {
+                list_cores(out, files);
+            }
+        }
+        Some(target) => match files.add_core(PathBuf::from(target)) {
+            Ok(()) => list_cores(out, files),
+            Err(err) => utils::warn(&format!("couldn't load {target}: {err}")),
+        },
+        None => list_cores(out, files),
+    }
+}
+
+pub fn file(mut out: impl Write, files: &mut ElfFiles, args: &FileArgs) {
+    match files.add_exe(args.path.clone()) {
+        Ok(()) => uwriteln!(out, "Loaded {}", args.path.display()),
+        Err(err) => utils::warn(&format!("couldn't load {}: {err}", args.path.display())),
+    }
+}
+
+pub fn unload(mut out: impl Write, files: &mut ElfFiles, args: &UnloadArgs) {
+    match files.unload(args.path.as_deref()) {
+        Ok(()) => match &args.path {
+            Some(path) => uwriteln!(out, "Unloaded {}", path.display()),
+            None => uwriteln!(out, "Unloaded all files"),
+        },
+        Err(err) => utils::warn(&err.to_string()),
+    }
+}
+
+fn list_cores(out: impl Write, files: &ElfFiles) {
+    if files.cores.is_empty() {
+        utils::warn("there are no core files");
+        return;
+    }
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_r("index", "used with the core command to select a core");
+    builder.add_col_l("selected", "* if this is the core commands operate on");
+    builder.add_col_r("pid", "the process id for the exe that produced the core");
+    builder.add_col_l("signal", "the signal that caused the core");
+    builder.add_col_l("exe", "the exe matched up with the core");
+    builder.add_col_l("core", "path to the core file");
+
+    for (i, core) in files.cores.iter().enumerate() {
+        let status = core.find_prstatus();
+        add_field!(builder, "index", i);
+        add_field!(
+            builder,
+            "selected",
+            if i == files.selected_core() { "*" } else { "" }
+        );
+        add_field!(
+            builder,
+            "pid",
+            status
+                .as_ref()
+                .map_or("?".to_string(), |s| s.pid.to_string())
+        );
+        add_field!(
+            builder,
+            "signal",
+            status.as_ref().map_or("?", |s| s.signal())
+        );
+        add_field!(
+            builder,
+            "exe",
+            files
+                .exe_for(core)
+                .map_or("none".to_string(), |e| e.path.display().to_string())
+        );
+        add_field!(builder, "core", core.path.display());
+    }
+
+    builder.writeln(out, true, false);
+}
+
+/// Frames are compared using file and line (or file and relative address) because
+/// ASLR means that the same frame will typically have different virtual addresses
+/// in different cores.
+fn frame_names(files: &ElfFiles, core: &ElfFile) -> Vec<String> {
+    match raw_backtrace(core) {
+        Ok(bt) => bt.iter().map(|a| frame_name(files, core, *a)).collect(),
+        Err(err) => vec![err.to_string()],
+    }
+}
+
+fn frame_name(files: &ElfFiles, core: &ElfFile, addr: VirtualAddr) -> String {
+    if let Ok((file, line, col)) = files.find_line_in(core, addr)
+        && line > 0
+    {
+        return format!("{file}:{line}:{col}");
+    }
+
+    if let Some(name) = core.describe_vdso_addr(addr) {
+        return name;
+    }
+
+    if let Some(mapped) = core.get_memory_mapped_files()
+        && let Some(m) = mapped.iter().find(|m| m.vbytes.contains(addr))
+    {
+        let name = Path::new(&m.file_name)
+            .file_name()
+            .map_or(m.file_name.clone(), |n| n.to_string_lossy().to_string());
+        format!("{name}+0x{:x}", addr.0 - m.vbytes.start.0)
+    } else {
+        format!("0x{:x}", addr.0)
+    }
+}
+
+fn fault_addr(core: &ElfFile) -> String {
+    match core.find_signal_info().map(|i| i.details) {
+        Some(SignalDetails::Fault(details)) => format!("0x{:x}", details.fault_addr),
+        _ => "".to_string(),
+    }
+}
+
+/// Returns the value of a global variable as saved in core.
+fn global_value(files: &ElfFiles, core: &ElfFile, name: &str) -> String {
+    let Some(exe) = files.exe_for(core) else {
+        return "no exe".to_string();
+    };
+    let Some(symbol) = exe.find_symbol(name) else {
+        return "not found".to_string();
+    };
+    let Some(bias) = ElfFiles::load_bias(core, exe) else {
+        return "not mapped".to_string();
+    };
+
+    let vaddr = VirtualAddr(symbol.value + bias);
+    format_value(&LayeredMemory::new(files, core), vaddr, symbol.size)
+}
+
+pub fn compare(mut out: impl Write, files: &ElfFiles, args: &CompareArgs) {
+    if files.cores.is_empty() {
+        utils::warn("there are no core files");
+        return;
+    }
+
+    // Cores with the same signal and backtrace are assumed to share a root cause.
+    let mut groups: Vec<(&'static str, Vec<String>, Vec<usize>)> = Vec::new();
+    let mut group_of = Vec::new();
+    for (i, core) in files.cores.iter().enumerate() {
+        let signal = core.find_prstatus().map_or("?", |s| s.signal());
+        let frames = frame_names(files, core);
+        match groups
+            .iter()
+            .position(|(s, f, _)| *s == signal && *f == frames)
+        {
+            Some(g) => {
+                groups[g].2.push(i);
+                group_of.push(g);
+            }
+            None => {
+                group_of.push(groups.len());
+                groups.push((signal, frames, vec![i]));
+            }
+        }
+    }
+
+    let mut builder = TableBuilder::new();
+    builder.add_col_r("core", "index of the core");
+    builder.add_col_r("group", "cores with the same signal and backtrace");
+    builder.add_col_r("pid", "the process id for the exe that produced the core");
+    builder.add_col_l("signal", "the signal that caused the core");
+    builder.add_col_r("fault addr", "the address that caused the core");
+    for name in args.globals.iter() {
+        builder.add_col_r(name, "the value of the global in the core");
+    }
+
+    for (i, core) in files.cores.iter().enumerate() {
+        let status = core.find_prstatus();
+        add_field!(builder, "core", i);
+        add_field!(builder, "group", group_of[i]);
+        add_field!(
+            builder,
+            "pid",
+            status
+                .as_ref()
+                .map_or("?".to_string(), |s| s.pid.to_string())
+        );
+        add_field!(
+            builder,
+            "signal",
+            status.as_ref().map_or("?", |s| s.signal())
+        );
+        add_field!(builder, "fault addr", fault_addr(core));
+        for name in args.globals.iter() {
+            let value = global_value(files, core, name);
+            builder.add_str_field(name, value.table_field().to_string());
+        }
+    }
+    builder.writeln(&mut out, true, args.explain);
+
+    for (i, (signal, frames, cores)) in groups.iter().enumerate() {
+        let cores: Vec<String> = cores.iter().map(|c| c.to_string()).collect();
+        uwriteln!(out);
+        uwriteln!(out, "group {i}: {signal} in cores {}", cores.join(", "));
+        for frame in frames.iter() {
+            uwriteln!(out, "   {frame}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_files() -> ElfFiles {
+        let paths = vec![
+            std::path::PathBuf::from("cores/shopping-debug/app-debug"),
+            std::path::PathBuf::from("cores/shopping-debug/app-debug.core"),
+            std::path::PathBuf::from("cores/shopping-release/app-release.core"),
+            std::path::PathBuf::from("cores/shopping-release/app-release"),
+        ];
+        ElfFiles::new(paths).unwrap()
+    }
+
+    #[test]
+    fn select_core() {
+        let mut files = all_files();
+        let mut v: Vec<u8> = Vec::new();
+        let args = CoreArgs {
+            target: Some("1".to_string()),
+        };
+        core(&mut v, &mut files, &args);
+        assert_eq!(files.selected_core(), 1);
+        assert!(files.exe().unwrap().path.ends_with("app-release"));
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn compare_cores() {
+        let files = all_files();
+        let args = CompareArgs {
+            explain: false,
+            globals: vec!["shopping_cart".to_string(), "completed.0".to_string()],
+        };
+        let mut v: Vec<u8> = Vec::new();
+        compare(&mut v, &files, &args);
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn load_files() {
+        let mut files = ElfFiles::new(Vec::new()).unwrap();
+        assert!(files.is_empty());
+
+        let mut v: Vec<u8> = Vec::new();
+        let args = FileArgs {
+            path: PathBuf::from("cores/shopping-debug/app-debug"),
+        };
+        file(&mut v, &mut files, &args);
+        let args = CoreArgs {
+            target: Some("cores/shopping-debug/app-debug.core".to_string()),
+        };
+        core(&mut v, &mut files, &args);
+        crate::commands::backtrace(&mut v, &files);
+
+        let args = UnloadArgs {
+            path: Some(PathBuf::from("cores/shopping-debug/app-debug")),
+        };
+        unload(&mut v, &mut files, &args);
+        assert!(files.exe().is_none());
+        assert!(files.core().is_some());
+
+        let s = String::from_utf8(v).unwrap();
+        let s = crate::utils::strip_escapes(&s);
+        insta::assert_snapshot!(s);
+    }
+
+    #[test]
+    fn unload_first_core() {
+        let mut files = ElfFiles::new(Vec::new()).unwrap();
+        files
+            .add_core(PathBuf::from("cores/shopping-debug/app-debug.core"))
+            .unwrap();
+        files
+            .add_core(PathBuf::from("cores/shopping-release/app-release.core"))
+            .unwrap();
+        assert_eq!(files.selected_core(), 1);
+
+        let mut v: Vec<u8> = Vec::new();
+        let args = UnloadArgs {
+            path: Some(PathBuf::from(
+                "cores/shopping-debug/../shopping-debug/app-debug.core",
+            )),
+        };
+        unload(&mut v, &mut files, &args);
+        assert_eq!(files.selected_core(), 0);
+        assert!(files.core().unwrap().path.ends_with("app-release.core"));
+    }
+
+    #[test]
+    fn symbol_file_with_addr() {
+        let paths = vec![PathBuf::from("cores/shopping-debug/app-debug.core")];
+        let mut files = ElfFiles::new(paths).unwrap();
+        let args = AddSymbolFileArgs {
+            path: PathBuf::from("cores/shopping-debug/app-debug"),
+            addr: Some(0x55957a4930c0),
+        };
+        add_symbol_file(Vec::new(), &mut files, &args);
+
+        let (file, line, col) = files.find_line(VirtualAddr(0x55957a4931d7)).unwrap();
+        assert_eq!(format!("{file}:{line}:{col}"), "./item.c:21:5");
+    }
+
+    #[test]
+    fn symbol_file_for_exe() {
+        let paths = vec![PathBuf::from("cores/shopping-debug/app-debug.core")];
+        let mut files = ElfFiles::new(paths).unwrap();
+        assert!(files.find_line(VirtualAddr(0x55957a4931d7)).is_err());
+
+        let args = AddSymbolFileArgs {
+            path: PathBuf::from("cores/shopping-debug/app-debug"),
+            addr: None,
+        };
+        add_symbol_file(Vec::new(), &mut files, &args);
+
+        let (file, line, col) = files.find_line(VirtualAddr(0x55957a4931d7)).unwrap();
+        assert_eq!(format!("{file}:{line}:{col}"), "./item.c:21:5");
+    }
+}
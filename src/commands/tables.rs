@@ -1,12 +1,20 @@
 //! Helpers for building tables using the tabled crate.
+use crate::repl::FilterArgs;
 use crate::utils::Styling;
-use crate::utils::uwriteln;
+use crate::utils::{self, uwriteln};
+use std::cmp::Ordering;
+use std::error::Error;
 use std::io::Write;
 use tabled::{
     builder::Builder,
     settings::{Alignment, Padding, Style, object::Columns},
 };
 
+/// Columns that --range checks, the first one the table has is used.
+const ADDRESS_COLS: [&str; 8] = [
+    "address", "vaddr", "value", "start", "stack", "offset", "chunk", "arena",
+];
+
 struct TableCol {
     header: String,
     align: Alignment,
@@ -77,6 +85,93 @@ impl TableBuilder {
         }
     }
 
+    /// Applies the filter options and then writes the table. Warns instead if the
+    /// table doesn't have a column one of the options needs.
+    pub fn writeln_filtered(
+        &mut self,
+        out: impl Write,
+        titles: bool,
+        explain: bool,
+        filter: &FilterArgs,
+    ) {
+        match self.filter(filter) {
+            Ok(_) => self.writeln(out, titles, explain),
+            Err(err) => utils::warn(&err.to_string()),
+        }
+    }
+
+    /// Removes rows that don't match the filter options and then sorts and truncates
+    /// the rows that are left.
+    pub fn filter(&mut self, args: &FilterArgs) -> Result<(), Box<dyn Error>> {
+        let height = self.cols.first().map_or(0, |c| c.fields.len());
+        let mut rows: Vec<usize> = (0..height).collect();
+
+        if let Some(re) = &args.name {
+            let values = self.plain_col(&["name", "symbol"])?;
+            rows.retain(|&i| re.is_match(&values[i]));
+        }
+        if let Some(stype) = &args.stype {
+            let values = self.plain_col(&["type"])?;
+            rows.retain(|&i| values[i].eq_ignore_ascii_case(stype));
+        }
+        if let Some(binding) = &args.binding {
+            let values = self.plain_col(&["binding"])?;
+            rows.retain(|&i| values[i].eq_ignore_ascii_case(binding));
+        }
+        if let Some(section) = &args.section {
+            let values = self.plain_col(&["section", "related"])?;
+            rows.retain(|&i| values[i] == *section);
+        }
+        if let Some((start, end)) = args.range {
+            let values = self.plain_col(&ADDRESS_COLS)?;
+            rows.retain(|&i| {
+                parse_hex(&values[i]).is_some_and(|a| start as u128 <= a && a < end as u128)
+            });
+        }
+
+        if let Some(header) = &args.sort {
+            if !self.has_col(header) {
+                let names: Vec<&str> = self.cols.iter().map(|c| c.header.as_str()).collect();
+                return Err(format!("sort column should be one of: {}", names.join(", ")).into());
+            }
+            let values = self.plain_col(&[header.as_str()])?;
+            if rows.iter().all(|&i| parse_hex(&values[i]).is_some()) {
+                // Decimal strings sort correctly when parsed as hex so we don't need to
+                // know which base the column uses.
+                rows.sort_by_key(|&i| parse_hex(&values[i]));
+            } else {
+                rows.sort_by(|&i, &j| compare_text(&values[i], &values[j]));
+            }
+        }
+        if args.reverse {
+            rows.reverse();
+        }
+        if let Some(limit) = args.limit {
+            rows.truncate(limit);
+        }
+
+        for col in self.cols.iter_mut() {
+            col.fields = rows.iter().map(|&i| col.fields[i].clone()).collect();
+        }
+        Ok(())
+    }
+
+    /// Returns the unstyled fields for the first of headers that the table has.
+    fn plain_col(&self, headers: &[&str]) -> Result<Vec<String>, Box<dyn Error>> {
+        let Some(col) = self
+            .cols
+            .iter()
+            .find(|c| headers.contains(&c.header.as_str()))
+        else {
+            return Err(format!("this table has no {} column", headers[0]).into());
+        };
+        Ok(col
+            .fields
+            .iter()
+            .map(|f| utils::strip_escapes(f).trim().to_string())
+            .collect())
+    }
+
     // We need to preserve add_col ordering so we can't use a HashMap
     // but O(n) should be fine for tables.
     fn has_col(&self, header: &str) -> bool {
@@ -136,6 +231,17 @@ impl TableBuilder {
     }
 }
 
+fn parse_hex(s: &str) -> Option<u128> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u128::from_str_radix(s, 16).ok()
+}
+
+fn compare_text(lhs: &str, rhs: &str) -> Ordering {
+    lhs.to_lowercase()
+        .cmp(&rhs.to_lowercase())
+        .then_with(|| lhs.cmp(rhs))
+}
+
 macro_rules! add_field {
     ($builder:ident, $header:literal, $value:expr) => {
         let s = format!("{}", $value);
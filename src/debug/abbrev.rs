@@ -1,5 +1,5 @@
 use crate::{
-    debug::{AttributeName, FormEncoding, Tag, decode_u64},
+    debug::{AttributeName, FormEncoding, Tag, decode_i64, decode_u64},
     elf::Stream,
 };
 use std::error::Error;
@@ -21,6 +21,9 @@ pub struct Abbreviation {
 pub struct AttributeEncoding {
     pub name: AttributeName,
     pub encoding: FormEncoding,
+
+    /// For DW_FORM_implicit_const the value is stored here instead of in .debug_info.
+    pub implicit_const: Option<i64>,
 }
 
 impl Abbreviation {
@@ -46,7 +49,16 @@ impl Abbreviation {
 
             let name = AttributeName::from_u64(name)?;
             let encoding = FormEncoding::from_u64(encoding)?;
-            attrs.push(AttributeEncoding { name, encoding })
+            let implicit_const = if encoding == FormEncoding::DW_FORM_implicit_const {
+                Some(decode_i64(stream)?)
+            } else {
+                None
+            };
+            attrs.push(AttributeEncoding {
+                name,
+                encoding,
+                implicit_const,
+            })
         }
         Ok(Some(Abbreviation {
             tag,
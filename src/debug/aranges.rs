@@ -0,0 +1,106 @@
+//! Address ranges from .debug_aranges. These map code addresses to the compilation unit
+//! in .debug_info that describes them without having to parse .debug_info.
+use crate::elf::{Offset, Stream};
+use std::{error::Error, ops::Range};
+
+/// The code for one compilation unit.
+pub struct ArangeSet {
+    /// Offset of the compilation unit's header within .debug_info.
+    pub info_offset: u64,
+
+    /// Relative addresses for the unit's code.
+    pub ranges: Vec<Range<u64>>,
+}
+
+/// Reads the sets from stream until end, see 6.1.2.
+pub fn read_aranges(stream: &mut Stream, end: Offset) -> Result<Vec<ArangeSet>, Box<dyn Error>> {
+    let mut sets = Vec::new();
+    while stream.offset < end {
+        let start = stream.offset;
+        let word = stream.read_word()?;
+        let (sixty_four, length) = if word == 0xffffffff {
+            (true, stream.read_xword()?)
+        } else {
+            (false, word as u64)
+        };
+        let next = stream.offset + length as i64;
+
+        let version = stream.read_half()?;
+        if version != 2 {
+            return Err(format!("bad .debug_aranges version: {version}").into());
+        }
+        let info_offset = if sixty_four {
+            stream.read_xword()?
+        } else {
+            stream.read_word()? as u64
+        };
+        let addr_size = stream.read_byte()?;
+        let segment_size = stream.read_byte()?;
+        if addr_size != 4 && addr_size != 8 {
+            return Err(format!("bad .debug_aranges address size: {addr_size}").into());
+        }
+
+        // The tuples are aligned to twice the address size.
+        let tuple_size = 2 * addr_size as u64;
+        let used = stream.offset.0 - start.0;
+        stream.offset = stream.offset + (used.next_multiple_of(tuple_size) - used) as i64;
+
+        let mut ranges = Vec::new();
+        while stream.offset < next {
+            stream.offset = stream.offset + segment_size as i64;
+            let (addr, len) = if addr_size == 8 {
+                (stream.read_xword()?, stream.read_xword()?)
+            } else {
+                (stream.read_word()? as u64, stream.read_word()? as u64)
+            };
+            if addr == 0 && len == 0 {
+                break;
+            }
+            ranges.push(addr..addr + len);
+        }
+        sets.push(ArangeSet {
+            info_offset,
+            ranges,
+        });
+        stream.offset = next;
+    }
+    Ok(sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::core_builder::Encoder;
+
+    #[test]
+    fn sets() {
+        let mut e = Encoder::new(true, true);
+        e.word(44).half(2).word(0x0).byte(8).byte(0).word(0); // header + padding
+        e.xword(0x1240).xword(0xe8).xword(0).xword(0);
+        e.word(60).half(2).word(0x263).byte(8).byte(0).word(0);
+        e.xword(0x1080).xword(0xaa).xword(0x2000).xword(0x10);
+        e.xword(0).xword(0);
+        let end = e.stream().offset + e.bytes.len() as i64;
+
+        let sets = read_aranges(&mut e.stream(), end).unwrap();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].info_offset, 0);
+        assert_eq!(sets[0].ranges, vec![0x1240..0x1328]);
+        assert_eq!(sets[1].info_offset, 0x263);
+        assert_eq!(sets[1].ranges, vec![0x1080..0x112a, 0x2000..0x2010]);
+    }
+
+    #[test]
+    fn sets_32_bit() {
+        let mut e = Encoder::new(false, false);
+        e.word(28).half(2).word(0x10).byte(4).byte(0); // tuples start at offset 16
+        e.word(0);
+        e.word(0x8000).word(0x20).word(0).word(0);
+        let end = e.stream().offset + e.bytes.len() as i64;
+
+        let sets = read_aranges(&mut e.stream(), end).unwrap();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].info_offset, 0x10);
+        assert_eq!(sets[0].ranges, vec![0x8000..0x8020]);
+    }
+}
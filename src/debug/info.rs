@@ -0,0 +1,1193 @@
+//! Functions from .debug_info. Unlike ParseTypes this only keeps what's needed to map
+//! addresses to compilation units and functions, including the functions that were
+//! inlined into other functions. Debug info can be huge so units are only parsed when an
+//! address within them is looked up.
+use crate::{
+    debug::{
+        Abbreviation, AttributeName, FormEncoding, LineInfo, LineStrings, Tag, decode_i64,
+        decode_u64, read_aranges,
+    },
+    elf::{ElfFile, Offset, Reader, RelativeAddr, Stream},
+    utils,
+};
+use rangemap::RangeMap;
+use std::{cell::OnceCell, collections::HashMap, error::Error, ops::Range};
+
+/// The compilation units in an exe or lib.
+pub struct DebugInfo {
+    reader: Reader,
+    sections: Sections,
+
+    /// Offsets of the unit headers within .debug_info along with the parsed units.
+    units: Vec<(u64, OnceCell<Option<UnitInfo>>)>,
+
+    /// Maps relative addresses to indexes into units. This comes from .debug_aranges
+    /// when possible so that only the units that are looked up are parsed.
+    index: OnceCell<RangeMap<u64, usize>>,
+}
+
+/// The code, functions, and lines for a compilation unit.
+pub struct UnitInfo {
+    /// Relative addresses for the unit's code. From DW_AT_ranges or DW_AT_low_pc and
+    /// DW_AT_high_pc, or the functions' ranges if the unit has neither.
+    pub ranges: Vec<Range<u64>>,
+
+    /// Out of line functions, these contain the functions inlined into them.
+    pub functions: Vec<Function>,
+
+    /// The unit's line program from DW_AT_stmt_list.
+    pub lines: Option<LineInfo>,
+}
+
+/// Either an out of line function or a copy of a function that was inlined.
+pub struct Function {
+    pub name: String,
+
+    /// Relative addresses for the function's code. Optimized code may be split into
+    /// multiple ranges.
+    pub ranges: Vec<Range<u64>>,
+
+    /// Where the function was inlined, None for out of line functions.
+    pub call: Option<CallLocation>,
+
+    /// Functions inlined into this function.
+    pub inlined: Vec<Function>,
+
+    /// DW_AT_frame_base for the function's code. Empty for inlined functions because
+    /// they use the frame of the function they were inlined into.
+    frame_bases: FrameBases,
+
+    /// Parameters and local variables (including those in lexical blocks) that live at
+    /// a fixed offset from the frame base.
+    pub variables: Vec<StackVariable>,
+
+    /// DW_AT_abstract_origin or DW_AT_specification, used to find the name.
+    origin: Option<u64>,
+}
+
+/// The frame base locations we can evaluate, see 2.5.1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameBase {
+    /// DW_OP_call_frame_cfa, i.e. the value of the stack pointer in the caller just
+    /// before the call.
+    Cfa,
+
+    /// DW_OP_regN or DW_OP_bregN: a DWARF register number plus an offset.
+    Register(u16, i64),
+}
+
+/// Code ranges and the frame base used for each.
+type FrameBases = Vec<(Range<u64>, FrameBase)>;
+
+/// A parameter or local variable whose location is DW_OP_fbreg.
+pub struct StackVariable {
+    pub name: String,
+
+    /// Offset from the function's frame base.
+    pub offset: i64,
+
+    /// DW_AT_abstract_origin for variables within inlined functions.
+    origin: Option<u64>,
+}
+
+/// Source location of an inlined call.
+#[derive(Clone)]
+pub struct CallLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: u16,
+}
+
+impl DebugInfo {
+    /// This only finds where the units start, they are parsed as needed.
+    pub fn new(exe: &ElfFile) -> Option<Self> {
+        let section = |name| exe.find_section_named(name).map(|s| s.obytes.start);
+        let info = exe.find_section_named(".debug_info")?;
+        let sections = Sections {
+            info: info.obytes.start,
+            abbrevs: section(".debug_abbrev"),
+            aranges: exe
+                .find_section_named(".debug_aranges")
+                .map(|s| (s.obytes.start, s.obytes.end())),
+            line: section(".debug_line"),
+            strings: section(".debug_str"),
+            line_strings: section(".debug_line_str"),
+            str_offsets: section(".debug_str_offsets"),
+            addr: section(".debug_addr"),
+            ranges: section(".debug_ranges"),
+            rnglists: section(".debug_rnglists"),
+            loc: section(".debug_loc"),
+        };
+
+        let mut units = Vec::new();
+        let mut stream = Stream::new(&exe.reader, info.obytes.start);
+        while stream.offset < info.obytes.end() {
+            units.push((stream.offset.0 - info.obytes.start.0, OnceCell::new()));
+            match read_unit_length(&mut stream) {
+                Ok((_, length)) => stream.offset = stream.offset + length as i64,
+                Err(err) => {
+                    utils::warn(&format!("failed to read .debug_info: {err}"));
+                    break;
+                }
+            }
+        }
+        Some(DebugInfo {
+            reader: exe.reader.clone(),
+            sections,
+            units,
+            index: OnceCell::new(),
+        })
+    }
+
+    /// Returns the compilation unit whose code contains addr.
+    pub fn find_unit(&self, addr: RelativeAddr) -> Option<&UnitInfo> {
+        let index = *self.get_index().get(&addr.0)?;
+        self.get_unit(index)
+    }
+
+    /// Returns the functions containing addr starting with the out of line function and
+    /// ending with the innermost inlined function. Empty if addr isn't in a function.
+    pub fn find_functions(&self, addr: RelativeAddr) -> Vec<&Function> {
+        let mut chain = Vec::new();
+        let Some(unit) = self.find_unit(addr) else {
+            return chain;
+        };
+        let mut candidates = &unit.functions;
+        while let Some(f) = candidates.iter().find(|f| f.contains(addr)) {
+            chain.push(f);
+            candidates = &f.inlined;
+        }
+        chain
+    }
+
+    /// Returns the file, line number, and column for addr using just the line program
+    /// for addr's compilation unit.
+    pub fn find_line(&self, addr: RelativeAddr) -> Option<(String, u32, u16)> {
+        self.find_unit(addr)?.lines.as_ref()?.find(addr)
+    }
+
+    fn get_index(&self) -> &RangeMap<u64, usize> {
+        self.index.get_or_init(|| {
+            let mut index = RangeMap::new();
+            let mut indexed = vec![false; self.units.len()];
+            if let Some((start, end)) = self.sections.aranges {
+                let mut stream = Stream::new(&self.reader, start);
+                match read_aranges(&mut stream, end) {
+                    Ok(sets) => {
+                        for set in sets {
+                            if let Ok(i) =
+                                self.units.binary_search_by_key(&set.info_offset, |u| u.0)
+                            {
+                                insert_ranges(&mut index, &set.ranges, i);
+                                indexed[i] = true;
+                            }
+                        }
+                    }
+                    Err(err) => utils::warn(&format!("failed to read .debug_aranges: {err}")),
+                }
+            }
+
+            // .debug_aranges is optional and may not cover every unit.
+            for (i, _) in indexed.iter().enumerate().filter(|(_, done)| !**done) {
+                insert_ranges(&mut index, &self.unit_ranges(i), i);
+            }
+            index
+        })
+    }
+
+    fn get_unit(&self, index: usize) -> Option<&UnitInfo> {
+        let (offset, unit) = &self.units[index];
+        unit.get_or_init(|| {
+            let mut walker = Walker {
+                info: self,
+                names: HashMap::new(),
+            };
+            match walker.parse_unit(*offset) {
+                Ok(unit) => unit,
+                Err(err) => {
+                    utils::warn(&format!(
+                        "failed to read .debug_info unit 0x{offset:x}: {err}"
+                    ));
+                    None
+                }
+            }
+        })
+        .as_ref()
+    }
+
+    /// Returns the code ranges for a unit using just its DW_TAG_compile_unit entry (if
+    /// possible).
+    fn unit_ranges(&self, index: usize) -> Vec<Range<u64>> {
+        let walker = Walker {
+            info: self,
+            names: HashMap::new(),
+        };
+        match walker.open_unit(self.units[index].0) {
+            Ok(Some(unit)) if !unit.ranges.is_empty() => unit.ranges,
+            Ok(Some(_)) => self
+                .get_unit(index)
+                .map(|u| u.ranges.clone())
+                .unwrap_or_default(),
+            Ok(None) => Vec::new(),
+            Err(err) => {
+                let offset = self.units[index].0;
+                utils::warn(&format!(
+                    "failed to read .debug_info unit 0x{offset:x}: {err}"
+                ));
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Function {
+    pub fn contains(&self, addr: RelativeAddr) -> bool {
+        self.ranges.iter().any(|r| r.contains(&addr.0))
+    }
+
+    /// Returns the frame base to use for code at addr.
+    pub fn frame_base(&self, addr: RelativeAddr) -> Option<FrameBase> {
+        self.frame_bases
+            .iter()
+            .find(|(r, _)| r.contains(&addr.0))
+            .map(|(_, base)| *base)
+    }
+}
+
+/// Start of the debug sections we use.
+struct Sections {
+    info: Offset,
+    abbrevs: Option<Offset>,
+    aranges: Option<(Offset, Offset)>,
+    line: Option<Offset>,
+    strings: Option<Offset>,
+    line_strings: Option<Offset>,
+    str_offsets: Option<Offset>,
+    addr: Option<Offset>,
+    ranges: Option<Offset>,
+    rnglists: Option<Offset>,
+    loc: Option<Offset>,
+}
+
+/// Header info for the compilation unit being walked.
+struct Unit {
+    /// Offset of the unit header within .debug_info, references are relative to this.
+    offset: u64,
+    version: u16,
+    addr_size: u8,
+    sixty_four: bool,
+    abbrevs: Vec<Abbreviation>,
+
+    /// DW_AT_low_pc for the compilation unit, range lists are relative to this.
+    base: u64,
+
+    /// DW_AT_str_offsets_base, DW_AT_addr_base, and DW_AT_rnglists_base for the unit,
+    /// used to resolve the DWARF 5 index forms.
+    str_offsets_base: Option<u64>,
+    addr_base: Option<u64>,
+    rnglists_base: Option<u64>,
+
+    /// DW_AT_stmt_list for the unit.
+    stmt_list: Option<u64>,
+
+    /// The unit's line program, used for DW_AT_call_file.
+    lines: Option<LineInfo>,
+
+    /// The unit's DW_AT_ranges or DW_AT_low_pc and DW_AT_high_pc.
+    ranges: Vec<Range<u64>>,
+
+    /// Where the compile unit's children start, None if it has no children.
+    children: Option<Offset>,
+}
+
+/// Attribute values we care about.
+enum Value {
+    Address(u64),
+    Constant(u64),
+    SecOffset(u64),
+
+    /// Offset within .debug_info.
+    Ref(u64),
+    Str(String),
+
+    /// DW_FORM_addrx, DW_FORM_strx, and DW_FORM_rnglistx. These can't be resolved
+    /// until we have the unit's base attributes.
+    AddrIndex(u64),
+    StrIndex(u64),
+    RangesIndex(u64),
+
+    /// A DWARF expression: its offset within the file and length.
+    Expr(Offset, u64),
+    Other,
+}
+
+/// The attributes of a debugging entry that we care about.
+/// Values are kept raw because they may depend on attributes that come later.
+#[derive(Default)]
+struct Entry {
+    name: Option<Value>,
+    origin: Option<u64>,
+    low_pc: Option<Value>,
+
+    /// In DWARF 4 and later this may be a Constant with the size of the code.
+    high_pc: Option<Value>,
+    ranges: Option<Value>,
+    call_file: Option<u64>,
+    call_line: Option<u64>,
+    call_column: Option<u64>,
+    location: Option<Value>,
+    frame_base: Option<Value>,
+    stmt_list: Option<u64>,
+    str_offsets_base: Option<u64>,
+    addr_base: Option<u64>,
+    rnglists_base: Option<u64>,
+}
+
+struct Walker<'a> {
+    info: &'a DebugInfo,
+
+    /// Maps the offset of entries to their name and origin.
+    names: HashMap<u64, (Option<String>, Option<u64>)>,
+}
+
+impl<'a> Walker<'a> {
+    /// Returns the functions and lines for the unit at offset within .debug_info.
+    fn parse_unit(&mut self, offset: u64) -> Result<Option<UnitInfo>, Box<dyn Error>> {
+        let Some(mut unit) = self.open_unit(offset)? else {
+            return Ok(None);
+        };
+        if let (Some(section), Some(stmt_list)) = (self.info.sections.line, unit.stmt_list) {
+            let strings = LineStrings {
+                strings: self.info.sections.strings,
+                line_strings: self.info.sections.line_strings,
+            };
+            match LineInfo::new_unit(&self.info.reader, section, stmt_list, &strings) {
+                Ok(lines) => unit.lines = Some(lines),
+                Err(err) => utils::warn(&format!("failed to read line info: {err}")),
+            }
+        }
+
+        let mut functions = match unit.children {
+            Some(start) => {
+                self.parse_entries(&unit, &mut Stream::new(&self.info.reader, start))?
+                    .functions
+            }
+            None => Vec::new(),
+        };
+        self.resolve_names(&mut functions);
+
+        let mut ranges = unit.ranges;
+        if ranges.is_empty() {
+            ranges = functions.iter().flat_map(|f| f.ranges.clone()).collect();
+        }
+        Ok(Some(UnitInfo {
+            ranges,
+            functions,
+            lines: unit.lines,
+        }))
+    }
+
+    /// Reads the header and DW_TAG_compile_unit entry for the unit at offset within
+    /// .debug_info. None for unit types we don't care about (e.g. type units).
+    fn open_unit(&self, offset: u64) -> Result<Option<Unit>, Box<dyn Error>> {
+        // See 7.5.1.1
+        let mut stream = Stream::new(&self.info.reader, self.info.sections.info + offset as i64);
+        let (sixty_four, _) = read_unit_length(&mut stream)?;
+
+        let version = stream.read_half()?;
+        if !(2..=5).contains(&version) {
+            return Err(format!("bad .debug_info version: {version}").into());
+        }
+        let (unit_type, addr_size, abbrev_offset) = if version >= 5 {
+            let unit_type = stream.read_byte()?;
+            let addr_size = stream.read_byte()?;
+            (unit_type, addr_size, read_offset(&mut stream, sixty_four)?)
+        } else {
+            let abbrev_offset = read_offset(&mut stream, sixty_four)?;
+            (DW_UT_COMPILE, stream.read_byte()?, abbrev_offset)
+        };
+        if unit_type != DW_UT_COMPILE && unit_type != DW_UT_PARTIAL {
+            return Ok(None);
+        }
+
+        let mut unit = Unit {
+            offset,
+            version,
+            addr_size,
+            sixty_four,
+            abbrevs: self.read_abbrevs(abbrev_offset)?,
+            base: 0,
+            str_offsets_base: None,
+            addr_base: None,
+            rnglists_base: None,
+            stmt_list: None,
+            lines: None,
+            ranges: Vec::new(),
+            children: None,
+        };
+        let Some((abbrev, entry)) = self.read_entry(&unit, &mut stream)? else {
+            return Ok(None);
+        };
+        let (tag, has_children) = (abbrev.tag, abbrev.has_children);
+        if tag != Tag::DW_TAG_compile_unit && tag != Tag::DW_TAG_partial_unit {
+            return Err(format!("expected a compile unit but found {tag:?}").into());
+        }
+        unit.str_offsets_base = entry.str_offsets_base;
+        unit.addr_base = entry.addr_base;
+        unit.rnglists_base = entry.rnglists_base;
+        unit.base = self.address(&unit, &entry.low_pc)?.unwrap_or(0);
+        unit.stmt_list = entry.stmt_list;
+        unit.ranges = self.entry_ranges(&unit, &entry);
+        unit.children = has_children.then_some(stream.offset);
+        Ok(Some(unit))
+    }
+
+    fn read_abbrevs(&self, offset: u64) -> Result<Vec<Abbreviation>, Box<dyn Error>> {
+        let Some(start) = self.info.sections.abbrevs else {
+            return Err("no .debug_abbrev section".into());
+        };
+        let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+        let mut abbrevs = Vec::new();
+        while let Some(abbrev) = Abbreviation::new(&mut stream)? {
+            abbrevs.push(abbrev);
+        }
+        Ok(abbrevs)
+    }
+
+    /// Reads the attributes for the entry at stream, None for the null entry that ends
+    /// a list of siblings.
+    fn read_entry<'u>(
+        &self,
+        unit: &'u Unit,
+        stream: &mut Stream,
+    ) -> Result<Option<(&'u Abbreviation, Entry)>, Box<dyn Error>> {
+        let offset = stream.offset.0 - self.info.sections.info.0;
+        let code = decode_u64(stream)? as usize;
+        if code == 0 {
+            return Ok(None);
+        }
+        let Some(abbrev) = unit.abbrevs.get(code - 1) else {
+            return Err(format!("bad abbreviation code {code} at 0x{offset:x}").into());
+        };
+
+        let mut entry = Entry::default();
+        for ae in abbrev.attrs.iter() {
+            let value = self.read_value(unit, stream, ae.encoding, ae.implicit_const)?;
+            entry.set(ae.name, value);
+        }
+        Ok(Some((abbrev, entry)))
+    }
+
+    /// Parses entries until the null entry that ends a list of siblings.
+    fn parse_entries(&mut self, unit: &Unit, stream: &mut Stream) -> Result<Scope, Box<dyn Error>> {
+        let mut scope = Scope::default();
+        while self.parse_entry(unit, stream, &mut scope)? {}
+        Ok(scope)
+    }
+
+    /// Parses an entry along with its children and adds any functions and variables
+    /// found to scope. Returns false for the null entry.
+    fn parse_entry(
+        &mut self,
+        unit: &Unit,
+        stream: &mut Stream,
+        scope: &mut Scope,
+    ) -> Result<bool, Box<dyn Error>> {
+        let offset = stream.offset.0 - self.info.sections.info.0;
+        let Some((abbrev, entry)) = self.read_entry(unit, stream)? else {
+            return Ok(false);
+        };
+        let (tag, has_children) = (abbrev.tag, abbrev.has_children);
+        let name = self.string(unit, &entry.name)?;
+        if name.is_some() || entry.origin.is_some() {
+            self.names.insert(offset, (name.clone(), entry.origin));
+        }
+
+        let children = if has_children {
+            self.parse_entries(unit, stream)?
+        } else {
+            Scope::default()
+        };
+        if (tag == Tag::DW_TAG_variable || tag == Tag::DW_TAG_formal_parameter)
+            && let Some(offset) = self.fbreg(&entry.location)
+        {
+            scope.variables.push(StackVariable {
+                name: name.clone().unwrap_or_default(),
+                offset,
+                origin: entry.origin,
+            });
+        }
+        let ranges = if tag == Tag::DW_TAG_subprogram || tag == Tag::DW_TAG_inlined_subroutine {
+            self.entry_ranges(unit, &entry)
+        } else {
+            Vec::new()
+        };
+        if ranges.is_empty() {
+            // Declarations, abstract instances, lexical blocks, etc.
+            scope.functions.extend(children.functions);
+            scope.variables.extend(children.variables);
+        } else {
+            let call = if tag == Tag::DW_TAG_inlined_subroutine {
+                Some(CallLocation {
+                    file: entry
+                        .call_file
+                        .and_then(|f| unit.lines.as_ref()?.units.first()?.file_path(f as u32))
+                        .unwrap_or_else(|| "?".to_string()),
+                    line: entry.call_line.unwrap_or(0) as u32,
+                    column: entry.call_column.unwrap_or(0) as u16,
+                })
+            } else {
+                None
+            };
+            let frame_bases = self.frame_base(unit, &entry.frame_base, &ranges);
+            scope.functions.push(Function {
+                name: name.unwrap_or_default(),
+                ranges,
+                call,
+                inlined: children.functions,
+                frame_bases,
+                variables: children.variables,
+                origin: entry.origin,
+            });
+        }
+        Ok(true)
+    }
+
+    /// Bad range lists are treated as empty so that they don't take out the rest of the
+    /// compilation unit.
+    fn entry_ranges(&self, unit: &Unit, entry: &Entry) -> Vec<Range<u64>> {
+        match self.read_entry_ranges(unit, entry) {
+            Ok(ranges) => ranges,
+            Err(err) => {
+                utils::warn(&format!("failed to read ranges: {err}"));
+                Vec::new()
+            }
+        }
+    }
+
+    fn read_entry_ranges(
+        &self,
+        unit: &Unit,
+        entry: &Entry,
+    ) -> Result<Vec<Range<u64>>, Box<dyn Error>> {
+        match entry.ranges {
+            Some(Value::RangesIndex(index)) => {
+                let offset = self.rnglist_offset(unit, index)?;
+                self.read_rnglist_at(unit, offset)
+            }
+            Some(Value::SecOffset(offset)) if unit.version >= 5 => {
+                self.read_rnglist_at(unit, offset)
+            }
+            Some(Value::SecOffset(offset)) => {
+                let Some(start) = self.info.sections.ranges else {
+                    return Err("no .debug_ranges section".into());
+                };
+                let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+                read_ranges(&mut stream, unit.addr_size, unit.base)
+            }
+            _ => {
+                let Some(low) = self.address(unit, &entry.low_pc)? else {
+                    return Ok(Vec::new());
+                };
+                let high = match entry.high_pc {
+                    Some(Value::Constant(size)) => Some(low + size),
+                    _ => self.address(unit, &entry.high_pc)?,
+                };
+                Ok(high
+                    .map(|end| vec![Range { start: low, end }])
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    /// Returns the offset for DW_OP_fbreg locations.
+    fn fbreg(&self, location: &Option<Value>) -> Option<i64> {
+        let Some(Value::Expr(offset, len)) = *location else {
+            return None;
+        };
+        let mut stream = Stream::new(&self.info.reader, offset);
+        if len < 2 || stream.read_byte().ok()? != DW_OP_FBREG {
+            return None;
+        }
+        let value = decode_i64(&mut stream).ok()?;
+        (stream.offset == offset + len as i64).then_some(value)
+    }
+
+    /// DW_AT_frame_base is either an expression that applies to all of the function's
+    /// code or a location list (in .debug_loc).
+    fn frame_base(&self, unit: &Unit, value: &Option<Value>, ranges: &[Range<u64>]) -> FrameBases {
+        match *value {
+            Some(Value::Expr(offset, len)) => match self.frame_base_expr(offset, len) {
+                Some(base) => ranges.iter().map(|r| (r.clone(), base)).collect(),
+                None => Vec::new(),
+            },
+            Some(Value::SecOffset(offset)) | Some(Value::Constant(offset)) if unit.version < 5 => {
+                match self.read_frame_base_list(unit, offset) {
+                    Ok(list) => list,
+                    Err(err) => {
+                        utils::warn(&format!("failed to read .debug_loc: {err}"));
+                        Vec::new()
+                    }
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn frame_base_expr(&self, offset: Offset, len: u64) -> Option<FrameBase> {
+        let mut stream = Stream::new(&self.info.reader, offset);
+        let op = stream.read_byte().ok()?;
+        let base = match op {
+            DW_OP_CALL_FRAME_CFA => FrameBase::Cfa,
+            DW_OP_REG0..=DW_OP_REG31 => FrameBase::Register((op - DW_OP_REG0) as u16, 0),
+            DW_OP_BREG0..=DW_OP_BREG31 => {
+                FrameBase::Register((op - DW_OP_BREG0) as u16, decode_i64(&mut stream).ok()?)
+            }
+            _ => return None,
+        };
+        (stream.offset == offset + len as i64).then_some(base)
+    }
+
+    /// Reads a DWARF 4 location list (see 2.6.2) skipping the entries with frame bases
+    /// we can't evaluate.
+    fn read_frame_base_list(&self, unit: &Unit, offset: u64) -> Result<FrameBases, Box<dyn Error>> {
+        let Some(start) = self.info.sections.loc else {
+            return Err("no .debug_loc section".into());
+        };
+        let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+        let largest = u64::MAX >> (64 - 8 * unit.addr_size as u32);
+        let mut base = unit.base;
+        let mut list = Vec::new();
+        loop {
+            let begin = read_sized(&mut stream, unit.addr_size)?;
+            let end = read_sized(&mut stream, unit.addr_size)?;
+            if begin == 0 && end == 0 {
+                break;
+            } else if begin == largest {
+                base = end;
+            } else {
+                let len = stream.read_half()? as u64;
+                if let Some(frame_base) = self.frame_base_expr(stream.offset, len) {
+                    list.push((base + begin..base + end, frame_base));
+                }
+                stream.offset = stream.offset + len as i64;
+            }
+        }
+        Ok(list)
+    }
+
+    fn read_rnglist_at(&self, unit: &Unit, offset: u64) -> Result<Vec<Range<u64>>, Box<dyn Error>> {
+        let Some(start) = self.info.sections.rnglists else {
+            return Err("no .debug_rnglists section".into());
+        };
+        let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+        read_rnglist(&mut stream, unit.addr_size, unit.base, |i| {
+            self.indexed_address(unit, i)
+        })
+    }
+
+    /// DW_FORM_rnglistx is an index into the offsets table at DW_AT_rnglists_base and
+    /// those offsets are relative to the table.
+    fn rnglist_offset(&self, unit: &Unit, index: u64) -> Result<u64, Box<dyn Error>> {
+        let (Some(start), Some(base)) = (self.info.sections.rnglists, unit.rnglists_base) else {
+            return Err("DW_FORM_rnglistx without .debug_rnglists or DW_AT_rnglists_base".into());
+        };
+        let size = if unit.sixty_four { 8 } else { 4 };
+        let mut stream = Stream::new(&self.info.reader, start + (base + index * size) as i64);
+        Ok(base + read_offset(&mut stream, unit.sixty_four)?)
+    }
+
+    fn address(&self, unit: &Unit, value: &Option<Value>) -> Result<Option<u64>, Box<dyn Error>> {
+        match value {
+            Some(Value::Address(addr)) => Ok(Some(*addr)),
+            Some(Value::AddrIndex(index)) => Ok(Some(self.indexed_address(unit, *index)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the address at index in the unit's .debug_addr table.
+    fn indexed_address(&self, unit: &Unit, index: u64) -> Result<u64, Box<dyn Error>> {
+        let (Some(start), Some(base)) = (self.info.sections.addr, unit.addr_base) else {
+            return Err("DW_FORM_addrx without .debug_addr or DW_AT_addr_base".into());
+        };
+        let offset = base + index * unit.addr_size as u64;
+        let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+        read_sized(&mut stream, unit.addr_size)
+    }
+
+    fn string(&self, unit: &Unit, value: &Option<Value>) -> Result<Option<String>, Box<dyn Error>> {
+        match value {
+            Some(Value::Str(s)) => Ok(Some(s.clone())),
+            Some(Value::StrIndex(index)) => {
+                let (Some(start), Some(base)) =
+                    (self.info.sections.str_offsets, unit.str_offsets_base)
+                else {
+                    return Err(
+                        "DW_FORM_strx without .debug_str_offsets or DW_AT_str_offsets_base".into(),
+                    );
+                };
+                let size = if unit.sixty_four { 8 } else { 4 };
+                let mut stream =
+                    Stream::new(&self.info.reader, start + (base + index * size) as i64);
+                let offset = read_offset(&mut stream, unit.sixty_four)?;
+                match self.read_str(self.info.sections.strings, offset)? {
+                    Value::Str(s) => Ok(Some(s)),
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn read_value(
+        &self,
+        unit: &Unit,
+        stream: &mut Stream,
+        encoding: FormEncoding,
+        implicit: Option<i64>,
+    ) -> Result<Value, Box<dyn Error>> {
+        // See section 7.5.5
+        let value = match encoding {
+            FormEncoding::DW_FORM_addr => Value::Address(read_sized(stream, unit.addr_size)?),
+            FormEncoding::DW_FORM_data1 => Value::Constant(stream.read_byte()? as u64),
+            FormEncoding::DW_FORM_data2 => Value::Constant(stream.read_half()? as u64),
+            FormEncoding::DW_FORM_data4 => Value::Constant(stream.read_word()? as u64),
+            FormEncoding::DW_FORM_data8 => Value::Constant(stream.read_xword()?),
+            FormEncoding::DW_FORM_sdata => Value::Constant(decode_i64(stream)? as u64),
+            FormEncoding::DW_FORM_udata => Value::Constant(decode_u64(stream)?),
+            FormEncoding::DW_FORM_implicit_const => Value::Constant(implicit.unwrap_or(0) as u64),
+            FormEncoding::DW_FORM_data16 => skip(stream, 16),
+            FormEncoding::DW_FORM_string => Value::Str(stream.read_string()?),
+            FormEncoding::DW_FORM_strp => {
+                let offset = read_offset(stream, unit.sixty_four)?;
+                self.read_str(self.info.sections.strings, offset)?
+            }
+            FormEncoding::DW_FORM_line_strp => {
+                let offset = read_offset(stream, unit.sixty_four)?;
+                self.read_str(self.info.sections.line_strings, offset)?
+            }
+            FormEncoding::DW_FORM_ref1 => Value::Ref(unit.offset + stream.read_byte()? as u64),
+            FormEncoding::DW_FORM_ref2 => Value::Ref(unit.offset + stream.read_half()? as u64),
+            FormEncoding::DW_FORM_ref4 => Value::Ref(unit.offset + stream.read_word()? as u64),
+            FormEncoding::DW_FORM_ref8 => Value::Ref(unit.offset + stream.read_xword()?),
+            FormEncoding::DW_FORM_ref_udata => Value::Ref(unit.offset + decode_u64(stream)?),
+            FormEncoding::DW_FORM_ref_addr if unit.version == 2 => {
+                Value::Ref(read_sized(stream, unit.addr_size)?)
+            }
+            FormEncoding::DW_FORM_ref_addr => Value::Ref(read_offset(stream, unit.sixty_four)?),
+            FormEncoding::DW_FORM_sec_offset => {
+                Value::SecOffset(read_offset(stream, unit.sixty_four)?)
+            }
+            FormEncoding::DW_FORM_strp_sup => {
+                read_offset(stream, unit.sixty_four)?;
+                Value::Other
+            }
+            FormEncoding::DW_FORM_ref_sup4 => skip(stream, 4),
+            FormEncoding::DW_FORM_ref_sig8 | FormEncoding::DW_FORM_ref_sup8 => skip(stream, 8),
+            FormEncoding::DW_FORM_block1 => {
+                let len = stream.read_byte()? as u64;
+                expr(stream, len)
+            }
+            FormEncoding::DW_FORM_block2 => {
+                let len = stream.read_half()? as u64;
+                expr(stream, len)
+            }
+            FormEncoding::DW_FORM_block4 => {
+                let len = stream.read_word()? as u64;
+                expr(stream, len)
+            }
+            FormEncoding::DW_FORM_block | FormEncoding::DW_FORM_exprloc => {
+                let len = decode_u64(stream)?;
+                expr(stream, len)
+            }
+            FormEncoding::DW_FORM_flag => skip(stream, 1),
+            FormEncoding::DW_FORM_flag_present => Value::Other,
+            FormEncoding::DW_FORM_strx => Value::StrIndex(decode_u64(stream)?),
+            FormEncoding::DW_FORM_strx1 => Value::StrIndex(read_sized(stream, 1)?),
+            FormEncoding::DW_FORM_strx2 => Value::StrIndex(read_sized(stream, 2)?),
+            FormEncoding::DW_FORM_strx3 => Value::StrIndex(read_sized(stream, 3)?),
+            FormEncoding::DW_FORM_strx4 => Value::StrIndex(read_sized(stream, 4)?),
+            FormEncoding::DW_FORM_addrx => Value::AddrIndex(decode_u64(stream)?),
+            FormEncoding::DW_FORM_addrx1 => Value::AddrIndex(read_sized(stream, 1)?),
+            FormEncoding::DW_FORM_addrx2 => Value::AddrIndex(read_sized(stream, 2)?),
+            FormEncoding::DW_FORM_addrx3 => Value::AddrIndex(read_sized(stream, 3)?),
+            FormEncoding::DW_FORM_addrx4 => Value::AddrIndex(read_sized(stream, 4)?),
+            FormEncoding::DW_FORM_rnglistx => Value::RangesIndex(decode_u64(stream)?),
+            FormEncoding::DW_FORM_loclistx => {
+                decode_u64(stream)?;
+                Value::Other
+            }
+            FormEncoding::DW_FORM_indirect => {
+                let encoding = FormEncoding::from_u64(decode_u64(stream)?)?;
+                self.read_value(unit, stream, encoding, implicit)?
+            }
+        };
+        Ok(value)
+    }
+
+    fn read_str(&self, section: Option<Offset>, offset: u64) -> Result<Value, Box<dyn Error>> {
+        let Some(start) = section else {
+            return Err("missing string section".into());
+        };
+        let mut stream = Stream::new(&self.info.reader, start + offset as i64);
+        Ok(Value::Str(stream.read_string()?))
+    }
+
+    /// Returns the name and origin for the entry at offset within .debug_info. This is
+    /// usually in the unit being walked but DW_FORM_ref_addr can refer to other units.
+    fn find_name(&self, offset: u64) -> Option<(Option<String>, Option<u64>)> {
+        if let Some(value) = self.names.get(&offset) {
+            return Some(value.clone());
+        }
+
+        let units = &self.info.units;
+        let i = units.partition_point(|u| u.0 <= offset).checked_sub(1)?;
+        let unit = self.open_unit(units[i].0).ok()??;
+        let mut stream = Stream::new(&self.info.reader, self.info.sections.info + offset as i64);
+        let (_, entry) = self.read_entry(&unit, &mut stream).ok()??;
+        Some((self.string(&unit, &entry.name).ok()?, entry.origin))
+    }
+
+    /// Inlined functions and out of line definitions of member functions get their
+    /// names from the entries they refer to. So do the variables in inlined functions.
+    fn resolve_names(&self, functions: &mut [Function]) {
+        for f in functions.iter_mut() {
+            self.resolve_name(&mut f.name, f.origin);
+            for v in f.variables.iter_mut() {
+                self.resolve_name(&mut v.name, v.origin);
+            }
+            self.resolve_names(&mut f.inlined);
+        }
+    }
+
+    fn resolve_name(&self, name: &mut String, mut origin: Option<u64>) {
+        let mut depth = 0;
+        while name.is_empty()
+            && depth < 8
+            && let Some(o) = origin
+            && let Some((found, next)) = self.find_name(o)
+        {
+            *name = found.unwrap_or_default();
+            origin = next;
+            depth += 1;
+        }
+        if name.is_empty() {
+            *name = "?".to_string();
+        }
+    }
+}
+
+impl Entry {
+    fn set(&mut self, name: AttributeName, value: Value) {
+        match (name, value) {
+            (AttributeName::DW_AT_name, v @ (Value::Str(_) | Value::StrIndex(_))) => {
+                self.name = Some(v)
+            }
+            (AttributeName::DW_AT_abstract_origin, Value::Ref(r))
+            | (AttributeName::DW_AT_specification, Value::Ref(r)) => self.origin = Some(r),
+            (AttributeName::DW_AT_low_pc, v) => self.low_pc = Some(v),
+            (AttributeName::DW_AT_high_pc, v) => self.high_pc = Some(v),
+            (AttributeName::DW_AT_ranges, Value::SecOffset(o))
+            | (AttributeName::DW_AT_ranges, Value::Constant(o)) => {
+                self.ranges = Some(Value::SecOffset(o))
+            }
+            (AttributeName::DW_AT_ranges, v @ Value::RangesIndex(_)) => self.ranges = Some(v),
+            (AttributeName::DW_AT_call_file, Value::Constant(n)) => self.call_file = Some(n),
+            (AttributeName::DW_AT_call_line, Value::Constant(n)) => self.call_line = Some(n),
+            (AttributeName::DW_AT_call_column, Value::Constant(n)) => self.call_column = Some(n),
+            (AttributeName::DW_AT_location, v) => self.location = Some(v),
+            (AttributeName::DW_AT_frame_base, v) => self.frame_base = Some(v),
+            (AttributeName::DW_AT_stmt_list, Value::SecOffset(o))
+            | (AttributeName::DW_AT_stmt_list, Value::Constant(o)) => self.stmt_list = Some(o),
+            (AttributeName::DW_AT_str_offsets_base, Value::SecOffset(o)) => {
+                self.str_offsets_base = Some(o)
+            }
+            (AttributeName::DW_AT_addr_base, Value::SecOffset(o)) => self.addr_base = Some(o),
+            (AttributeName::DW_AT_rnglists_base, Value::SecOffset(o)) => {
+                self.rnglists_base = Some(o)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The functions and variables found while walking an entry's children.
+#[derive(Default)]
+struct Scope {
+    functions: Vec<Function>,
+    variables: Vec<StackVariable>,
+}
+
+// Unit types, see 7.5.1
+const DW_UT_COMPILE: u8 = 0x01;
+const DW_UT_PARTIAL: u8 = 0x03;
+
+// Location expression operations, see 7.7.1
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_REG31: u8 = 0x6f;
+const DW_OP_BREG0: u8 = 0x70;
+const DW_OP_BREG31: u8 = 0x8f;
+const DW_OP_FBREG: u8 = 0x91;
+const DW_OP_CALL_FRAME_CFA: u8 = 0x9c;
+
+// Range list entries, see 7.25
+const DW_RLE_END_OF_LIST: u8 = 0x00;
+const DW_RLE_BASE_ADDRESSX: u8 = 0x01;
+const DW_RLE_STARTX_ENDX: u8 = 0x02;
+const DW_RLE_STARTX_LENGTH: u8 = 0x03;
+const DW_RLE_OFFSET_PAIR: u8 = 0x04;
+const DW_RLE_BASE_ADDRESS: u8 = 0x05;
+const DW_RLE_START_END: u8 = 0x06;
+const DW_RLE_START_LENGTH: u8 = 0x07;
+
+/// Reads a DWARF 4 range list from .debug_ranges, see 2.17.3.
+fn read_ranges(
+    stream: &mut Stream,
+    addr_size: u8,
+    mut base: u64,
+) -> Result<Vec<Range<u64>>, Box<dyn Error>> {
+    let largest = u64::MAX >> (64 - 8 * addr_size as u32);
+    let mut ranges = Vec::new();
+    loop {
+        let start = read_sized(stream, addr_size)?;
+        let end = read_sized(stream, addr_size)?;
+        if start == 0 && end == 0 {
+            break;
+        } else if start == largest {
+            base = end;
+        } else {
+            ranges.push(base + start..base + end);
+        }
+    }
+    Ok(ranges)
+}
+
+/// Reads a DWARF 5 range list from .debug_rnglists. address maps an index into the
+/// unit's .debug_addr table to an address.
+fn read_rnglist(
+    stream: &mut Stream,
+    addr_size: u8,
+    mut base: u64,
+    address: impl Fn(u64) -> Result<u64, Box<dyn Error>>,
+) -> Result<Vec<Range<u64>>, Box<dyn Error>> {
+    let mut ranges = Vec::new();
+    loop {
+        let kind = stream.read_byte()?;
+        match kind {
+            DW_RLE_END_OF_LIST => break,
+            DW_RLE_BASE_ADDRESSX => base = address(decode_u64(stream)?)?,
+            DW_RLE_STARTX_ENDX => {
+                let start = address(decode_u64(stream)?)?;
+                let end = address(decode_u64(stream)?)?;
+                ranges.push(start..end);
+            }
+            DW_RLE_STARTX_LENGTH => {
+                let start = address(decode_u64(stream)?)?;
+                let len = decode_u64(stream)?;
+                ranges.push(start..start + len);
+            }
+            DW_RLE_OFFSET_PAIR => {
+                let start = decode_u64(stream)?;
+                let end = decode_u64(stream)?;
+                ranges.push(base + start..base + end);
+            }
+            DW_RLE_BASE_ADDRESS => base = read_sized(stream, addr_size)?,
+            DW_RLE_START_END => {
+                let start = read_sized(stream, addr_size)?;
+                let end = read_sized(stream, addr_size)?;
+                ranges.push(start..end);
+            }
+            DW_RLE_START_LENGTH => {
+                let start = read_sized(stream, addr_size)?;
+                let len = decode_u64(stream)?;
+                ranges.push(start..start + len);
+            }
+            _ => return Err(format!("unsupported range list entry 0x{kind:x}").into()),
+        }
+    }
+    Ok(ranges)
+}
+
+/// Returns whether the unit uses the 64-bit format along with the length of the rest of
+/// the unit.
+fn read_unit_length(stream: &mut Stream) -> Result<(bool, u64), Box<dyn Error>> {
+    let word = stream.read_word()?;
+    if word == 0xffffffff {
+        Ok((true, stream.read_xword()?))
+    } else {
+        Ok((false, word as u64))
+    }
+}
+
+fn insert_ranges(index: &mut RangeMap<u64, usize>, ranges: &[Range<u64>], unit: usize) {
+    for range in ranges.iter().filter(|r| r.start < r.end) {
+        index.insert(range.clone(), unit);
+    }
+}
+
+fn read_sized(stream: &mut Stream, size: u8) -> Result<u64, Box<dyn Error>> {
+    match size {
+        1 => Ok(stream.read_byte()? as u64),
+        2 => Ok(stream.read_half()? as u64),
+        3 => {
+            let bytes = stream.reader.slice(stream.offset, 3)?;
+            let value = stream.reader.endian.decode(bytes);
+            stream.offset = stream.offset + 3;
+            Ok(value)
+        }
+        4 => Ok(stream.read_word()? as u64),
+        8 => stream.read_xword(),
+        _ => Err(format!("bad size: {size}").into()),
+    }
+}
+
+fn read_offset(stream: &mut Stream, sixty_four: bool) -> Result<u64, Box<dyn Error>> {
+    read_sized(stream, if sixty_four { 8 } else { 4 })
+}
+
+fn skip(stream: &mut Stream, len: i64) -> Value {
+    stream.offset = stream.offset + len;
+    Value::Other
+}
+
+fn expr(stream: &mut Stream, len: u64) -> Value {
+    let offset = stream.offset;
+    stream.offset = stream.offset + len as i64;
+    Value::Expr(offset, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::core_builder::Encoder;
+
+    #[test]
+    fn debug_ranges() {
+        let mut e = Encoder::new(true, true);
+        e.xword(0x10).xword(0x20); // relative to the unit's low_pc
+        e.xword(u64::MAX).xword(0x5000); // new base address
+        e.xword(0x8).xword(0xc);
+        e.xword(0).xword(0);
+        let ranges = read_ranges(&mut e.stream(), 8, 0x1000).unwrap();
+        assert_eq!(ranges, vec![0x1010..0x1020, 0x5008..0x500c]);
+    }
+
+    #[test]
+    fn debug_ranges_32_bit() {
+        let mut e = Encoder::new(false, true);
+        e.word(0xffffffff).word(0x2000);
+        e.word(0x4).word(0x8);
+        e.word(0).word(0);
+        let ranges = read_ranges(&mut e.stream(), 4, 0).unwrap();
+        assert_eq!(ranges, vec![0x2004..0x2008]);
+    }
+
+    #[test]
+    fn rnglists() {
+        let table = [0x7000, 0x7100, 0x7200];
+        let address = |i: u64| -> Result<u64, Box<dyn Error>> { Ok(table[i as usize]) };
+
+        let mut e = Encoder::new(true, true);
+        e.byte(DW_RLE_OFFSET_PAIR).byte(0x10).byte(0x20);
+        e.byte(DW_RLE_BASE_ADDRESSX).byte(1);
+        e.byte(DW_RLE_OFFSET_PAIR).byte(0x4).byte(0x8);
+        e.byte(DW_RLE_STARTX_ENDX).byte(0).byte(2);
+        e.byte(DW_RLE_STARTX_LENGTH).byte(2).byte(0x30);
+        e.byte(DW_RLE_BASE_ADDRESS).xword(0x9000);
+        e.byte(DW_RLE_OFFSET_PAIR).byte(0x1).byte(0x2);
+        e.byte(DW_RLE_START_END).xword(0xa000).xword(0xa010);
+        e.byte(DW_RLE_START_LENGTH).xword(0xb000).byte(0x8);
+        e.byte(DW_RLE_END_OF_LIST);
+        let ranges = read_rnglist(&mut e.stream(), 8, 0x1000, address).unwrap();
+        assert_eq!(
+            ranges,
+            vec![
+                0x1010..0x1020,
+                0x7104..0x7108,
+                0x7000..0x7200,
+                0x7200..0x7230,
+                0x9001..0x9002,
+                0xa000..0xa010,
+                0xb000..0xb008,
+            ]
+        );
+    }
+
+    #[test]
+    fn release_units() {
+        let path = std::path::PathBuf::from("cores/shopping-release/app-release");
+        let exe = ElfFile::new(path).unwrap();
+        let info = exe.get_debug_info().as_ref().unwrap();
+
+        // main.c uses DW_AT_ranges, item.c uses DW_AT_low_pc and a DW_AT_high_pc offset.
+        let unit = info.find_unit(RelativeAddr(0x1100)).unwrap();
+        assert_eq!(unit.ranges, vec![0x1080..0x112a]);
+        let unit = info.find_unit(RelativeAddr(0x1250)).unwrap();
+        assert_eq!(unit.ranges, vec![0x1240..0x1328]);
+        assert!(info.find_unit(RelativeAddr(0x10)).is_none());
+
+        // printf was inlined into two ranges of main.
+        let names: Vec<_> = info
+            .find_functions(RelativeAddr(0x1119))
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "printf"]);
+        let names: Vec<_> = info
+            .find_functions(RelativeAddr(0x1116))
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main"]);
+    }
+
+    #[test]
+    fn lazy_units() {
+        let path = std::path::PathBuf::from("cores/shopping-release/app-release");
+        let exe = ElfFile::new(path).unwrap();
+        let info = exe.get_debug_info().as_ref().unwrap();
+        assert_eq!(info.units.len(), 2);
+        assert!(info.units.iter().all(|u| u.1.get().is_none()));
+
+        // Only main.c's unit is parsed.
+        let line = info.find_line(RelativeAddr(0x1080)).unwrap();
+        assert_eq!(line, ("/home/vorisek/shopping/main.c".to_string(), 5, 12));
+        let parsed: Vec<_> = info.units.iter().map(|u| u.1.get().is_some()).collect();
+        assert_eq!(parsed, vec![false, true]);
+    }
+
+    #[test]
+    fn index_without_aranges() {
+        for name in ["debug", "release"] {
+            let path = std::path::PathBuf::from(format!("cores/shopping-{name}/app-{name}"));
+            let exe = ElfFile::new(path).unwrap();
+            let mut info = DebugInfo::new(&exe).unwrap();
+            let expected = info.get_index().clone();
+            assert!(!expected.is_empty());
+
+            info.sections.aranges = None;
+            info.index = OnceCell::new();
+            assert_eq!(info.get_index(), &expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn lines_match_line_table() {
+        for name in ["debug", "release"] {
+            let path = std::path::PathBuf::from(format!("cores/shopping-{name}/app-{name}"));
+            let exe = ElfFile::new(path).unwrap();
+            let info = exe.get_debug_info().as_ref().unwrap();
+            let lines = exe.get_lines().as_ref().unwrap();
+            for (range, _) in lines.lines.iter() {
+                if info.find_unit(range.start).is_some() {
+                    assert_eq!(
+                        info.find_line(range.start),
+                        lines.find(range.start),
+                        "{name} 0x{:x}",
+                        range.start.0
+                    );
+                }
+            }
+        }
+    }
+}
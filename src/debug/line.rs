@@ -1,7 +1,8 @@
 //! TODO talk a bit about what this is useful for
 //! mention that it is .debug_line
 use crate::{
-    elf::{Offset, RelativeAddr, Stream},
+    debug::{FormEncoding, decode_i64},
+    elf::{Offset, Reader, RelativeAddr, Stream},
     utils::{self, KeyMap, key16},
 };
 use rangemap::RangeMap;
@@ -38,6 +39,9 @@ pub struct LineValue {
 }
 
 pub struct CompilationUnit {
+    /// DWARF version of the line program, this affects how files are numbered
+    pub version: u16,
+
     /// paths that were searched for includes
     pub include_paths: Vec<String>,
 
@@ -59,7 +63,19 @@ pub struct SourceFile {
     pub length: Option<u32>,
 }
 
+/// Offsets of the string sections that DWARF 5 line program headers may refer to.
+pub struct LineStrings {
+    /// .debug_str
+    pub strings: Option<Offset>,
+
+    /// .debug_line_str
+    pub line_strings: Option<Offset>,
+}
+
 struct RawLineInfo {
+    /// DWARF version of the line program
+    version: u16,
+
     /// size in bytes of the smallest target machine instruction
     min_instruction_len: u8,
 
@@ -134,7 +150,7 @@ enum Opcode {
     /// Standard opcodes
     Copy,
     AdvancePc(u32),
-    AdvanceLine(u32),
+    AdvanceLine(i32),
     SetFile(u32),
     SetColumn(u32),
     NegateStmt,
@@ -157,11 +173,34 @@ enum Opcode {
 }
 
 impl LineInfo {
-    pub fn new(stream: &mut Stream, max_offset: Offset) -> Self {
-        let mut infos = LineInfo::parse_raw_infos(stream, max_offset);
+    pub fn new(stream: &mut Stream, max_offset: Offset, strings: &LineStrings) -> Self {
+        let mut infos = LineInfo::parse_raw_infos(stream, max_offset, strings);
+        LineInfo::from_raw_infos(&mut infos)
+    }
+
+    /// Like new except that only the line program at offset within .debug_line (i.e.
+    /// a DW_AT_stmt_list) is parsed.
+    pub fn new_unit(
+        reader: &Reader,
+        section: Offset,
+        offset: u64,
+        strings: &LineStrings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut stream = Stream::new(reader, section + offset as i64);
+        let info = RawLineInfo::new(&mut stream, strings)?;
+        Ok(LineInfo::from_raw_infos(&mut [info]))
+    }
+
+    /// Returns the file, line number, and column for addr.
+    pub fn find(&self, addr: RelativeAddr) -> Option<(String, u32, u16)> {
+        let value = self.lines.get(&addr)?;
+        Some((self.files.get(value.file).clone(), value.line, value.column))
+    }
+
+    fn from_raw_infos(infos: &mut [RawLineInfo]) -> Self {
         let mut files = KeyMap::new("?".to_string());
-        let units = LineInfo::get_units(&mut infos);
-        let lines = LineInfo::find_lines(&infos, &mut files);
+        let units = LineInfo::get_units(infos);
+        let lines = LineInfo::find_lines(infos, &mut files);
         LineInfo {
             files,
             units,
@@ -169,10 +208,14 @@ impl LineInfo {
         }
     }
 
-    fn parse_raw_infos(stream: &mut Stream, max_offset: Offset) -> Vec<RawLineInfo> {
+    fn parse_raw_infos(
+        stream: &mut Stream,
+        max_offset: Offset,
+        strings: &LineStrings,
+    ) -> Vec<RawLineInfo> {
         let mut infos = Vec::new();
         while stream.offset < max_offset {
-            match RawLineInfo::new(stream) {
+            match RawLineInfo::new(stream, strings) {
                 Ok(info) => infos.push(info),
                 Err(err) => {
                     utils::warn(&format!("failed to read line info: {err}"));
@@ -189,6 +232,7 @@ impl LineInfo {
             let include_paths = std::mem::take(&mut info.include_paths);
             let source_files = info.source_files.clone(); // TODO it's a little tricky to take this once because run() needs it in place
             units.push(CompilationUnit {
+                version: info.version,
                 include_paths,
                 source_files,
             });
@@ -210,7 +254,11 @@ impl LineInfo {
         if !lines.is_empty() {
             let (mut addr, mut info) = lines[0];
             for (next_addr, next_info) in lines.iter().skip(1) {
-                map.insert(addr..(*next_addr), info);
+                // Rows can share an address and addresses go backwards when a new
+                // sequence starts.
+                if *next_addr > addr {
+                    map.insert(addr..(*next_addr), info);
+                }
                 addr = *next_addr;
                 info = *next_info;
             }
@@ -220,17 +268,25 @@ impl LineInfo {
     }
 }
 
+impl CompilationUnit {
+    /// Returns the path for a file number, e.g. from DW_AT_call_file.
+    pub fn file_path(&self, index: u32) -> Option<String> {
+        let file = self.source_files.get(file_index(self.version, index)?)?;
+        Some(format!("{}/{}", file.dir, file.file))
+    }
+}
+
 impl RawLineInfo {
-    fn new(stream: &mut Stream) -> Result<Self, Box<dyn Error>> {
-        let _start = stream.offset; // 6.2.4
+    fn new(stream: &mut Stream, strings: &LineStrings) -> Result<Self, Box<dyn Error>> {
         let len = stream.read_word()? as usize;
-        let _length = if len == 0xffffffff {
-            stream.read_xword()? as usize
+        let (sixty_four, length) = if len == 0xffffffff {
+            (true, stream.read_xword()? as usize)
         } else {
-            len
+            (false, len)
         };
+        let end = stream.offset + length as i64;
         let version = stream.read_half()?;
-        if !(version == 2 || version == 3) {
+        if !(2..=5).contains(&version) {
             return Err(format!("unsupported dwarf version: {version}").into());
         }
         let (_address_size, _segment_selector_size) = if version >= 5 {
@@ -238,7 +294,7 @@ impl RawLineInfo {
         } else {
             (None, None)
         };
-        let _header_length = if version >= 5 {
+        let _header_length = if sixty_four {
             stream.read_xword()? as usize
         } else {
             stream.read_word()? as usize
@@ -256,7 +312,51 @@ impl RawLineInfo {
             opcode_sizes.insert(i + 1, size);
         }
 
-        // TODO the rest of this is quite different in version 5
+        let (include_paths, source_files) = if version >= 5 {
+            let dirs = read_entry_table(stream, sixty_four, strings)?;
+            let include_paths: Vec<String> = dirs.into_iter().map(|(path, _)| path).collect();
+            let files = read_entry_table(stream, sixty_four, strings)?;
+            let source_files = files
+                .into_iter()
+                .map(|(file, index)| SourceFile {
+                    file,
+                    dir: include_paths
+                        .get(index as usize)
+                        .cloned()
+                        .unwrap_or_else(|| format!("bad dir index {index}")),
+                    length: None,
+                })
+                .collect();
+            (include_paths, source_files)
+        } else {
+            RawLineInfo::read_v4_tables(stream)?
+        };
+
+        // This isn't documented very well, but opcodes follow the header. And after the
+        // opcodes more headers/opcodes will follow for the remaining source files.
+        let mut opcodes = Vec::new();
+        while stream.offset < end {
+            opcodes.push(Opcode::from_steam(stream, opcode_base)?);
+        }
+
+        Ok(RawLineInfo {
+            version,
+            min_instruction_len,
+            max_ops_per_instruction,
+            default_is_stmt,
+            line_base,
+            line_range,
+            opcode_base,
+            include_paths,
+            source_files,
+            opcodes,
+        })
+    }
+
+    /// Include directories and file names for versions 2 through 4.
+    fn read_v4_tables(
+        stream: &mut Stream,
+    ) -> Result<(Vec<String>, Vec<SourceFile>), Box<dyn Error>> {
         let mut include_paths = Vec::new();
         loop {
             let s = stream.read_string()?;
@@ -285,31 +385,7 @@ impl RawLineInfo {
             let length = if len == 0 { None } else { Some(len) };
             source_files.push(SourceFile { file, dir, length });
         }
-        // println!("offset: 0x{:x}", stream.offset.0 - start.0);
-
-        // This isn't documented very well, but opcodes follow the header. And after the
-        // opcodes more headers/opcodes will follow for the remaining source files.
-        let mut opcodes = Vec::new();
-        loop {
-            let opcode = Opcode::from_steam(stream, opcode_base)?;
-            let done = opcode == Opcode::EndSequence;
-            opcodes.push(opcode);
-            if done {
-                break;
-            }
-        }
-
-        Ok(RawLineInfo {
-            min_instruction_len,
-            max_ops_per_instruction,
-            default_is_stmt,
-            line_base,
-            line_range,
-            opcode_base,
-            include_paths,
-            source_files,
-            opcodes,
-        })
+        Ok((include_paths, source_files))
     }
 
     fn default_path(&self) -> String {
@@ -446,11 +522,11 @@ impl StateMachine {
                 Opcode::SetFile(file) => {
                     // I think this would be used for things like inline functions in an
                     // include for the compilation unit being compiled.
-                    self.file = if (*file as usize) <= info.source_files.len() {
-                        let sf = &info.source_files[*file as usize];
-                        format!("{}/{}", sf.dir, sf.file)
-                    } else {
-                        format!("bad file index {file}")
+                    self.file = match file_index(info.version, *file)
+                        .and_then(|i| info.source_files.get(i))
+                    {
+                        Some(sf) => format!("{}/{}", sf.dir, sf.file),
+                        None => format!("bad file index {file}"),
                     };
                     // println!("file: {}", self.file);
                 }
@@ -515,7 +591,7 @@ impl Opcode {
             match code {
                 1 => Opcode::Copy,
                 2 => Opcode::AdvancePc(decode_u32(stream)?),
-                3 => Opcode::AdvanceLine(decode_u32(stream)?),
+                3 => Opcode::AdvanceLine(decode_i64(stream)? as i32),
                 4 => Opcode::SetFile(decode_u32(stream)?),
                 5 => Opcode::SetColumn(decode_u32(stream)?),
                 6 => Opcode::NegateStmt,
@@ -560,28 +636,84 @@ impl Opcode {
     }
 }
 
-// #[derive(Clone, Copy, Debug, PartialEq, Eq)] // 6.2.4.1
-// pub enum ContentType {
-//     Path,
-//     DirIndex,
-//     Timestamp,
-//     Size,
-//     Md5Sum,
-//     Vendor(u32),
-// }
-
-// impl ContentType {
-//     fn from_u32(value: u32) -> Self {
-//         match value {
-//             1 => ContentType::Path,
-//             2 => ContentType::DirIndex,
-//             3 => ContentType::Timestamp,
-//             4 => ContentType::Size,
-//             5 => ContentType::Md5Sum,
-//             _ => ContentType::Vendor(value),
-//         }
-//     }
-// }
+// 6.2.4.1
+const DW_LNCT_PATH: u32 = 1;
+const DW_LNCT_DIRECTORY_INDEX: u32 = 2;
+
+/// File numbers start at 1 before version 5, in version 5 file 0 is the primary source
+/// file.
+fn file_index(version: u16, index: u32) -> Option<usize> {
+    if version >= 5 {
+        Some(index as usize)
+    } else {
+        (index as usize).checked_sub(1)
+    }
+}
+
+/// Reads a version 5 directory or file name table. Returns the path and directory index
+/// for each entry.
+fn read_entry_table(
+    stream: &mut Stream,
+    sixty_four: bool,
+    strings: &LineStrings,
+) -> Result<Vec<(String, u32)>, Box<dyn Error>> {
+    let format_count = stream.read_byte()?;
+    let mut formats = Vec::with_capacity(format_count as usize);
+    for _ in 0..format_count {
+        let content = decode_u32(stream)?;
+        let form = FormEncoding::from_u64(decode_u32(stream)? as u64)?;
+        formats.push((content, form));
+    }
+
+    let count = decode_u32(stream)?;
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut path = String::new();
+        let mut dir = 0;
+        for (content, form) in formats.iter() {
+            let mut number = 0;
+            let mut text = None;
+            match form {
+                FormEncoding::DW_FORM_string => text = Some(stream.read_string()?),
+                FormEncoding::DW_FORM_line_strp | FormEncoding::DW_FORM_strp => {
+                    let offset = if sixty_four {
+                        stream.read_xword()?
+                    } else {
+                        stream.read_word()? as u64
+                    };
+                    let section = if *form == FormEncoding::DW_FORM_strp {
+                        strings.strings
+                    } else {
+                        strings.line_strings
+                    };
+                    let Some(section) = section else {
+                        return Err(format!("no string section for {form:?}").into());
+                    };
+                    let mut s = Stream::new(&stream.reader, section + offset as i64);
+                    text = Some(s.read_string()?);
+                }
+                FormEncoding::DW_FORM_udata => number = decode_u32(stream)?,
+                FormEncoding::DW_FORM_data1 => number = stream.read_byte()? as u32,
+                FormEncoding::DW_FORM_data2 => number = stream.read_half()? as u32,
+                FormEncoding::DW_FORM_data4 => number = stream.read_word()?,
+                FormEncoding::DW_FORM_data8 => number = stream.read_xword()? as u32,
+                FormEncoding::DW_FORM_data16 => stream.offset = stream.offset + 16, // MD5
+                FormEncoding::DW_FORM_block => {
+                    let len = decode_u32(stream)?;
+                    stream.offset = stream.offset + len as i64;
+                }
+                _ => return Err(format!("unexpected line table form {form:?}").into()),
+            }
+            match *content {
+                DW_LNCT_PATH => path = text.unwrap_or_default(),
+                DW_LNCT_DIRECTORY_INDEX => dir = number,
+                _ => (),
+            }
+        }
+        entries.push((path, dir));
+    }
+    Ok(entries)
+}
 
 // section 7.6
 /// LEB128 encoded
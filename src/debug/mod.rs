@@ -6,16 +6,20 @@
 use std::error::Error;
 
 pub mod abbrev;
+pub mod aranges;
+pub mod info;
 pub mod line;
 pub mod symbols;
 pub mod types;
 
 pub use abbrev::*;
+pub use aranges::*;
+pub use info::*;
 pub use line::*;
 pub use symbols::*;
 pub use types::*;
 
-use crate::elf::{Offset, Stream};
+use crate::elf::Stream;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)] // figure 20
@@ -113,6 +117,37 @@ pub enum AttributeName {
     DW_AT_const_expr,              // ‡ 0x6c flag
     DW_AT_enum_class,              // ‡ 0x6d flag
     DW_AT_linkage_name,            // ‡ 0x6e string
+    DW_AT_string_length_bit_size,  // 0x6f constant
+    DW_AT_string_length_byte_size, // 0x70 constant
+    DW_AT_rank,                    // 0x71 constant, exprloc
+    DW_AT_str_offsets_base,        // 0x72 stroffsetsptr
+    DW_AT_addr_base,               // 0x73 addrptr
+    DW_AT_rnglists_base,           // 0x74 rnglistsptr
+    DW_AT_dwo_name,                // 0x76 string
+    DW_AT_reference,               // 0x77 flag
+    DW_AT_rvalue_reference,        // 0x78 flag
+    DW_AT_macros,                  // 0x79 macptr
+    DW_AT_call_all_calls,          // 0x7a flag
+    DW_AT_call_all_source_calls,   // 0x7b flag
+    DW_AT_call_all_tail_calls,     // 0x7c flag
+    DW_AT_call_return_pc,          // 0x7d address
+    DW_AT_call_value,              // 0x7e exprloc
+    DW_AT_call_origin,             // 0x7f exprloc
+    DW_AT_call_parameter,          // 0x80 reference
+    DW_AT_call_pc,                 // 0x81 address
+    DW_AT_call_tail_call,          // 0x82 flag
+    DW_AT_call_target,             // 0x83 exprloc
+    DW_AT_call_target_clobbered,   // 0x84 exprloc
+    DW_AT_call_data_location,      // 0x85 exprloc
+    DW_AT_call_data_value,         // 0x86 exprloc
+    DW_AT_noreturn,                // 0x87 flag
+    DW_AT_alignment,               // 0x88 constant
+    DW_AT_export_symbols,          // 0x89 flag
+    DW_AT_deleted,                 // 0x8a flag
+    DW_AT_defaulted,               // 0x8b constant
+    DW_AT_loclists_base,           // 0x8c loclistsptr
+    DW_AT_GNU_locviews,            // 0x2137 loclistptr
+    DW_AT_GNU_entry_view,          // 0x2138 constant
     DW_AT_GNU_all_tail_call_sites, // 0x2116 flag, see https://sourceware.org/elfutils/DwarfExtensions
     DW_AT_GNU_all_call_sites,      // 0x2117 flag
     DW_AT_user,                    // [0x2000, 0x3fff) ---
@@ -182,6 +217,14 @@ pub enum Tag {
     DW_TAG_type_unit,                // ‡, // 0x41
     DW_TAG_rvalue_reference_type,    // ‡, // 0x42
     DW_TAG_template_alias,           // ‡, // 0x43
+    DW_TAG_coarray_type,             // 0x44
+    DW_TAG_generic_subrange,         // 0x45
+    DW_TAG_dynamic_type,             // 0x46
+    DW_TAG_atomic_type,              // 0x47
+    DW_TAG_call_site,                // 0x48
+    DW_TAG_call_site_parameter,      // 0x49
+    DW_TAG_skeleton_unit,            // 0x4a
+    DW_TAG_immutable_type,           // 0x4b
     DW_TAG_user,                     // [0x4080, 0xffff]
 }
 
@@ -189,30 +232,49 @@ pub enum Tag {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FormEncoding {
     //                       value & class
-    DW_FORM_addr,         // 0x01 address
-    DW_FORM_block2,       // 0x03 block
-    DW_FORM_block4,       // 0x04 block
-    DW_FORM_data2,        // 0x05 constant
-    DW_FORM_data4,        // 0x06 constant
-    DW_FORM_data8,        // 0x07 constant
-    DW_FORM_string,       // 0x08 string
-    DW_FORM_block,        // 0x09 block
-    DW_FORM_block1,       // 0x0a block
-    DW_FORM_data1,        // 0x0b constant
-    DW_FORM_flag,         // 0x0c flag
-    DW_FORM_sdata,        // 0x0d constant
-    DW_FORM_strp,         // 0x0e string
-    DW_FORM_udata,        // 0x0f constant
-    DW_FORM_ref_addr,     // 0x10 reference
-    DW_FORM_ref1,         // 0x11 reference
-    DW_FORM_ref2,         // 0x12 reference
-    DW_FORM_ref4,         // 0x13 reference
-    DW_FORM_ref8,         // 0x14 reference
-    DW_FORM_ref_udata,    // 0x15 reference
-    DW_FORM_indirect,     // 0x16 (see Section 7.5.3 on page 203)
+    DW_FORM_addr,           // 0x01 address
+    DW_FORM_block2,         // 0x03 block
+    DW_FORM_block4,         // 0x04 block
+    DW_FORM_data2,          // 0x05 constant
+    DW_FORM_data4,          // 0x06 constant
+    DW_FORM_data8,          // 0x07 constant
+    DW_FORM_string,         // 0x08 string
+    DW_FORM_block,          // 0x09 block
+    DW_FORM_block1,         // 0x0a block
+    DW_FORM_data1,          // 0x0b constant
+    DW_FORM_flag,           // 0x0c flag
+    DW_FORM_sdata,          // 0x0d constant
+    DW_FORM_strp,           // 0x0e string
+    DW_FORM_udata,          // 0x0f constant
+    DW_FORM_ref_addr,       // 0x10 reference
+    DW_FORM_ref1,           // 0x11 reference
+    DW_FORM_ref2,           // 0x12 reference
+    DW_FORM_ref4,           // 0x13 reference
+    DW_FORM_ref8,           // 0x14 reference
+    DW_FORM_ref_udata,      // 0x15 reference
+    DW_FORM_indirect,       // 0x16 (see Section 7.5.3 on page 203)
     DW_FORM_sec_offset, // 0x17 addrptr, lineptr, loclist, loclistsptr, macptr, rnglist, rnglistsptr, stroffsetsptr
     DW_FORM_exprloc,    // 0x18 exprloc
     DW_FORM_flag_present, //0x19 flag
+    DW_FORM_strx,       // 0x1a string
+    DW_FORM_addrx,      // 0x1b address
+    DW_FORM_ref_sup4,   // 0x1c reference
+    DW_FORM_strp_sup,   // 0x1d string
+    DW_FORM_data16,     // 0x1e constant
+    DW_FORM_line_strp,  // 0x1f string
+    DW_FORM_ref_sig8,   // 0x20 reference
+    DW_FORM_implicit_const, // 0x21 constant
+    DW_FORM_loclistx,   // 0x22 loclist
+    DW_FORM_rnglistx,   // 0x23 rnglist
+    DW_FORM_ref_sup8,   // 0x24 reference
+    DW_FORM_strx1,      // 0x25 string
+    DW_FORM_strx2,      // 0x26 string
+    DW_FORM_strx3,      // 0x27 string
+    DW_FORM_strx4,      // 0x28 string
+    DW_FORM_addrx1,     // 0x29 address
+    DW_FORM_addrx2,     // 0x2a address
+    DW_FORM_addrx3,     // 0x2b address
+    DW_FORM_addrx4,     // 0x2c address
 }
 
 impl AttributeName {
@@ -310,8 +372,39 @@ impl AttributeName {
             0x6c => Ok(AttributeName::DW_AT_const_expr),
             0x6d => Ok(AttributeName::DW_AT_enum_class),
             0x6e => Ok(AttributeName::DW_AT_linkage_name),
+            0x6f => Ok(AttributeName::DW_AT_string_length_bit_size),
+            0x70 => Ok(AttributeName::DW_AT_string_length_byte_size),
+            0x71 => Ok(AttributeName::DW_AT_rank),
+            0x72 => Ok(AttributeName::DW_AT_str_offsets_base),
+            0x73 => Ok(AttributeName::DW_AT_addr_base),
+            0x74 => Ok(AttributeName::DW_AT_rnglists_base),
+            0x76 => Ok(AttributeName::DW_AT_dwo_name),
+            0x77 => Ok(AttributeName::DW_AT_reference),
+            0x78 => Ok(AttributeName::DW_AT_rvalue_reference),
+            0x79 => Ok(AttributeName::DW_AT_macros),
+            0x7a => Ok(AttributeName::DW_AT_call_all_calls),
+            0x7b => Ok(AttributeName::DW_AT_call_all_source_calls),
+            0x7c => Ok(AttributeName::DW_AT_call_all_tail_calls),
+            0x7d => Ok(AttributeName::DW_AT_call_return_pc),
+            0x7e => Ok(AttributeName::DW_AT_call_value),
+            0x7f => Ok(AttributeName::DW_AT_call_origin),
+            0x80 => Ok(AttributeName::DW_AT_call_parameter),
+            0x81 => Ok(AttributeName::DW_AT_call_pc),
+            0x82 => Ok(AttributeName::DW_AT_call_tail_call),
+            0x83 => Ok(AttributeName::DW_AT_call_target),
+            0x84 => Ok(AttributeName::DW_AT_call_target_clobbered),
+            0x85 => Ok(AttributeName::DW_AT_call_data_location),
+            0x86 => Ok(AttributeName::DW_AT_call_data_value),
+            0x87 => Ok(AttributeName::DW_AT_noreturn),
+            0x88 => Ok(AttributeName::DW_AT_alignment),
+            0x89 => Ok(AttributeName::DW_AT_export_symbols),
+            0x8a => Ok(AttributeName::DW_AT_deleted),
+            0x8b => Ok(AttributeName::DW_AT_defaulted),
+            0x8c => Ok(AttributeName::DW_AT_loclists_base),
             0x2116 => Ok(AttributeName::DW_AT_GNU_all_tail_call_sites),
             0x2117 => Ok(AttributeName::DW_AT_GNU_all_call_sites),
+            0x2137 => Ok(AttributeName::DW_AT_GNU_locviews),
+            0x2138 => Ok(AttributeName::DW_AT_GNU_entry_view),
             0x2000..0x3fff => Ok(AttributeName::DW_AT_user),
             _ => Err(format!("unknown attribute name encoding: {value}").into()),
         }
@@ -381,6 +474,14 @@ impl Tag {
             0x41 => Ok(Tag::DW_TAG_type_unit),
             0x42 => Ok(Tag::DW_TAG_rvalue_reference_type),
             0x43 => Ok(Tag::DW_TAG_template_alias),
+            0x44 => Ok(Tag::DW_TAG_coarray_type),
+            0x45 => Ok(Tag::DW_TAG_generic_subrange),
+            0x46 => Ok(Tag::DW_TAG_dynamic_type),
+            0x47 => Ok(Tag::DW_TAG_atomic_type),
+            0x48 => Ok(Tag::DW_TAG_call_site),
+            0x49 => Ok(Tag::DW_TAG_call_site_parameter),
+            0x4a => Ok(Tag::DW_TAG_skeleton_unit),
+            0x4b => Ok(Tag::DW_TAG_immutable_type),
             0x4080..0xffff => Ok(Tag::DW_TAG_user),
             _ => Err(format!("unknown tag encoding: {value}").into()),
         }
@@ -414,6 +515,25 @@ impl FormEncoding {
             0x17 => Ok(FormEncoding::DW_FORM_sec_offset),
             0x18 => Ok(FormEncoding::DW_FORM_exprloc),
             0x19 => Ok(FormEncoding::DW_FORM_flag_present),
+            0x1a => Ok(FormEncoding::DW_FORM_strx),
+            0x1b => Ok(FormEncoding::DW_FORM_addrx),
+            0x1c => Ok(FormEncoding::DW_FORM_ref_sup4),
+            0x1d => Ok(FormEncoding::DW_FORM_strp_sup),
+            0x1e => Ok(FormEncoding::DW_FORM_data16),
+            0x1f => Ok(FormEncoding::DW_FORM_line_strp),
+            0x20 => Ok(FormEncoding::DW_FORM_ref_sig8),
+            0x21 => Ok(FormEncoding::DW_FORM_implicit_const),
+            0x22 => Ok(FormEncoding::DW_FORM_loclistx),
+            0x23 => Ok(FormEncoding::DW_FORM_rnglistx),
+            0x24 => Ok(FormEncoding::DW_FORM_ref_sup8),
+            0x25 => Ok(FormEncoding::DW_FORM_strx1),
+            0x26 => Ok(FormEncoding::DW_FORM_strx2),
+            0x27 => Ok(FormEncoding::DW_FORM_strx3),
+            0x28 => Ok(FormEncoding::DW_FORM_strx4),
+            0x29 => Ok(FormEncoding::DW_FORM_addrx1),
+            0x2a => Ok(FormEncoding::DW_FORM_addrx2),
+            0x2b => Ok(FormEncoding::DW_FORM_addrx3),
+            0x2c => Ok(FormEncoding::DW_FORM_addrx4),
             _ => Err(format!("unknown form encoding: {value:x}").into()),
         }
     }
@@ -433,3 +553,21 @@ fn decode_u64(stream: &mut Stream) -> Result<u64, Box<dyn Error>> {
     }
     Ok(result)
 }
+
+/// Signed LEB128 encoded
+fn decode_i64(stream: &mut Stream) -> Result<i64, Box<dyn Error>> {
+    let mut result = 0;
+    let mut shift = 0;
+    loop {
+        let byte = stream.read_byte()? as i64;
+        result |= (byte & 0x7F) << shift;
+        shift += 7;
+        if (byte & 0x80) == 0 {
+            if shift < 64 && (byte & 0x40) != 0 {
+                result |= -1 << shift;
+            }
+            break;
+        }
+    }
+    Ok(result)
+}
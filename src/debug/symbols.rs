@@ -107,7 +107,7 @@ pub enum SymbolType {
 }
 
 impl SymbolTableEntry {
-    pub fn new(reader: &'static Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
+    pub fn new(reader: &Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
         // Field order is different so we need both cases.
         let mut s = Stream::new(reader, offset);
         if reader.sixty_four_bit {
@@ -12,6 +12,7 @@ pub struct Type {
     pub children: Vec<Type>,
 }
 
+#[allow(dead_code)] // fields are only used via Debug (for now)
 #[derive(Debug)]
 pub enum TypeLoc {
     /// Offset into the file plus the number of information bytes containing a DWARF expression.
@@ -23,8 +24,9 @@ pub enum TypeLoc {
 }
 
 #[allow(non_camel_case_types)]
+#[allow(dead_code)]
 #[derive(Debug)]
-enum Language {
+pub enum Language {
     //                         Value  Default Lower Bound
     DW_LANG_C89,            // 0x0001 0
     DW_LANG_C,              // 0x0002 0
@@ -50,8 +52,9 @@ enum Language {
 }
 
 #[allow(non_camel_case_types)]
+#[allow(dead_code)]
 #[derive(Debug)]
-enum TypeEncoding {
+pub enum TypeEncoding {
     //                         Value
     DW_ATE_address,         // 0x01
     DW_ATE_boolean,         // 0x02
@@ -73,6 +76,7 @@ enum TypeEncoding {
 }
 
 #[allow(non_camel_case_types)]
+#[allow(dead_code)] // fields are only used via Debug (for now)
 #[derive(Debug)]
 pub enum Attribute {
     DW_AT_sibling(u64),
@@ -84,7 +88,7 @@ pub enum Attribute {
     // DW_AT_bit_size,             // 0x0d constant, exprloc, reference
     DW_AT_stmt_list(u32), // section offset to the line number information for this compilation unit
     DW_AT_low_pc(u64),    // relocated address of the first instruction associated with the entity
-    DW_AT_high_pc(u64),
+    DW_AT_high_pc(u64), // address of the first byte past the entity or, for constant forms, its size
     DW_AT_language(Language),
     // DW_AT_discr,                // 0x15 reference
     // DW_AT_discr_value,          // 0x16 constant
@@ -141,7 +145,7 @@ pub enum Attribute {
     // DW_AT_entry_pc,             // 0x52 address
     // DW_AT_use_UTF8,             // 0x53 flag
     // DW_AT_extension,            // 0x54 reference
-    // DW_AT_ranges,               // 0x55 rangelistptr
+    DW_AT_ranges(u64), // offset into .debug_ranges or .debug_rnglists, or a DW_FORM_rnglistx index
     // DW_AT_trampoline,           // 0x56 address, flag, reference, string
     // DW_AT_call_column,          // 0x57 constant
     // DW_AT_call_file,            // 0x58 constant
@@ -185,7 +189,7 @@ pub struct ParseTypes<'a> {
 impl<'a> ParseTypes<'a> {
     pub fn new(exe: &'a ElfFile) -> Result<Self, Box<dyn Error>> {
         if let Some(section) = exe.find_section_named(".debug_info") {
-            let mut stream = Stream::new(exe.reader, section.obytes.start);
+            let mut stream = Stream::new(&exe.reader, section.obytes.start);
             let (_, abbrevs) = exe.abbreviations_at(0);
             let strings = exe.find_section_named(".debug_str").map(|s| s.obytes.start);
             match ParseTypes::parse_header(&mut stream) {
@@ -206,7 +210,7 @@ impl<'a> ParseTypes<'a> {
     }
 
     pub fn parse(&self) -> Vec<Type> {
-        let mut stream = Stream::new(self.exe.reader, self.values);
+        let mut stream = Stream::new(&self.exe.reader, self.values);
         match self.parse_types(&mut stream) {
             (t, None) => t,
             (t, Some(e)) => {
@@ -376,7 +380,9 @@ impl<'a> ParseTypes<'a> {
                 Attribute::DW_AT_stmt_list(self.parse_u32(stream, ae.encoding)?)
             }
             AttributeName::DW_AT_low_pc => Attribute::DW_AT_low_pc(self.parse_addr(stream)?),
-            AttributeName::DW_AT_high_pc => Attribute::DW_AT_high_pc(self.parse_addr(stream)?), // TODO can be a constant (which is added to low_pc)
+            AttributeName::DW_AT_high_pc => {
+                Attribute::DW_AT_high_pc(self.parse_high_pc(stream, ae.encoding)?)
+            }
             AttributeName::DW_AT_language => {
                 let lang = Language::from_u16(self.parse_u16(stream, ae.encoding)?);
                 Attribute::DW_AT_language(lang)
@@ -720,11 +726,7 @@ impl<'a> ParseTypes<'a> {
                 .into());
             }
             AttributeName::DW_AT_ranges => {
-                return Err(format!(
-                    "{:?} not implemented for encoding {:?}",
-                    ae.name, ae.encoding
-                )
-                .into());
+                Attribute::DW_AT_ranges(self.parse_ranges(stream, ae.encoding)?)
             }
             AttributeName::DW_AT_trampoline => {
                 return Err(format!(
@@ -915,6 +917,14 @@ impl<'a> ParseTypes<'a> {
             AttributeName::DW_AT_GNU_all_call_sites => {
                 Attribute::DW_AT_GNU_all_call_sites(self.parse_flag(stream, ae.encoding)?)
             }
+            _ => {
+                // TODO handle the DWARF 5 attributes
+                return Err(format!(
+                    "{:?} not implemented for encoding {:?}",
+                    ae.name, ae.encoding
+                )
+                .into());
+            }
         };
         Ok(a)
     }
@@ -1014,7 +1024,7 @@ impl<'a> ParseTypes<'a> {
             | FormEncoding::DW_FORM_data4
             | FormEncoding::DW_FORM_data8 => loclistptr(stream, encoding),
             FormEncoding::DW_FORM_exprloc => todo!(),
-            _ => return Err(format!("parse_exprloc didn't expect {encoding:?}").into()),
+            _ => Err(format!("parse_exprloc didn't expect {encoding:?}").into()),
         }
     }
 
@@ -1078,6 +1088,38 @@ impl<'a> ParseTypes<'a> {
     // See section 7.5.4 for encoding details
 
     // DW_FORM_addr
+    fn parse_high_pc(
+        &self,
+        stream: &mut Stream,
+        encoding: FormEncoding,
+    ) -> Result<u64, Box<dyn Error>> {
+        match encoding {
+            FormEncoding::DW_FORM_addr => self.parse_addr(stream),
+            FormEncoding::DW_FORM_data1 => Ok(self.parse_data1(stream)? as u64),
+            FormEncoding::DW_FORM_data2 => Ok(self.parse_data2(stream)? as u64),
+            FormEncoding::DW_FORM_data4 => Ok(self.parse_data4(stream)? as u64),
+            FormEncoding::DW_FORM_data8 => self.parse_data8(stream),
+            FormEncoding::DW_FORM_udata => Ok(decode_u64(stream)?),
+            _ => Err(format!("parse_high_pc didn't expect {encoding:?}").into()),
+        }
+    }
+
+    fn parse_ranges(
+        &self,
+        stream: &mut Stream,
+        encoding: FormEncoding,
+    ) -> Result<u64, Box<dyn Error>> {
+        match encoding {
+            FormEncoding::DW_FORM_sec_offset if self.sixty_four => Ok(stream.read_xword()?),
+            FormEncoding::DW_FORM_sec_offset | FormEncoding::DW_FORM_data4 => {
+                Ok(stream.read_word()? as u64)
+            }
+            FormEncoding::DW_FORM_data8 => self.parse_data8(stream),
+            FormEncoding::DW_FORM_rnglistx => Ok(decode_u64(stream)?),
+            _ => Err(format!("parse_ranges didn't expect {encoding:?}").into()),
+        }
+    }
+
     fn parse_addr(&self, stream: &mut Stream) -> Result<u64, Box<dyn Error>> {
         if self.addr_size == 4 {
             Ok(stream.read_word()? as u64)
@@ -1117,7 +1159,7 @@ impl<'a> ParseTypes<'a> {
             stream.read_word()? as i64
         };
         if let Some(start) = self.strings {
-            Ok(StringView::new(stream.reader, start + delta))
+            Ok(StringView::new(&stream.reader, start + delta))
         } else {
             Err("no .debug_str section".into())
         }
@@ -1125,7 +1167,7 @@ impl<'a> ParseTypes<'a> {
 
     // DW_FORM_string
     fn parse_string(&self, stream: &mut Stream) -> Result<StringView, Box<dyn Error>> {
-        let result = StringView::new(stream.reader, stream.offset);
+        let result = StringView::new(&stream.reader, stream.offset);
         loop {
             let byte = stream.read_byte()?;
             if byte == 0 {
@@ -0,0 +1,210 @@
+//! The dynamic section (PT_DYNAMIC) used by the dynamic linker and the link map it builds
+//! at runtime. See https://refspecs.linuxbase.org/elf/gabi4+/ch5.dynamic.html and
+//! include/link.h in glibc.
+use crate::elf::{Memory, MemoryError, VirtualAddr};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DynamicTag {
+    Null,
+
+    /// Name of a needed library (string).
+    Needed,
+    PltRelSize,
+    PltGot,
+    Hash,
+
+    /// Address of the string table used by the string tags.
+    StrTab,
+    SymTab,
+    Rela,
+    RelaSize,
+    RelaEntry,
+    StrSize,
+    SymEntry,
+    Init,
+    Fini,
+
+    /// Name of this shared object (string).
+    SoName,
+
+    /// Library search path, deprecated in favor of RunPath (string).
+    RPath,
+    Symbolic,
+    Rel,
+    RelSize,
+    RelEntry,
+    PltRel,
+
+    /// Set by the dynamic linker to the address of its r_debug struct.
+    Debug,
+    TextRel,
+    JmpRel,
+    BindNow,
+    InitArray,
+    FiniArray,
+    InitArraySize,
+    FiniArraySize,
+
+    /// Library search path (string).
+    RunPath,
+    Flags,
+    PreInitArray,
+    PreInitArraySize,
+    SymTabIndex,
+    GnuHash,
+    VerSym,
+    RelaCount,
+    RelCount,
+    Flags1,
+    VerDef,
+    VerDefNum,
+    VerNeed,
+    VerNeedNum,
+
+    /// Tag we don't handle.
+    Other(u64),
+}
+
+impl DynamicTag {
+    pub fn from_u64(value: u64) -> Self {
+        match value {
+            0 => DynamicTag::Null,
+            1 => DynamicTag::Needed,
+            2 => DynamicTag::PltRelSize,
+            3 => DynamicTag::PltGot,
+            4 => DynamicTag::Hash,
+            5 => DynamicTag::StrTab,
+            6 => DynamicTag::SymTab,
+            7 => DynamicTag::Rela,
+            8 => DynamicTag::RelaSize,
+            9 => DynamicTag::RelaEntry,
+            10 => DynamicTag::StrSize,
+            11 => DynamicTag::SymEntry,
+            12 => DynamicTag::Init,
+            13 => DynamicTag::Fini,
+            14 => DynamicTag::SoName,
+            15 => DynamicTag::RPath,
+            16 => DynamicTag::Symbolic,
+            17 => DynamicTag::Rel,
+            18 => DynamicTag::RelSize,
+            19 => DynamicTag::RelEntry,
+            20 => DynamicTag::PltRel,
+            21 => DynamicTag::Debug,
+            22 => DynamicTag::TextRel,
+            23 => DynamicTag::JmpRel,
+            24 => DynamicTag::BindNow,
+            25 => DynamicTag::InitArray,
+            26 => DynamicTag::FiniArray,
+            27 => DynamicTag::InitArraySize,
+            28 => DynamicTag::FiniArraySize,
+            29 => DynamicTag::RunPath,
+            30 => DynamicTag::Flags,
+            32 => DynamicTag::PreInitArray,
+            33 => DynamicTag::PreInitArraySize,
+            34 => DynamicTag::SymTabIndex,
+            0x6ffffef5 => DynamicTag::GnuHash,
+            0x6ffffff0 => DynamicTag::VerSym,
+            0x6ffffff9 => DynamicTag::RelaCount,
+            0x6ffffffa => DynamicTag::RelCount,
+            0x6ffffffb => DynamicTag::Flags1,
+            0x6ffffffc => DynamicTag::VerDef,
+            0x6ffffffd => DynamicTag::VerDefNum,
+            0x6ffffffe => DynamicTag::VerNeed,
+            0x6fffffff => DynamicTag::VerNeedNum,
+            _ => DynamicTag::Other(value),
+        }
+    }
+
+    /// True if the value is an offset into the DT_STRTAB string table.
+    pub fn is_string(self) -> bool {
+        matches!(
+            self,
+            DynamicTag::Needed | DynamicTag::SoName | DynamicTag::RPath | DynamicTag::RunPath
+        )
+    }
+}
+
+impl fmt::Display for DynamicTag {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DynamicTag::Other(value) => write!(fmt, "0x{value:x}"),
+            _ => write!(fmt, "{self:?}"),
+        }
+    }
+}
+
+/// Elf64_Dyn or Elf32_Dyn.
+pub struct DynamicEntry {
+    pub tag: DynamicTag,
+
+    /// An integer or an address depending on the tag.
+    pub value: u64,
+}
+
+/// Reads the dynamic array at addr up to DT_NULL (or max_bytes).
+pub fn read_dynamic(
+    memory: &impl Memory,
+    addr: VirtualAddr,
+    max_bytes: usize,
+) -> Result<Vec<DynamicEntry>, MemoryError> {
+    let size = memory.addr_size();
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + 2 * size <= max_bytes {
+        let tag = DynamicTag::from_u64(memory.read_uint(addr + offset as i64, size)?);
+        if tag == DynamicTag::Null {
+            break;
+        }
+        let value = memory.read_uint(addr + (offset + size) as i64, size)?;
+        entries.push(DynamicEntry { tag, value });
+        offset += 2 * size;
+    }
+    Ok(entries)
+}
+
+/// An object the dynamic linker loaded, i.e. a struct link_map.
+pub struct LinkMapEntry {
+    /// Address of the link_map struct.
+    pub addr: VirtualAddr,
+
+    /// Difference between where the object was loaded and the addresses in its file
+    /// (l_addr).
+    pub bias: u64,
+
+    /// Path to the object, empty for the exe (l_name).
+    pub name: String,
+
+    /// Address of the object's dynamic section (l_ld).
+    pub dynamic: VirtualAddr,
+}
+
+/// Walks r_debug.r_map and the l_next links.
+pub fn read_link_map(
+    memory: &impl Memory,
+    r_debug: VirtualAddr,
+) -> Result<Vec<LinkMapEntry>, MemoryError> {
+    let size = memory.addr_size() as i64;
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+
+    // r_version is an int but r_map is pointer aligned.
+    let mut addr = memory.read_ptr(r_debug + size)?;
+    while addr != 0 && visited.insert(addr) {
+        let node = VirtualAddr(addr);
+        let name = memory.read_ptr(node + size)?;
+        entries.push(LinkMapEntry {
+            addr: node,
+            bias: memory.read_ptr(node)?,
+            name: if name == 0 {
+                String::new()
+            } else {
+                memory.read_cstring(VirtualAddr(name), 4096)?
+            },
+            dynamic: VirtualAddr(memory.read_ptr(node + 2 * size)?),
+        });
+        addr = memory.read_ptr(node + 3 * size)?;
+    }
+    Ok(entries)
+}
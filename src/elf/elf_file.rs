@@ -1,50 +1,54 @@
 //! Data within a core file or exe.
 use super::{
-    ElfHeader, LoadSegment, MemoryMappedFile, NoteType, PrStatus, ProgramHeader, Reader,
-    SectionIndex, SegmentType, Stream,
+    AT_SYSINFO_EHDR, DynamicEntry, DynamicTag, ElfHeader, FileBytes, LinkMapLibrary, LoadSegment,
+    Memory, MemoryMappedFile, NoteType, PrStatus, ProgramHeader, Reader, SectionIndex, SegmentType,
+    Stream, SymbolFile, read_dynamic,
 };
-use crate::debug::{Abbreviation, LineInfo, SymbolTable, SymbolTableEntry};
+use crate::arch::Arch;
+use crate::debug::{Abbreviation, DebugInfo, LineInfo, LineStrings, SymbolTable, SymbolTableEntry};
 use crate::elf::{
-    Bytes, ChildSignal, CoreNoteType, FaultSignal, KillSignal, Note, Offset, PosixSignal,
-    RelativeAddr, Relocation, SectionHeader, SectionType, SigInfo, SignalDetails, StringIndex,
-    VirtualAddr,
+    Bytes, ChildSignal, CoreNoteType, FaultSignal, KillSignal, LinuxNoteType, Note, Offset,
+    PollSignal, PosixSignal, RelativeAddr, Relocation, SectionHeader, SectionType, SigInfo,
+    SignalDetails, StringIndex, SysSignal, TimerSignal, VirtualAddr,
 };
 use crate::utils::{self, warn};
-use memmap2::Mmap;
 use std::cell::OnceCell;
 use std::error::Error;
-use std::fs::File;
 
 pub struct ElfFile {
     pub header: ElfHeader,
     pub path: std::path::PathBuf,
-    pub reader: &'static Reader,
+    pub reader: Reader,
     pub loads: Vec<LoadSegment>,
     pub notes: Vec<Note>,
     pub sections: Vec<SectionHeader>, // not used for core files
 
+    file_mappings: OnceCell<Option<Vec<MemoryMappedFile>>>,
     memory_mapped: OnceCell<Option<Vec<MemoryMappedFile>>>,
     lines: OnceCell<Option<LineInfo>>,
+    debug_info: OnceCell<Option<DebugInfo>>,
+    vdso: OnceCell<Option<Box<SymbolFile>>>,
+    libraries: OnceCell<Vec<LinkMapLibrary>>,
 }
 
 impl ElfFile {
     pub fn new(path: std::path::PathBuf) -> Result<Self, Box<dyn Error>> {
-        let file = File::open(path.clone())?;
-
-        // This is unfafe because it has undefined behavior if the underlying file is
-        // modified while the memory map is in use.
-        let bytes = unsafe { Mmap::map(&file) }?;
-
-        // Readers will always persist for the entire life of udb so it's OK to leak them.
-        // And because we leak them we can use stuff like StringView containing a static
-        // reference to a Reader allowing us to not allocate memory for strings until we
-        // actually need it.
-        let reader = Box::leak(Box::new(Reader::new(bytes)?));
-        let header = ElfHeader::new(reader)?;
-        let loads = ElfFile::load_loads(reader, &header);
-        let notes = ElfFile::load_notes(reader, &header);
-        let sections = ElfFile::load_sections(reader, &header);
-        ElfFile::load_others(reader, &header);
+        let bytes = FileBytes::map(&path)?;
+        ElfFile::from_bytes(path, bytes)
+    }
+
+    /// Used for ELF images that aren't files, e.g. the vDSO within a core. path is used
+    /// for display.
+    pub fn from_bytes(path: std::path::PathBuf, bytes: FileBytes) -> Result<Self, Box<dyn Error>> {
+        // Readers share the file's bytes so stuff like StringView can refer to the file
+        // without allocating memory for strings until we actually need them. The bytes
+        // are freed once the file and everything referring to it are dropped.
+        let reader = Reader::new(bytes)?;
+        let header = ElfHeader::new(&reader)?;
+        let loads = ElfFile::load_loads(&reader, &header);
+        let notes = ElfFile::load_notes(&reader, &header);
+        let sections = ElfFile::load_sections(&reader, &header);
+        ElfFile::load_others(&reader, &header);
         Ok(ElfFile {
             path,
             reader,
@@ -52,8 +56,12 @@ impl ElfFile {
             loads,
             notes,
             sections,
+            file_mappings: OnceCell::new(),
             memory_mapped: OnceCell::new(),
             lines: OnceCell::new(),
+            debug_info: OnceCell::new(),
+            vdso: OnceCell::new(),
+            libraries: OnceCell::new(),
         })
     }
 
@@ -65,26 +73,6 @@ impl ElfFile {
         self.loads.iter().find(|s| s.vbytes.contains(vaddr))
     }
 
-    pub fn offset_to_vaddr(&self, offset: Offset) -> Option<(&LoadSegment, VirtualAddr)> {
-        if self.is_core() {
-            self.loads
-                .iter()
-                .find(|s| s.obytes.contains(offset))
-                .map(|s| (s, s.vbytes.start + (offset - s.obytes.start)))
-        } else {
-            None // exe files don't have virtual addresses
-        }
-    }
-
-    pub fn vaddr_to_raddr(&self, addr: VirtualAddr) -> Option<RelativeAddr> {
-        let files = self.get_memory_mapped_files();
-        files.as_ref().and_then(|maps| {
-            maps.iter()
-                .find(|s| s.vbytes.contains(addr))
-                .map(|s| RelativeAddr(addr.0 - s.vbytes.start.0))
-        })
-    }
-
     /// Returns a string from the section string table. Note that index can point into
     /// the middle of a string.
     pub fn find_default_string(&self, index: StringIndex) -> Option<String> {
@@ -98,7 +86,7 @@ impl ElfFile {
         let h = self.find_section(section)?;
         // TODO really should return an error if indexing past h.offset + h.size
         let offset = h.obytes.start + index.0 as i64;
-        match Stream::new(self.reader, offset).read_string() {
+        match Stream::new(&self.reader, offset).read_string() {
             Ok(s) => Some(s),
             Err(err) => {
                 utils::warn(&format!("failed to read section string {index:?}: {err}"));
@@ -110,7 +98,7 @@ impl ElfFile {
     pub fn find_strings(&self, section: &SectionHeader, max: usize) -> Vec<String> {
         let mut result = Vec::new();
         if section.stype == SectionType::StringTable {
-            let mut stream = Stream::new(self.reader, section.obytes.start);
+            let mut stream = Stream::new(&self.reader, section.obytes.start);
             while stream.offset < section.obytes.end() {
                 if let Ok(s) = stream.read_string() {
                     result.push(s);
@@ -137,9 +125,18 @@ impl ElfFile {
                         && name == ".debug_line"
                     {
                         let max_offset = section.obytes.end();
+                        let strings = LineStrings {
+                            strings: self
+                                .find_section_named(".debug_str")
+                                .map(|s| s.obytes.start),
+                            line_strings: self
+                                .find_section_named(".debug_line_str")
+                                .map(|s| s.obytes.start),
+                        };
                         return Some(LineInfo::new(
-                            &mut Stream::new(self.reader, section.obytes.start),
+                            &mut Stream::new(&self.reader, section.obytes.start),
                             max_offset,
+                            &strings,
                         ));
                     }
                 }
@@ -148,6 +145,21 @@ impl ElfFile {
         })
     }
 
+    /// Functions from .debug_info, None if there is no .debug_info section.
+    pub fn get_debug_info(&self) -> &Option<DebugInfo> {
+        self.debug_info.get_or_init(|| DebugInfo::new(self))
+    }
+
+    /// Returns the file, line number, and column for addr. When there is .debug_info only
+    /// the line program for addr's compilation unit is parsed. If that fails (e.g. the
+    /// unit's ranges couldn't be read) all of the line programs are used.
+    pub fn find_line(&self, addr: RelativeAddr) -> Option<(String, u32, u16)> {
+        self.get_debug_info()
+            .as_ref()
+            .and_then(|info| info.find_line(addr))
+            .or_else(|| self.get_lines().as_ref()?.find(addr))
+    }
+
     pub fn find_symbols(&self) -> Option<SymbolTable> {
         self.do_find_symbols(SectionType::SymbolTable)
     }
@@ -156,7 +168,52 @@ impl ElfFile {
         self.do_find_symbols(SectionType::DynamicSymbolTable)
     }
 
-    pub fn find_segments(reader: &'static Reader, header: &ElfHeader) -> Vec<ProgramHeader> {
+    /// Returns the symbol table at index, e.g. the one a relocation section links to.
+    pub fn symbol_table_at(&self, index: SectionIndex) -> Option<SymbolTable> {
+        let section = self.find_section(index)?;
+        match section.stype {
+            SectionType::SymbolTable | SectionType::DynamicSymbolTable => {
+                Some(self.read_symbol_table(section))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the first symbol with the given name from either the static or dynamic
+    /// symbol table.
+    pub fn find_symbol(&self, name: &str) -> Option<SymbolTableEntry> {
+        let tables = [self.find_symbols(), self.find_dynamic_symbols()];
+        for table in tables.into_iter().flatten() {
+            let link = table.section.link;
+            for e in table.entries {
+                if self.find_string(link, e.name).is_some_and(|s| s == name) {
+                    return Some(e);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the name of the sized symbol that value is within along with the symbol.
+    pub fn find_symbol_containing(&self, value: u64) -> Option<(String, SymbolTableEntry)> {
+        let tables = [self.find_symbols(), self.find_dynamic_symbols()];
+        for table in tables.into_iter().flatten() {
+            let link = table.section.link;
+            for e in table.entries {
+                if e.size > 0
+                    && value >= e.value
+                    && value - e.value < e.size
+                    && let Some(name) = self.find_string(link, e.name)
+                    && !name.is_empty()
+                {
+                    return Some((name, e));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn find_segments(reader: &Reader, header: &ElfHeader) -> Vec<ProgramHeader> {
         let mut segments = Vec::new();
         let mut offset = Offset(header.ph_offset);
 
@@ -174,28 +231,56 @@ impl ElfFile {
         segments
     }
 
+    /// Returns the entries in the PT_DYNAMIC segment along with the string for tags like
+    /// DT_NEEDED. None if the file has no dynamic segment (e.g. it's a core or statically
+    /// linked).
+    pub fn find_dynamic(&self) -> Option<Vec<(DynamicEntry, Option<String>)>> {
+        let segment = ElfFile::find_segments(&self.reader, &self.header)
+            .into_iter()
+            .find(|s| s.stype == SegmentType::Dynamic)?;
+        let entries =
+            match read_dynamic(self, VirtualAddr(segment.vaddr), segment.mem_size as usize) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    utils::warn(&format!("failed to read the dynamic segment: {err}"));
+                    return None;
+                }
+            };
+        let strings = entries
+            .iter()
+            .find(|e| e.tag == DynamicTag::StrTab)
+            .map(|e| e.value);
+        Some(
+            entries
+                .into_iter()
+                .map(|e| {
+                    let s = match strings {
+                        Some(strings) if e.tag.is_string() => self
+                            .read_cstring(VirtualAddr(strings.wrapping_add(e.value)), 4096)
+                            .ok(),
+                        _ => None,
+                    };
+                    (e, s)
+                })
+                .collect(),
+        )
+    }
+
     pub fn get_sections(&self) -> &Vec<SectionHeader> {
         &self.sections
     }
 
     pub fn find_section_named(&self, name: &str) -> Option<&SectionHeader> {
-        self.sections.iter().find(|h| {
-            self.find_default_string(h.name)
-                .map_or(false, |x| x == name)
-        })
+        self.sections
+            .iter()
+            .find(|h| self.find_default_string(h.name).is_some_and(|x| x == name))
     }
 
-    pub fn get_memory_mapped_files(&self) -> &Option<Vec<MemoryMappedFile>> {
+    /// Returns the NT_FILE entries, these are typically one per load segment.
+    pub fn get_file_mappings(&self) -> &Option<Vec<MemoryMappedFile>> {
         fn get_files(s: &mut Stream) -> Result<Vec<MemoryMappedFile>, Box<dyn Error>> {
-            // For some reason files get mapped in multiple times, e.g.
-            //    7f45e7402000 7f45e7559000   1404928 /usr/lib64/libxpath.so
-            //    7f45e7559000 7f45e7758000   2093056 /usr/lib64/libxpath.so
-            //    7f45e7758000 7f45e77cd000    479232 /usr/lib64/libxpath.so
-            //    7f45e77cd000 7f45e7a37000   2531328 /usr/lib64/libxpath.so
-            // This is annoying and not useful so we we'll merge them together.
-            // Note that the end of one line usually matches the start of the next.
             let count = s.read_ulong()?;
-            let _page_size = s.read_ulong()?;
+            let page_size = s.read_ulong()?;
 
             let mut elements = Vec::new();
             for _ in 0..count {
@@ -205,21 +290,14 @@ impl ElfFile {
                 elements.push((start, end, offset));
             }
 
-            let mut files: Vec<MemoryMappedFile> = Vec::new();
-            for (start, end, _offset) in elements {
+            let mut files = Vec::new();
+            for (start, end, offset) in elements {
                 if let Ok(file_name) = s.read_string() {
-                    if let Some(old) = files.last_mut()
-                        && start == old.vbytes.end().0
-                        && file_name == old.file_name
-                    {
-                        old.vbytes.size = (end - old.vbytes.start.0) as usize;
-                    } else {
-                        files.push(MemoryMappedFile {
-                            vbytes: Bytes::<VirtualAddr>::from_raw(start, (end - start) as usize),
-                            // offset: offset * page_size,
-                            file_name,
-                        });
-                    }
+                    files.push(MemoryMappedFile {
+                        vbytes: Bytes::<VirtualAddr>::from_raw(start, (end - start) as usize),
+                        offset: offset * page_size,
+                        file_name,
+                    });
                 } else {
                     utils::warn(&format!(
                         "Failed to read MemoryMappedFile at offset {:?}",
@@ -231,9 +309,9 @@ impl ElfFile {
             Ok(files)
         }
 
-        self.memory_mapped.get_or_init(|| {
+        self.file_mappings.get_or_init(|| {
             if let Some(note) = self.find_core_note(CoreNoteType::File) {
-                let mut s = Stream::new(self.reader, note.contents.start);
+                let mut s = Stream::new(&self.reader, note.contents.start);
                 match get_files(&mut s) {
                     Ok(files) => Some(files),
                     Err(e) => {
@@ -247,6 +325,107 @@ impl ElfFile {
         })
     }
 
+    /// Like get_file_mappings except that adjacent mappings of the same file are merged.
+    pub fn get_memory_mapped_files(&self) -> &Option<Vec<MemoryMappedFile>> {
+        // For some reason files get mapped in multiple times, e.g.
+        //    7f45e7402000 7f45e7559000   1404928 /usr/lib64/libxpath.so
+        //    7f45e7559000 7f45e7758000   2093056 /usr/lib64/libxpath.so
+        //    7f45e7758000 7f45e77cd000    479232 /usr/lib64/libxpath.so
+        //    7f45e77cd000 7f45e7a37000   2531328 /usr/lib64/libxpath.so
+        // This is annoying and not useful so we we'll merge them together.
+        // Note that the end of one line usually matches the start of the next.
+        self.memory_mapped.get_or_init(|| {
+            let mappings = self.get_file_mappings().as_ref()?;
+            let mut files: Vec<MemoryMappedFile> = Vec::new();
+            for mapping in mappings {
+                if let Some(old) = files.last_mut()
+                    && mapping.vbytes.start == old.vbytes.end()
+                    && mapping.file_name == old.file_name
+                {
+                    old.vbytes.size = mapping.vbytes.end().0 as usize - old.vbytes.start.0 as usize;
+                } else {
+                    files.push(MemoryMappedFile {
+                        vbytes: mapping.vbytes,
+                        offset: mapping.offset,
+                        file_name: mapping.file_name.clone(),
+                    });
+                }
+            }
+            Some(files)
+        })
+    }
+
+    /// Returns the vDSO the kernel mapped into the cored process. It isn't in NT_FILE
+    /// but its ELF image is saved in the core.
+    pub fn get_vdso(&self) -> Option<&SymbolFile> {
+        self.vdso
+            .get_or_init(|| {
+                let addr = VirtualAddr(self.find_auxv(AT_SYSINFO_EHDR)?);
+                let load = self.find_load_segment(addr)?;
+                let bytes = match self.read(addr, (load.vbytes.end().0 - addr.0) as usize) {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        warn(&format!("couldn't read the vdso: {err}"));
+                        return None;
+                    }
+                };
+                let path = std::path::PathBuf::from("[vdso]");
+                match ElfFile::from_bytes(path, FileBytes::from_vec(bytes)) {
+                    Ok(file) => {
+                        // Symbols are relative to the vDSO's first load segment.
+                        let base = file.loads.first().map_or(0, |l| l.vbytes.start.0);
+                        Some(Box::new(SymbolFile {
+                            file,
+                            bias: Some(addr.0.wrapping_sub(base)),
+                        }))
+                    }
+                    Err(err) => {
+                        warn(&format!("couldn't parse the vdso: {err}"));
+                        None
+                    }
+                }
+            })
+            .as_deref()
+    }
+
+    /// Returns the shared libraries in this core's link map, load is called to find them
+    /// the first time. See ElfFiles::link_map_objects.
+    pub fn get_libraries(&self, load: impl FnOnce() -> Vec<LinkMapLibrary>) -> &[LinkMapLibrary] {
+        self.libraries.get_or_init(load)
+    }
+
+    /// Forgets the shared libraries, e.g. because the exe they were found with changed.
+    pub fn clear_libraries(&mut self) {
+        self.libraries.take();
+    }
+
+    /// Returns the vDSO function addr is within, e.g. "clock_gettime+0x4".
+    pub fn describe_vdso_addr(&self, addr: VirtualAddr) -> Option<String> {
+        let vdso = self.get_vdso()?;
+        let value = addr.0.wrapping_sub(vdso.bias?);
+        let (name, symbol) = vdso.file.find_symbol_containing(value)?;
+        let offset = value - symbol.value;
+        Some(if offset == 0 {
+            name
+        } else {
+            format!("{name}+0x{offset:x}")
+        })
+    }
+
+    /// Returns the value for an NT_AUXV key, e.g. AT_SYSINFO_EHDR.
+    pub fn find_auxv(&self, key: u64) -> Option<u64> {
+        let note = self.find_core_note(CoreNoteType::AuxV)?;
+        let mut s = Stream::new(&self.reader, note.contents.start);
+        while s.offset < note.contents.end() {
+            let k = s.read_ulong().ok()?;
+            let value = s.read_ulong().ok()?;
+            if k == key {
+                return Some(value);
+            }
+        }
+        None
+    }
+
     pub fn find_core_note(&self, ntype: CoreNoteType) -> Option<&Note> {
         for note in self.notes.iter() {
             if let NoteType::Core(t) = &note.ntype
@@ -258,37 +437,56 @@ impl ElfFile {
         None
     }
 
+    /// Returns the status of the thread that crashed.
     pub fn find_prstatus(&self) -> Option<PrStatus> {
-        fn get_prstatus(s: &mut Stream) -> Result<PrStatus, Box<dyn Error>> {
+        self.find_prstatuses().into_iter().next()
+    }
+
+    /// Returns the status of the thread with tid, or the thread that crashed if tid is
+    /// None.
+    pub fn find_thread(&self, tid: Option<i32>) -> Option<PrStatus> {
+        match tid {
+            Some(tid) => self.find_prstatuses().into_iter().find(|s| s.pid == tid),
+            None => self.find_prstatus(),
+        }
+    }
+
+    /// Returns the status of every thread, starting with the thread that crashed.
+    pub fn find_prstatuses(&self) -> Vec<PrStatus> {
+        fn get_prstatus(
+            s: &mut Stream,
+            arch: &'static dyn Arch,
+            pac_mask: u64,
+        ) -> Result<PrStatus, Box<dyn Error>> {
             // See elf_prstatus in https://docs.huihoo.com/doxygen/linux/kernel/3.7/uapi_2linux_2elfcore_8h_source.html
             let signal_num = s.read_int()?;
             let signal_code = s.read_int()?;
-            let _errno = s.read_int()?;
+            let errno = s.read_int()?;
             let _current_signal = s.read_half()?; // This is the current signal, not the one that caused the core dump.
             let _padding = s.read_half()?;
-            let _pending_signals = s.read_xword()?;
-            let _held_signals = s.read_xword()?;
+            let _pending_signals = s.read_ulong()?;
+            let _held_signals = s.read_ulong()?;
             let pid = s.read_int()?;
             let _pppid = s.read_int()?;
             let _pgrp = s.read_int()?;
             let _prsid = s.read_int()?;
 
-            let _utime_s = s.read_xword()?; // time spent in user code
-            let _utime_u = s.read_xword()?;
+            // These are timevals which use longs.
+            let _utime_s = s.read_ulong()?; // time spent in user code
+            let _utime_u = s.read_ulong()?;
 
-            let _stime_s = s.read_xword()?; // time spent in system code
-            let _stime_u = s.read_xword()?;
+            let _stime_s = s.read_ulong()?; // time spent in system code
+            let _stime_u = s.read_ulong()?;
 
-            let _cutime_s = s.read_xword()?;
-            let _cutime_u = s.read_xword()?;
+            let _cutime_s = s.read_ulong()?;
+            let _cutime_u = s.read_ulong()?;
 
-            let _cstime_s = s.read_xword()?;
-            let _cstime_u = s.read_xword()?;
+            let _cstime_s = s.read_ulong()?;
+            let _cstime_u = s.read_ulong()?;
 
-            // TODO good only for x86 and arm
             let mut registers = Vec::new();
-            for _ in 1..27 {
-                let r = s.read_xword()?;
+            for _ in 0..arch.num_registers() {
+                let r = s.read_ulong()?;
                 registers.push(r);
             }
             // TODO may need to use pr_exec_fdpic_loadmap
@@ -296,90 +494,177 @@ impl ElfFile {
             Ok(PrStatus {
                 signal_num,
                 signal_code,
-                // errno,
+                errno,
                 pid,
                 registers,
+                arch,
+                pac_mask,
             })
         }
 
-        if let Some(note) = self.find_core_note(CoreNoteType::PrStatus) {
-            let mut s = Stream::new(self.reader, note.contents.start);
-            match get_prstatus(&mut s) {
-                Ok(status) => Some(status),
-                Err(e) => {
-                    utils::warn(&format!("Error reading prstatus: {}", e));
-                    None
+        let Some(arch) = self.header.arch() else {
+            utils::warn(&format!(
+                "registers aren't supported for {}",
+                self.header.machine()
+            ));
+            return Vec::new();
+        };
+        let pac_mask = self.find_pac_mask();
+        self.notes
+            .iter()
+            .filter(|n| n.ntype == NoteType::Core(CoreNoteType::PrStatus))
+            .filter_map(|note| {
+                let mut s = Stream::new(&self.reader, note.contents.start);
+                match get_prstatus(&mut s, arch, pac_mask) {
+                    Ok(status) => Some(status),
+                    Err(e) => {
+                        utils::warn(&format!("Error reading prstatus: {}", e));
+                        None
+                    }
                 }
-            }
-        } else {
-            None
-        }
+            })
+            .collect()
+    }
+
+    /// Returns the mask for pointer authentication bits in code addresses, zero if there
+    /// is no NT_ARM_PAC_MASK note.
+    pub fn find_pac_mask(&self) -> u64 {
+        self.notes
+            .iter()
+            .find(|n| n.ntype == NoteType::Linux(LinuxNoteType::ArmPacMask))
+            .and_then(|n| {
+                let mut s = Stream::new(&self.reader, n.contents.start);
+                let _data_mask = s.read_xword().ok()?;
+                s.read_xword().ok() // insn_mask
+            })
+            .unwrap_or(0)
     }
 
     pub fn find_signal_info(&self) -> Option<SigInfo> {
         fn get_signal_info(s: &mut Stream) -> Result<SigInfo, Box<dyn Error>> {
-            const SI_MASK: u32 = 0xffff0000;
-            const SI_KILL: u32 = 0 << 16;
-            const SI_TIMER: u32 = 1 << 16;
-            const SI_POLL: u32 = 2 << 16;
-            const SI_FAULT: u32 = 3 << 16;
-            const SI_CHLD: u32 = 4 << 16;
-            const SI_MESGQ: u32 = 6 << 16;
-            const SI_SYS: u32 = 7 << 16;
-
-            // See https://elixir.bootlin.com/linux/v4.9/source/arch/ia64/include/uapi/asm/siginfo.h#L83
-            // and https://elixir.bootlin.com/linux/v4.9/source/arch/arm64/kernel/signal32.c#L144
-            let _si_signo = s.read_xword()? as i32; // TODO I think these fields are all emitted as 64 bits?
-            let _si_errno = s.read_xword()? as i32; // TODO need to test this better
-            let _si_code = s.read_xword()? as i32; // TODO this seems completely wrong: we're getting kill for a seg fault
-
-            let details = match (_si_code as u32) & SI_MASK {
-                SI_KILL => {
-                    let sender_pid = s.read_xword()? as i32;
-                    let sender_uid = s.read_xword()? as i32;
-                    SignalDetails::Kill(KillSignal {
-                        sender_pid,
-                        sender_uid,
-                    })
-                }
-                SI_TIMER => SignalDetails::Timer, // TODO bit more we could include here
-                SI_POLL => SignalDetails::Poll,   // TODO bit more we could include here
-                SI_FAULT => {
-                    let fault_addr = s.read_addr()?;
-                    SignalDetails::Fault(FaultSignal { fault_addr })
-                }
-                SI_CHLD => {
-                    let child_pid = s.read_xword()? as i32;
-                    let child_uid = s.read_xword()? as i32;
-                    let exit_code = s.read_xword()? as i32;
-                    SignalDetails::Child(ChildSignal {
-                        child_pid,
-                        child_uid,
-                        exit_code,
+            const SI_KERNEL: i32 = 0x80;
+            const SI_QUEUE: i32 = -1;
+            const SI_TIMER: i32 = -2;
+            const SI_MESGQ: i32 = -3;
+            const SI_ASYNCIO: i32 = -4;
+            const SI_SIGIO: i32 = -5;
+
+            // See siginfo_t in https://elixir.bootlin.com/linux/v6.6/source/include/uapi/asm-generic/siginfo.h
+            let signal_num = s.read_int()?;
+            let errno = s.read_int()?;
+            let signal_code = s.read_int()?;
+            if s.reader.sixty_four_bit {
+                let _padding = s.read_int()?; // the union is pointer aligned
+            }
+
+            // Codes <= 0 are used for signals sent by processes (e.g. SI_USER), kernel
+            // generated signals use positive codes specific to the signal.
+            let details = match signal_code {
+                SI_TIMER => {
+                    let timer_id = s.read_int()?;
+                    let overrun = s.read_int()?;
+                    let value = s.read_addr()?;
+                    SignalDetails::Timer(TimerSignal {
+                        timer_id,
+                        overrun,
+                        value,
                     })
                 }
-                SI_MESGQ => SignalDetails::MesgQ, // TODO more we can add here
-                SI_SYS => SignalDetails::Sys,     // TODO more we can add here
-                _ => {
-                    let sender_pid = s.read_xword()? as i32;
-                    let sender_uid = s.read_xword()? as i32;
+                SI_SIGIO => get_poll(s)?,
+                SI_QUEUE | SI_MESGQ | SI_ASYNCIO => {
+                    let sender_pid = s.read_int()?;
+                    let sender_uid = s.read_int()?;
+                    let value = s.read_addr()?;
                     SignalDetails::Posix(PosixSignal {
                         sender_pid,
                         sender_uid,
+                        value,
                     })
                 }
+                _ if signal_code <= 0 || signal_code == SI_KERNEL => get_kill(s)?,
+                _ => match signal_num {
+                    4 | 5 | 7 | 8 | 11 => get_fault(s, signal_num, signal_code)?, // SIGILL, SIGTRAP, SIGBUS, SIGFPE, SIGSEGV
+                    17 => {
+                        let child_pid = s.read_int()?;
+                        let child_uid = s.read_int()?;
+                        let exit_code = s.read_int()?;
+                        SignalDetails::Child(ChildSignal {
+                            child_pid,
+                            child_uid,
+                            exit_code,
+                        })
+                    }
+                    29 => get_poll(s)?,
+                    31 => {
+                        let call_addr = s.read_addr()?;
+                        let syscall = s.read_int()?;
+                        let arch = s.read_word()?;
+                        SignalDetails::Sys(SysSignal {
+                            call_addr,
+                            syscall,
+                            arch,
+                        })
+                    }
+                    _ => get_kill(s)?,
+                },
             };
 
             Ok(SigInfo {
-                // signal_num: si_signo,
-                // errno: si_errno,
-                // signal_code: si_code,
+                signal_num,
+                errno,
+                signal_code,
                 details,
             })
         }
 
+        fn get_kill(s: &mut Stream) -> Result<SignalDetails, Box<dyn Error>> {
+            let sender_pid = s.read_int()?;
+            let sender_uid = s.read_int()?;
+            Ok(SignalDetails::Kill(KillSignal {
+                sender_pid,
+                sender_uid,
+            }))
+        }
+
+        fn get_poll(s: &mut Stream) -> Result<SignalDetails, Box<dyn Error>> {
+            let band = s.read_ulong()? as i64;
+            let fd = s.read_int()?;
+            Ok(SignalDetails::Poll(PollSignal { band, fd }))
+        }
+
+        fn get_fault(
+            s: &mut Stream,
+            signal_num: i32,
+            signal_code: i32,
+        ) -> Result<SignalDetails, Box<dyn Error>> {
+            let mut details = FaultSignal {
+                fault_addr: s.read_addr()?,
+                addr_lsb: None,
+                bounds: None,
+                pkey: None,
+            };
+            match (signal_num, signal_code) {
+                (7, 4 | 5) => {
+                    // BUS_MCEERR_AR, BUS_MCEERR_AO
+                    details.addr_lsb = Some(s.read_half()?);
+                }
+                (11, 3) => {
+                    // SEGV_BNDERR, the bounds are pointer aligned after the lsb
+                    let _padding = s.read_addr()?;
+                    details.bounds = Some((s.read_addr()?, s.read_addr()?));
+                }
+                (11, 4) => {
+                    // SEGV_PKUERR
+                    let _padding = s.read_addr()?;
+                    details.pkey = Some(s.read_word()?);
+                }
+                _ => (),
+            }
+            Ok(SignalDetails::Fault(details))
+        }
+
         if let Some(note) = self.find_core_note(CoreNoteType::SigInfo) {
-            let mut s = Stream::new(self.reader, note.contents.start);
+            let mut s = Stream::new(&self.reader, note.contents.start);
             match get_signal_info(&mut s) {
                 Ok(status) => Some(status),
                 Err(e) => {
@@ -392,74 +677,37 @@ impl ElfFile {
         }
     }
 
-    pub fn find_relocations(&self, result: &mut Vec<Relocation>) {
-        fn load_with(reader: &'static Reader, offset: Offset, dynamic: bool) -> Option<Relocation> {
-            match Relocation::with_addend(reader, offset, dynamic) {
-                Ok(r) => Some(r),
-                Err(err) => {
-                    utils::warn(&format!("couldn't read relocation at {offset:?}: {err}"));
-                    None
-                }
-            }
-        }
-
-        fn load_without(
-            reader: &'static Reader,
-            offset: Offset,
-            dynamic: bool,
-        ) -> Option<Relocation> {
-            match Relocation::with_no_addend(reader, offset, dynamic) {
-                Ok(r) => Some(r),
-                Err(err) => {
-                    utils::warn(&format!("couldn't read relocation at {offset:?}: {err}"));
-                    None
-                }
+    pub fn find_relocations(&self) -> Vec<Relocation> {
+        let mut result = Vec::new();
+        let Some(arch) = self.header.arch() else {
+            utils::warn(&format!(
+                "relocations aren't supported for {}",
+                self.header.machine()
+            ));
+            return result;
+        };
+        let relocatable = self.header.etype == 1; // ET_REL
+        for section in self.sections.iter() {
+            if section.stype != SectionType::RelocationsWith
+                && section.stype != SectionType::RelocationsWithout
+            {
+                continue;
             }
-        }
-
-        fn load_relocations_with(
-            reader: &'static Reader,
-            section: &SectionHeader,
-            result: &mut Vec<Relocation>,
-        ) {
+            let dynamic = self
+                .find_section(section.link)
+                .is_some_and(|s| s.stype == SectionType::DynamicSymbolTable);
             let mut offset = section.obytes.start;
             while offset + section.entry_size as i64 <= section.obytes.end() {
-                let dynamic = section.info == 0; // TODO better to look at section name?
-                if let Some(r) = load_with(reader, offset, dynamic) {
-                    result.push(r)
-                }
-                offset = offset + section.entry_size as i64;
-            }
-        }
-
-        fn load_relocations_without(
-            reader: &'static Reader,
-            section: &SectionHeader,
-            result: &mut Vec<Relocation>,
-        ) {
-            let mut offset = section.obytes.start;
-            while offset + section.entry_size as i64 <= section.obytes.end() {
-                let dynamic = section.info == 0; // TODO better to look at section name?
-                if let Some(r) = load_without(reader, offset, dynamic) {
-                    result.push(r)
+                match Relocation::new(&self.reader, arch, offset, section, relocatable, dynamic) {
+                    Ok(r) => result.push(r),
+                    Err(err) => {
+                        utils::warn(&format!("couldn't read relocation at {offset:?}: {err}"))
+                    }
                 }
                 offset = offset + section.entry_size as i64;
             }
         }
-
-        if !self.header.is_x66_64() {
-            utils::warn("relocations are only supported for x86 64-bit");
-            return;
-        }
-        for section in self.sections.iter() {
-            match section.stype {
-                SectionType::RelocationsWith => load_relocations_with(self.reader, section, result),
-                SectionType::RelocationsWithout => {
-                    load_relocations_without(self.reader, section, result)
-                }
-                _ => (),
-            }
-        }
+        result
     }
 
     /// Returns the abbreviations for the compilation unit at offset into .debug_abbrev.
@@ -468,7 +716,7 @@ impl ElfFile {
     pub fn abbreviations_at(&self, offset: u64) -> (Option<u64>, Vec<Abbreviation>) {
         let mut result = Vec::new();
         if let Some(section) = self.find_section_named(".debug_abbrev") {
-            let mut stream = Stream::new(self.reader, section.obytes.start + offset as i64);
+            let mut stream = Stream::new(&self.reader, section.obytes.start + offset as i64);
             loop {
                 match Abbreviation::new(&mut stream) {
                     Ok(Some(a)) => result.push(a),
@@ -502,32 +750,31 @@ impl ElfFile {
     }
 
     fn do_find_symbols(&self, stype: SectionType) -> Option<SymbolTable> {
-        for section in self.sections.iter() {
-            if section.stype == stype {
-                // TODO warn if there is more than one of these
-                let mut offset = section.obytes.start;
-                let mut entries = Vec::new();
-                while offset < section.obytes.end() {
-                    match SymbolTableEntry::new(self.reader, offset) {
-                        Ok(s) => entries.push(s),
-                        Err(err) => warn(&format!(
-                            "failed to read symbols at offset {offset:?}: {err}"
-                        )),
-                    }
-                    offset = offset + section.entry_size as i64;
-                }
-                let table = SymbolTable {
-                    section: section.clone(),
-                    dynamic: stype == SectionType::DynamicSymbolTable,
-                    entries,
-                };
-                return Some(table);
+        // TODO warn if there is more than one of these
+        let section = self.sections.iter().find(|s| s.stype == stype)?;
+        Some(self.read_symbol_table(section))
+    }
+
+    fn read_symbol_table(&self, section: &SectionHeader) -> SymbolTable {
+        let mut offset = section.obytes.start;
+        let mut entries = Vec::new();
+        while offset < section.obytes.end() {
+            match SymbolTableEntry::new(&self.reader, offset) {
+                Ok(s) => entries.push(s),
+                Err(err) => warn(&format!(
+                    "failed to read symbols at offset {offset:?}: {err}"
+                )),
             }
+            offset = offset + section.entry_size as i64;
+        }
+        SymbolTable {
+            section: section.clone(),
+            dynamic: section.stype == SectionType::DynamicSymbolTable,
+            entries,
         }
-        None
     }
 
-    fn load_loads(reader: &'static Reader, header: &ElfHeader) -> Vec<LoadSegment> {
+    fn load_loads(reader: &Reader, header: &ElfHeader) -> Vec<LoadSegment> {
         let mut loads = Vec::new();
         let mut offset = Offset(header.ph_offset);
 
@@ -566,7 +813,7 @@ impl ElfFile {
         loads
     }
 
-    fn load_notes(reader: &'static Reader, header: &ElfHeader) -> Vec<Note> {
+    fn load_notes(reader: &Reader, header: &ElfHeader) -> Vec<Note> {
         fn load_note(s: &mut Stream) -> Option<Note> {
             if let Ok((name, ntype, contents)) = super::read_note(s) {
                 Some(Note {
@@ -611,13 +858,13 @@ impl ElfFile {
     }
 
     // This is just here so we can report unknown segments.
-    fn load_others(reader: &'static Reader, header: &ElfHeader) {
+    fn load_others(reader: &Reader, header: &ElfHeader) {
         let mut offset = Offset(header.ph_offset);
 
         for _ in 0..header.num_ph_entries {
             match ProgramHeader::new(reader, offset) {
                 Ok(ph) => match ph.stype {
-                    SegmentType::Dynamic => (), // TODO may need to use this one
+                    SegmentType::Dynamic => (), // see find_dynamic
                     SegmentType::Interpreter => (),
                     SegmentType::Note => (),
                     SegmentType::Null => (),
@@ -633,7 +880,7 @@ impl ElfFile {
         }
     }
 
-    fn load_sections(reader: &'static Reader, header: &ElfHeader) -> Vec<SectionHeader> {
+    fn load_sections(reader: &Reader, header: &ElfHeader) -> Vec<SectionHeader> {
         let mut sections = Vec::new();
         let mut offset = Offset(header.section_offset);
 
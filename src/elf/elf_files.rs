@@ -1,9 +1,77 @@
-use crate::elf::{ElfFile, LoadSegment, PrStatus, Relocation, VirtualAddr};
+use crate::arch::{Arch, FrameLayout};
+use crate::debug::{FrameBase, Function};
+use crate::elf::{
+    DynamicTag, ElfFile, FileBytes, LayeredMemory, LinkMapEntry, Memory, MemoryRegion, PrStatus,
+    RelativeAddr, SegmentType, TlsModule, VirtualAddr, exe_mapping, find_regions, map_file,
+    matches_core, read_dtv_block, read_dynamic, read_link_map, read_tls_segment,
+};
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
+/// The files loaded into a udb session. There can be any number of cores and exes:
+/// commands operate on the selected core and the exe that goes with it.
 pub struct ElfFiles {
-    pub core: Option<ElfFile>,
-    pub exe: Option<ElfFile>,
+    pub cores: Vec<ElfFile>,
+    pub exes: Vec<ElfFile>,
+
+    /// Files added with add-symbol-file, these are used for debug info.
+    pub symbol_files: Vec<SymbolFile>,
+
+    /// Index into cores.
+    selected: usize,
+
+    /// Files named in NT_FILE that were read from disk keyed by core path and file name
+    /// (None if they couldn't be read or don't match that core).
+    disk_files: Mutex<HashMap<(PathBuf, String), Option<FileBytes>>>,
+}
+
+/// A shared library from a core's link map.
+pub struct LinkMapLibrary {
+    /// The file name from NT_FILE.
+    pub name: String,
+
+    /// Amount to add to addresses within the library to get addresses within the core.
+    pub bias: u64,
+
+    /// None if the library is a loaded exe or couldn't be read.
+    pub file: Option<ElfFile>,
+}
+
+/// A function call in the source code. Functions that were inlined don't have a stack
+/// frame of their own so there may be several of these for one stack frame.
+pub struct SourceFrame {
+    /// None if there isn't debug info for the function.
+    pub function: Option<String>,
+    pub file: String,
+    pub line: u32,
+    pub column: u16,
+
+    /// True if the function was inlined into its caller.
+    pub inlined: bool,
+}
+
+impl fmt::Display for SourceFrame {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}:{}", self.file, self.line, self.column)?;
+        if self.inlined {
+            let function = self.function.as_deref().unwrap_or("?");
+            write!(fmt, " {function} [inlined]")?;
+        }
+        Ok(())
+    }
+}
+
+/// An ELF file loaded purely for its debug info, e.g. a separate .debug file for a
+/// stripped exe.
+pub struct SymbolFile {
+    pub file: ElfFile,
+
+    /// Amount to subtract from virtual addresses in the core to get addresses within
+    /// file. If this is None then file describes the exe.
+    pub bias: Option<u64>,
 }
 
 impl ElfFiles {
@@ -12,36 +80,396 @@ impl ElfFiles {
             .into_iter()
             .map(|p| ElfFile::new(p))
             .collect::<Result<Vec<_>, _>>()?;
-        let mut core = None;
-        let mut exe = None;
+        let mut cores = Vec::new();
+        let mut exes = Vec::new();
         for file in files {
-            if file.header.etype == 4 {
-                if core.is_none() {
-                    core = Some(file);
+            if file.is_core() {
+                cores.push(file);
+            } else {
+                exes.push(file);
+            }
+        }
+        Ok(ElfFiles {
+            cores,
+            exes,
+            symbol_files: Vec::new(),
+            selected: 0,
+            disk_files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cores.is_empty() && self.exes.is_empty() && self.symbol_files.is_empty()
+    }
+
+    /// Loads an exe (or shared library). If an exe with the same file name is already
+    /// loaded then it is replaced.
+    pub fn add_exe(&mut self, path: PathBuf) -> Result<(), Box<dyn Error>> {
+        let file = ElfFile::new(path)?;
+        if file.is_core() {
+            return Err("this is a core file (use the core command to load it)".into());
+        }
+        match self
+            .exes
+            .iter()
+            .position(|e| same_file_name(&e.path, &file.path))
+        {
+            Some(i) => self.exes[i] = file,
+            None => self.exes.push(file),
+        }
+        for core in self.cores.iter_mut() {
+            core.clear_libraries(); // the exe may be one of the libraries
+        }
+        Ok(())
+    }
+
+    /// Loads a core and makes it the selected core.
+    pub fn add_core(&mut self, path: PathBuf) -> Result<(), Box<dyn Error>> {
+        let file = ElfFile::new(path)?;
+        if !file.is_core() {
+            return Err("this isn't a core file (use the file command to load exes)".into());
+        }
+        self.cores.push(file);
+        self.selected = self.cores.len() - 1;
+        Ok(())
+    }
+
+    /// Loads debug info from path. If addr is present it's the virtual address the
+    /// .text section of path was loaded at. Otherwise path is assumed to have debug
+    /// info for the exe.
+    pub fn add_symbol_file(
+        &mut self,
+        path: PathBuf,
+        addr: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let file = ElfFile::new(path)?;
+        if file.is_core() {
+            return Err("symbol files can't be core files".into());
+        }
+        let bias = match addr {
+            Some(addr) => {
+                let text = file
+                    .find_section_named(".text")
+                    .ok_or("symbol file has no .text section")?;
+                Some(addr.wrapping_sub(text.vbytes.start.0))
+            }
+            None => None,
+        };
+        self.symbol_files.push(SymbolFile { file, bias });
+        Ok(())
+    }
+
+    /// Unloads the file with the given path or, if path is None, all files.
+    pub fn unload(&mut self, path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+        self.disk_files.get_mut().unwrap().clear();
+        for core in self.cores.iter_mut() {
+            core.clear_libraries();
+        }
+        match path {
+            Some(path) => {
+                let path = canonical_path(path);
+                let matches = |file: &ElfFile| canonical_path(&file.path) == path;
+                let count = self.cores.len() + self.exes.len() + self.symbol_files.len();
+                let removed = self.cores[..self.selected.min(self.cores.len())]
+                    .iter()
+                    .filter(|f| matches(f))
+                    .count();
+                let unload_selected = self.cores.get(self.selected).is_some_and(matches);
+                self.cores.retain(|f| !matches(f));
+                self.exes.retain(|f| !matches(f));
+                self.symbol_files.retain(|s| !matches(&s.file));
+                if count == self.cores.len() + self.exes.len() + self.symbol_files.len() {
+                    return Err(format!("{} isn't loaded", path.display()).into());
+                }
+                if unload_selected {
+                    self.selected = 0;
                 } else {
-                    return Err("can't have multiple core files".into());
+                    self.selected -= removed;
+                }
+            }
+            None => {
+                self.cores.clear();
+                self.exes.clear();
+                self.symbol_files.clear();
+            }
+        }
+        if self.selected >= self.cores.len() {
+            self.selected = 0;
+        }
+        Ok(())
+    }
+
+    /// The core that commands currently operate on.
+    pub fn core(&self) -> Option<&ElfFile> {
+        self.cores.get(self.selected)
+    }
+
+    /// The exe associated with the selected core.
+    pub fn exe(&self) -> Option<&ElfFile> {
+        match self.core() {
+            Some(core) => self.exe_for(core),
+            None => self.exes.first(),
+        }
+    }
+
+    pub fn selected_core(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select_core(&mut self, index: usize) -> Result<(), Box<dyn Error>> {
+        if index < self.cores.len() {
+            self.selected = index;
+            Ok(())
+        } else if self.cores.is_empty() {
+            Err("there are no core files".into())
+        } else {
+            Err(format!("core index should be less than {}", self.cores.len()).into())
+        }
+    }
+
+    /// Returns the exe that was used to produce core. This is the exe whose file name
+    /// appears in the core's memory mapped files or, failing that, the first exe.
+    pub fn exe_for(&self, core: &ElfFile) -> Option<&ElfFile> {
+        if self.exes.len() > 1
+            && let Some(mapped) = core.get_memory_mapped_files()
+        {
+            for exe in self.exes.iter() {
+                if mapped
+                    .iter()
+                    .any(|m| same_file_name(Path::new(&m.file_name), &exe.path))
+                {
+                    return Some(exe);
                 }
-            } else if exe.is_none() {
-                exe = Some(file);
+            }
+        }
+        self.exes.first()
+    }
+
+    /// Returns the amount that needs to be added to addresses within exe to get the
+    /// corresponding virtual address within core. This is zero unless exe is position
+    /// independent.
+    pub fn load_bias(core: &ElfFile, exe: &ElfFile) -> Option<u64> {
+        if exe.header.etype == 2 {
+            return Some(0); // ET_EXEC so addresses are absolute
+        }
+        let mapped = core.get_memory_mapped_files().as_ref()?;
+        mapped
+            .iter()
+            .find(|m| same_file_name(Path::new(&m.file_name), &exe.path))
+            .map(|m| m.vbytes.start.0)
+    }
+
+    /// Returns the contents of a file the cored process mapped. This is a loaded exe with
+    /// the same file name or, failing that, the file on disk if it matches the core.
+    pub fn mapped_file_bytes(&self, core: &ElfFile, file_name: &str) -> Option<FileBytes> {
+        if let Some(exe) = self
+            .exes
+            .iter()
+            .find(|e| same_file_name(Path::new(file_name), &e.path))
+        {
+            return Some(exe.reader.bytes().clone());
+        }
+
+        let mut disk_files = self.disk_files.lock().unwrap();
+        disk_files
+            .entry((core.path.clone(), file_name.to_string()))
+            .or_insert_with(|| {
+                map_file(Path::new(file_name)).filter(|bytes| matches_core(core, file_name, bytes))
+            })
+            .clone()
+    }
+
+    /// Returns the exes, symbol files, and vDSO along with the amount to add to their
+    /// addresses to get addresses within core. Exes that weren't mapped into core are
+    /// skipped. If there is no core then the bias is zero.
+    pub fn symbol_objects<'a>(&'a self, core: Option<&'a ElfFile>) -> Vec<(&'a ElfFile, u64)> {
+        let mut objects = Vec::new();
+        for exe in self.exes.iter() {
+            match core {
+                Some(core) => {
+                    if let Some(bias) = Self::load_bias(core, exe) {
+                        objects.push((exe, bias));
+                    }
+                }
+                None => objects.push((exe, 0)),
+            }
+        }
+        for symbols in self.symbol_files.iter() {
+            let bias = match (symbols.bias, core) {
+                (Some(bias), _) => Some(bias),
+                (None, Some(core)) => self
+                    .exe_for(core)
+                    .and_then(|exe| Self::load_bias(core, exe)),
+                (None, None) => Some(0),
+            };
+            if let Some(bias) = bias {
+                objects.push((&symbols.file, bias));
+            }
+        }
+        if let Some(vdso) = core.and_then(|core| core.get_vdso())
+            && let Some(bias) = vdso.bias
+        {
+            objects.push((&vdso.file, bias));
+        }
+        objects
+    }
+
+    /// Returns the objects the dynamic linker loaded, including ones loaded by dlopen.
+    /// This uses the DT_DEBUG entry in the exe's dynamic section which ld.so sets to its
+    /// r_debug struct.
+    pub fn link_map(&self, core: &ElfFile) -> Result<Vec<LinkMapEntry>, Box<dyn Error>> {
+        let exe = self
+            .exe_for(core)
+            .ok_or("need an exe to find the link map")?;
+        let bias = Self::load_bias(core, exe).ok_or("couldn't find where the exe was loaded")?;
+        let segment = ElfFile::find_segments(&exe.reader, &exe.header)
+            .into_iter()
+            .find(|s| s.stype == SegmentType::Dynamic)
+            .ok_or("the exe has no dynamic segment (it may be statically linked)")?;
+
+        let memory = LayeredMemory::new(self, core);
+        let addr = VirtualAddr(segment.vaddr.wrapping_add(bias));
+        let entries = read_dynamic(&memory, addr, segment.mem_size as usize)?;
+        let r_debug = entries
+            .iter()
+            .find(|e| e.tag == DynamicTag::Debug && e.value != 0)
+            .ok_or("DT_DEBUG isn't set in the core")?;
+        Ok(read_link_map(&memory, VirtualAddr(r_debug.value))?)
+    }
+
+    /// Returns the shared libraries in the link map along with their load biases. These
+    /// are loaded exes with the same file name or, failing that, files read from disk
+    /// that match the core. Libraries are only parsed once per core.
+    pub fn link_map_objects<'a>(&'a self, core: &'a ElfFile) -> Vec<(&'a ElfFile, u64)> {
+        core.get_libraries(|| self.load_libraries(core))
+            .iter()
+            .filter_map(|library| {
+                let name = Path::new(&library.name);
+                match self.exes.iter().find(|e| same_file_name(name, &e.path)) {
+                    Some(exe) => Some((exe, library.bias)),
+                    None => library.file.as_ref().map(|file| (file, library.bias)),
+                }
+            })
+            .collect()
+    }
+
+    /// Parses the shared libraries in core's link map that aren't loaded exes.
+    fn load_libraries(&self, core: &ElfFile) -> Vec<LinkMapLibrary> {
+        let mut libraries = Vec::new();
+        let (Some(mappings), Ok(link_map)) = (core.get_file_mappings(), self.link_map(core)) else {
+            return libraries;
+        };
+        for object in link_map.iter().filter(|o| !o.name.is_empty()) {
+            // Shared libraries are mapped starting with their ELF header so the first
+            // mapping for a library is at its load bias.
+            let Some(mapping) = mappings.iter().find(|m| m.vbytes.start.0 == object.bias) else {
+                continue;
+            };
+            let name = Path::new(&mapping.file_name);
+            let file = if self.exes.iter().any(|e| same_file_name(name, &e.path)) {
+                None // link_map_objects uses the exe
             } else {
-                return Err("can't have multiple exe files".into());
+                self.mapped_file_bytes(core, &mapping.file_name)
+                    .and_then(|bytes| ElfFile::from_bytes(name.to_path_buf(), bytes).ok())
+            };
+            libraries.push(LinkMapLibrary {
+                name: mapping.file_name.clone(),
+                bias: object.bias,
+                file,
+            });
+        }
+        libraries
+    }
+
+    /// Returns symbol_objects followed by the link_map_objects that aren't already in
+    /// it, i.e. every object whose symbols describe an address within core.
+    pub fn searched_objects<'a>(&'a self, core: Option<&'a ElfFile>) -> Vec<(&'a ElfFile, u64)> {
+        let mut objects = self.symbol_objects(core);
+        if let Some(core) = core {
+            for (library, bias) in self.link_map_objects(core) {
+                if !objects.iter().any(|(file, _)| std::ptr::eq(*file, library)) {
+                    objects.push((library, bias));
+                }
             }
         }
-        Ok(ElfFiles { core, exe })
+        objects
     }
 
-    pub fn find_load_segment(&self, vaddr: VirtualAddr) -> Option<&LoadSegment> {
-        match &self.core {
-            Some(c) => c.find_load_segment(vaddr),
-            None => None,
+    /// Returns the objects with TLS in the order the dynamic linker assigns module ids
+    /// along with where thread's blocks are.
+    pub fn tls_modules(
+        &self,
+        core: &ElfFile,
+        thread: &PrStatus,
+    ) -> Result<Vec<TlsModule>, Box<dyn Error>> {
+        let Some((tp_index, dtv_offset)) = thread.arch.thread_pointer() else {
+            return Err(format!("TLS isn't supported for {}", thread.arch.name()).into());
+        };
+        let tp = thread.registers.get(tp_index).copied().unwrap_or(0);
+        if tp == 0 {
+            return Err("the thread pointer isn't set".into());
+        }
+
+        let memory = LayeredMemory::new(self, core);
+        let dtv = VirtualAddr(memory.read_ptr(VirtualAddr(tp) + dtv_offset)?);
+        let exe_start = self
+            .exe_for(core)
+            .and_then(|exe| exe.loads.iter().map(|l| l.vbytes.start.0).min())
+            .unwrap_or(0);
+
+        let mut modules = Vec::new();
+        for object in self.link_map(core)? {
+            // The ELF header is mapped at the start of the object.
+            let header = if object.name.is_empty() {
+                object.bias.wrapping_add(exe_start)
+            } else {
+                object.bias
+            };
+            if let Some(segment) = read_tls_segment(&memory, VirtualAddr(header))? {
+                let id = modules.len() + 1;
+                modules.push(TlsModule {
+                    id,
+                    name: object.name,
+                    bias: object.bias,
+                    segment,
+                    block: read_dtv_block(&memory, dtv, id)?,
+                });
+            }
         }
+        Ok(modules)
     }
 
-    pub fn find_prstatus(&self) -> Option<PrStatus> {
-        match &self.core {
-            Some(c) => c.find_prstatus(),
-            None => None,
+    /// Returns the memory map for the core.
+    pub fn regions(&self, core: &ElfFile) -> Vec<MemoryRegion> {
+        find_regions(core, crate::heap::find_main_heap_top(self, core))
+    }
+
+    /// Returns the symbol (or the memory mapped file) that addr is within, e.g.
+    /// "shopping_cart+0x8", "clock_gettime+0x4" (in the vDSO), or "libc.so.6+0x1f00".
+    pub fn describe_addr(&self, core: &ElfFile, addr: VirtualAddr) -> Option<String> {
+        for exe in self.exes.iter() {
+            if let Some(bias) = Self::load_bias(core, exe)
+                && let Some((name, symbol)) = exe.find_symbol_containing(addr.0.wrapping_sub(bias))
+            {
+                let offset = addr.0.wrapping_sub(bias) - symbol.value;
+                return Some(if offset == 0 {
+                    name
+                } else {
+                    format!("{name}+0x{offset:x}")
+                });
+            }
+        }
+
+        if let Some(name) = core.describe_vdso_addr(addr) {
+            return Some(name);
         }
+
+        let mapped = core.get_memory_mapped_files().as_ref()?;
+        let m = mapped.iter().find(|m| m.vbytes.contains(addr))?;
+        let name = Path::new(&m.file_name)
+            .file_name()
+            .map_or(m.file_name.clone(), |n| n.to_string_lossy().to_string());
+        Some(format!("{name}+0x{:x}", addr.0 - m.vbytes.start.0))
     }
 
     // pub fn find_vaddr(&self, offset: u64) -> Option<(&LoadSegment, u64)> {
@@ -53,39 +481,194 @@ impl ElfFiles {
 
     /// Returns file name, line number, and column for the given address.
     pub fn find_line(&self, addr: VirtualAddr) -> Result<(String, u32, u16), Box<dyn Error>> {
-        match (&self.core, &self.exe) {
-            (Some(core), Some(exe)) => {
-                match core.vaddr_to_raddr(addr) {
-                    Some(addr) => {
-                        match exe.get_lines() {
-                            // TODO need to cache lines
-                            Some(lines) => match lines.lines.get(&addr) {
-                                Some(value) => {
-                                    let file = lines.files.get(value.file);
-                                    Ok((file.clone(), value.line, value.column))
-                                }
-                                None => Ok(("?".to_string(), 0, 0)),
-                            },
-                            None => Err("Couldn't find .debug_line section".into()),
-                        }
-                    }
-                    None => Err("couldn't find a load segment matching the addr".into()),
-                }
+        match self.core() {
+            Some(core) => self.find_line_in(core, addr),
+            None if self.exe().is_some() => Err("need an core file to find file and line".into()),
+            None => Err("need core and exe files to find file and line".into()),
+        }
+    }
+
+    /// Like find_line except that addr is within an arbitrary core.
+    pub fn find_line_in(
+        &self,
+        core: &ElfFile,
+        addr: VirtualAddr,
+    ) -> Result<(String, u32, u16), Box<dyn Error>> {
+        // Symbol files added with an address take precedence.
+        for symbols in self.symbol_files.iter() {
+            if let Some(bias) = symbols.bias
+                && let Some(line) = symbols
+                    .file
+                    .find_line(RelativeAddr(addr.0.wrapping_sub(bias)))
+            {
+                return Ok(line);
+            }
+        }
+
+        let exe = self.exe_for(core);
+        let has_lines = |f: &&ElfFile| f.find_section_named(".debug_line").is_some();
+        let file = exe.filter(has_lines).or_else(|| {
+            self.symbol_files
+                .iter()
+                .filter(|s| s.bias.is_none())
+                .map(|s| &s.file)
+                .find(has_lines)
+        });
+        let unknown = || ("?".to_string(), 0, 0);
+        match file {
+            Some(file) => match self.exe_raddr(core, addr)? {
+                Some(addr) => Ok(file.find_line(addr).unwrap_or_else(unknown)),
+                None => Ok(unknown()), // the exe's debug info doesn't cover libraries
+            },
+            None if exe.is_some() => Err("Couldn't find .debug_line section".into()),
+            None => Err("need an exe file to find file and line".into()), // TODO addr2line doesn't need a core file
+        }
+    }
+
+    /// Like find_line_in except that functions inlined at addr get their own frames, the
+    /// innermost function is first.
+    pub fn find_frames_in(
+        &self,
+        core: &ElfFile,
+        addr: VirtualAddr,
+    ) -> Result<Vec<SourceFrame>, Box<dyn Error>> {
+        let (mut file, mut line, mut column) = self.find_line_in(core, addr)?;
+        let mut frames = Vec::new();
+        for function in self.find_functions_in(core, addr).iter().rev() {
+            frames.push(SourceFrame {
+                function: Some(function.name.clone()),
+                file: file.clone(),
+                line,
+                column,
+                inlined: function.call.is_some(),
+            });
+            if let Some(call) = &function.call {
+                (file, line, column) = (call.file.clone(), call.line, call.column);
             }
-            (None, Some(_)) => Err("need an core file to find file and line".into()),
-            (Some(_), None) => Err("need an exe file to find file and line".into()), // TODO addr2line doesn't need a core file
-            (None, None) => Err("need core and exe files to find file and line".into()),
         }
+        if frames.is_empty() {
+            frames.push(SourceFrame {
+                function: None,
+                file,
+                line,
+                column,
+                inlined: false,
+            });
+        }
+        Ok(frames)
     }
 
-    pub fn find_relocations(&self) -> Vec<Relocation> {
-        let mut result = Vec::new();
-        if let Some(file) = &self.core {
-            file.find_relocations(&mut result);
+    /// Returns the name of the parameter or local variable stored at addr. ip and fp are
+    /// the instruction and frame pointers for the frame addr is in.
+    pub fn find_stack_variable(
+        &self,
+        core: &ElfFile,
+        arch: &dyn Arch,
+        ip: VirtualAddr,
+        fp: VirtualAddr,
+        addr: VirtualAddr,
+    ) -> Option<String> {
+        let (raddr, chain) = self.locate_functions(core, ip);
+        let ptr = core.reader.addr_size() as i64;
+        let base = match chain.first()?.frame_base(raddr?)? {
+            // The CFA is only at a known offset from the frame pointer when the call
+            // pushed the return address (e.g. x86).
+            FrameBase::Cfa => match arch.frame_layout(ptr) {
+                FrameLayout::Record { ret, .. } if arch.lr_index().is_none() => fp + (ret + ptr),
+                _ => return None,
+            },
+            FrameBase::Register(reg, offset)
+                if arch.dwarf_register(reg) == Some(arch.fp_index()) =>
+            {
+                fp + offset
+            }
+            FrameBase::Register(..) => return None,
+        };
+        chain
+            .iter()
+            .rev()
+            .flat_map(|f| f.variables.iter())
+            .find(|v| base + v.offset == addr)
+            .map(|v| v.name.clone())
+    }
+
+    /// Returns the out of line function containing addr followed by the functions that
+    /// were inlined at addr.
+    fn find_functions_in(&self, core: &ElfFile, addr: VirtualAddr) -> Vec<&Function> {
+        self.locate_functions(core, addr).1
+    }
+
+    /// Like find_functions_in except that this also returns the relative address that
+    /// was used for the lookup.
+    fn locate_functions(
+        &self,
+        core: &ElfFile,
+        addr: VirtualAddr,
+    ) -> (Option<RelativeAddr>, Vec<&Function>) {
+        for symbols in self.symbol_files.iter() {
+            if let Some(bias) = symbols.bias
+                && let Some(info) = symbols.file.get_debug_info()
+            {
+                let raddr = RelativeAddr(addr.0.wrapping_sub(bias));
+                let chain = info.find_functions(raddr);
+                if !chain.is_empty() {
+                    return (Some(raddr), chain);
+                }
+            }
+        }
+
+        let exe = self.exe_for(core);
+        let info = exe.and_then(|e| e.get_debug_info().as_ref()).or_else(|| {
+            self.symbol_files
+                .iter()
+                .filter(|s| s.bias.is_none())
+                .find_map(|s| s.file.get_debug_info().as_ref())
+        });
+        match (info, self.exe_raddr(core, addr)) {
+            (Some(info), Ok(Some(raddr))) => (Some(raddr), info.find_functions(raddr)),
+            _ => (None, Vec::new()),
         }
-        if let Some(file) = &self.exe {
-            file.find_relocations(&mut result);
+    }
+
+    /// Returns addr relative to where the exe was mapped. None if addr is within some
+    /// other file, e.g. a shared library or the vDSO.
+    fn exe_raddr(
+        &self,
+        core: &ElfFile,
+        addr: VirtualAddr,
+    ) -> Result<Option<RelativeAddr>, Box<dyn Error>> {
+        let mapped = core
+            .get_memory_mapped_files()
+            .as_ref()
+            .ok_or("the core has no mapped files")?;
+        let mapping = mapped
+            .iter()
+            .find(|m| m.vbytes.contains(addr))
+            .ok_or("couldn't find a load segment matching the addr")?;
+        let is_exe = match self.exe_for(core) {
+            Some(exe) => same_file_name(Path::new(&mapping.file_name), &exe.path),
+            None => exe_mapping(core, mapped).is_some_and(|m| m.file_name == mapping.file_name),
+        };
+        if !is_exe {
+            return Ok(None);
         }
-        result
+
+        // The exe may be mapped in pieces so use the start of the first one.
+        let start = mapped
+            .iter()
+            .find(|m| m.file_name == mapping.file_name)
+            .map_or(mapping.vbytes.start, |m| m.vbytes.start);
+        Ok(Some(RelativeAddr(addr.0 - start.0)))
     }
 }
+
+/// Falls back to path if it can't be canonicalized, e.g. because the file was deleted.
+fn canonical_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Core files record full paths but users will often have the exe in some other
+/// directory so we only compare file names.
+fn same_file_name(lhs: &Path, rhs: &Path) -> bool {
+    lhs.file_name().is_some() && lhs.file_name() == rhs.file_name()
+}
@@ -1,6 +1,7 @@
 //! The ELF header, it includes a mafic number, ABI details, and offsets to the different
 //! parts of the ELF file.
 use super::{Reader, Stream};
+use crate::arch::{Arch, find_arch};
 use crate::{elf::Offset, utils};
 use std::error::Error;
 
@@ -49,7 +50,7 @@ pub struct ElfHeader {
 }
 
 impl ElfHeader {
-    pub fn new(reader: &'static Reader) -> Result<Self, Box<dyn Error>> {
+    pub fn new(reader: &Reader) -> Result<Self, Box<dyn Error>> {
         const EI_NIDENT: usize = 16;
 
         let mut s = Stream::new(reader, Offset(EI_NIDENT as u64));
@@ -96,9 +97,9 @@ impl ElfHeader {
         })
     }
 
-    pub fn is_x66_64(&self) -> bool {
-        // AMD x86-64 or x86 && 64-bit
-        self.machine == 0x3E || (self.machine == 0x03 && self.class == 2)
+    /// Returns None if the machine isn't supported.
+    pub fn arch(&self) -> Option<&'static dyn Arch> {
+        find_arch(self.machine, self.class)
     }
 
     pub fn machine(&self) -> &'static str {
@@ -5,31 +5,155 @@ use crate::utils::print_styled;
 use crate::utils::{self, uwrite, uwriteln};
 use memmap2::Mmap;
 use std::error::Error;
+use std::fmt;
 use std::io::Write;
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::sync::Arc;
 
+/// Byte order of the target that produced an ELF file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Decodes an unsigned integer from up to eight bytes.
+    pub fn decode(self, bytes: &[u8]) -> u64 {
+        let mut value = 0;
+        match self {
+            Endian::Little => bytes
+                .iter()
+                .rev()
+                .for_each(|b| value = (value << 8) | *b as u64),
+            Endian::Big => bytes.iter().for_each(|b| value = (value << 8) | *b as u64),
+        }
+        value
+    }
+
+    /// Encodes the low size bytes of value.
+    pub fn encode(self, value: u64, size: usize) -> Vec<u8> {
+        let bytes = match self {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        };
+        match self {
+            Endian::Little => bytes[..size].to_vec(),
+            Endian::Big => bytes[8 - size..].to_vec(),
+        }
+    }
+
+    /// Decodes a signed integer from up to eight bytes.
+    pub fn decode_signed(self, bytes: &[u8]) -> i64 {
+        let value = self.decode(bytes);
+        let shift = 64 - 8 * bytes.len().clamp(1, 8);
+        ((value << shift) as i64) >> shift
+    }
+}
+
+impl fmt::Display for Endian {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Endian::Little => write!(fmt, "little"),
+            Endian::Big => write!(fmt, "big"),
+        }
+    }
+}
+
+/// Where a file's contents live.
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+/// A range of bytes within a file's contents. The contents are shared so cloning these
+/// is cheap and the memory is freed once nothing refers to it (e.g. after the files
+/// using it are unloaded).
+#[derive(Clone)]
+pub struct FileBytes {
+    storage: Arc<Storage>,
+    range: Range<usize>,
+}
+
+impl FileBytes {
+    /// Memory maps the file at path.
+    pub fn map(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // This is unsafe because it has undefined behavior if the underlying file is
+        // modified while the memory map is in use.
+        let bytes = unsafe { Mmap::map(&file) }?;
+        let range = 0..bytes.len();
+        Ok(FileBytes {
+            storage: Arc::new(Storage::Mapped(bytes)),
+            range,
+        })
+    }
+
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        let range = 0..bytes.len();
+        FileBytes {
+            storage: Arc::new(Storage::Owned(bytes)),
+            range,
+        }
+    }
+
+    /// Returns the bytes within range (relative to the start of these bytes).
+    pub fn slice(&self, range: Range<usize>) -> Option<FileBytes> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        Some(FileBytes {
+            storage: self.storage.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        })
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let bytes = match self.storage.as_ref() {
+            Storage::Mapped(mmap) => &mmap[..],
+            Storage::Owned(bytes) => &bytes[..],
+        };
+        &bytes[self.range.clone()]
+    }
+}
+
+#[derive(Clone)]
 pub struct Reader {
-    pub little_endian: bool,
+    pub endian: Endian,
     pub sixty_four_bit: bool,
-    bytes: Mmap,
+    bytes: FileBytes,
 }
 
 impl Reader {
     /// Note that these functions all return a Result because core files are sometimes
     /// corrupted and we want to continue to work as well as we can when that happens.
-    pub fn new(bytes: Mmap) -> Result<Self, Box<dyn Error>> {
+    pub fn new(bytes: FileBytes) -> Result<Self, Box<dyn Error>> {
         // see https://en.wikipedia.org/wiki/Executable_and_Linkable_Format
         utils::require(bytes.len() > 64, "core file is much too small")?;
         let magic = bytes.get(0..4).unwrap();
         utils::require(
-            magic[0] == 0x7f && magic[1] == 0x45 && magic[2] == 0x4c || magic[3] == 0x46,
+            magic == [0x7f, 0x45, 0x4c, 0x46],
             "not a core file (bad magic)",
         )?;
 
         let ei_class = *bytes.get(0x04).unwrap();
         let ei_data = *bytes.get(0x05).unwrap();
         let ei_version = *bytes.get(0x06).unwrap();
-        let e_type = *bytes.get(0x10).unwrap();
         utils::require(ei_version == 1, &format!("bad elf version: {ei_version}"))?;
+        let endian = match ei_data {
+            1 => Endian::Little,
+            2 => Endian::Big,
+            _ => return Err(format!("bad elf data encoding: {ei_data}").into()),
+        };
+
+        // e_type is a half so we need to know the byte order to read it.
+        let e_type = endian.decode(bytes.get(0x10..0x12).unwrap());
         utils::require(
             e_type == 0x01 || e_type == 0x02 || e_type == 0x03 || e_type == 0x04,
             "bad elf type: not a core, exe, shared lib, or relocatable file",
@@ -38,7 +162,7 @@ impl Reader {
         Ok(Reader {
             bytes,
             sixty_four_bit: ei_class == 2,
-            little_endian: ei_data == 1,
+            endian,
         })
     }
 
@@ -48,12 +172,29 @@ impl Reader {
 
     pub fn slice(&self, offset: Offset, size: usize) -> Result<&[u8], Box<dyn Error>> {
         let offset = offset.0 as usize;
-        if offset + size > self.bytes.len() {
+        if offset
+            .checked_add(size)
+            .is_none_or(|end| end > self.bytes.len())
+        {
             return Err("slice out of bounds".into());
         }
         Ok(&self.bytes[offset..offset + size])
     }
 
+    /// Like slice except that the bytes share ownership of the file's contents.
+    pub fn shared_slice(&self, offset: Offset, size: usize) -> Result<FileBytes, Box<dyn Error>> {
+        let offset = offset.0 as usize;
+        offset
+            .checked_add(size)
+            .and_then(|end| self.bytes.slice(offset..end))
+            .ok_or_else(|| "slice out of bounds".into())
+    }
+
+    /// All of the file's bytes.
+    pub fn bytes(&self) -> &FileBytes {
+        &self.bytes
+    }
+
     pub fn read_byte(&self, offset: Offset) -> Result<u8, Box<dyn Error>> {
         self.bytes
             .get(offset.0 as usize)
@@ -69,54 +210,25 @@ impl Reader {
     }
 
     pub fn read_half(&self, offset: Offset) -> Result<u16, Box<dyn Error>> {
-        let offset = offset.0 as usize;
-        let slice = &self.bytes[offset..offset + 2];
-        if self.little_endian {
-            Ok(u16::from_le_bytes(slice.try_into()?))
-        } else {
-            Ok(u16::from_be_bytes(slice.try_into()?))
-        }
+        Ok(self.endian.decode(self.slice(offset, 2)?) as u16)
     }
 
     pub fn read_word(&self, offset: Offset) -> Result<u32, Box<dyn Error>> {
-        let offset = offset.0 as usize;
-        let slice = &self.bytes[offset..offset + 4];
-        if self.little_endian {
-            Ok(u32::from_le_bytes(slice.try_into()?))
-        } else {
-            Ok(u32::from_be_bytes(slice.try_into()?))
-        }
+        Ok(self.endian.decode(self.slice(offset, 4)?) as u32)
     }
 
     pub fn read_xword(&self, offset: Offset) -> Result<u64, Box<dyn Error>> {
-        let offset = offset.0 as usize;
-        let slice = &self.bytes[offset..offset + 8];
-        if self.little_endian {
-            Ok(u64::from_le_bytes(slice.try_into()?))
-        } else {
-            Ok(u64::from_be_bytes(slice.try_into()?))
-        }
+        Ok(self.endian.decode(self.slice(offset, 8)?))
     }
 
     pub fn read_sxword(&self, offset: Offset) -> Result<i64, Box<dyn Error>> {
-        let offset = offset.0 as usize;
-        let slice = &self.bytes[offset..offset + 8];
-        if self.little_endian {
-            Ok(i64::from_le_bytes(slice.try_into()?))
-        } else {
-            Ok(i64::from_be_bytes(slice.try_into()?))
-        }
+        Ok(self.endian.decode_signed(self.slice(offset, 8)?))
     }
 
-    // /// Read either a u32 or u64 word depending on whether the core file is 64-bit.
-    // /// But, for sanity, always return the result as 64 bits.
-    // pub fn read_addr(&self, offset: Offset) -> Result<u64, Box<dyn Error>> {
-    //     if self.sixty_four_bit {
-    //         self.read_xword(offset)
-    //     } else {
-    //         Ok(self.read_word(offset)? as u64)
-    //     }
-    // }
+    /// Size of an address (or a long) in bytes.
+    pub fn addr_size(&self) -> usize {
+        if self.sixty_four_bit { 8 } else { 4 }
+    }
 
     // // TODO should address and offset be new types?
     // fn read_offset(&self, offset: Offset) -> Result<u64, Box<dyn Error>> {
@@ -127,76 +239,94 @@ impl Reader {
     //     }
     // }
 
+    /// Dumps size bytes starting at offset. If width is larger than one then bytes are
+    /// grouped into integers of that many bytes using the file's byte order.
     pub fn hex_dump(
         &self,
         out: &mut impl Write,
         addr: u64,
         offset: Offset,
         size: usize,
+        width: usize,
         labels: HexdumpLabels,
     ) {
-        let offset = offset.0 as usize;
-        let mut i = offset;
-        loop {
-            match labels {
-                HexdumpLabels::None => (),
-                HexdumpLabels::Addr => {
-                    print_styled!(out, "{:012x}: ", hex_offset, addr + (i - offset) as u64);
-                }
-                HexdumpLabels::Zero => {
-                    print_styled!(out, "{:04x}: ", hex_offset, i - offset);
-                }
-            }
+        let start = (offset.0 as usize).min(self.len());
+        let end = start.saturating_add(size).min(self.len());
+        hex_dump(
+            out,
+            self.endian,
+            addr,
+            &self.bytes[start..end],
+            width,
+            labels,
+        );
+    }
+}
 
-            for j in 0..8 {
-                if i + j >= offset + size || i + j >= self.len() {
-                    print_styled!(out, "   ", hex_hex);
-                } else {
-                    let o = Offset((i + j) as u64);
-                    print_styled!(out, "{:02x} ", hex_hex, self.read_byte(o).unwrap());
-                }
+/// Dumps bytes that started at addr. If width is larger than one then bytes are grouped
+/// into integers of that many bytes using endian.
+pub fn hex_dump(
+    out: &mut impl Write,
+    endian: Endian,
+    addr: u64,
+    bytes: &[u8],
+    width: usize,
+    labels: HexdumpLabels,
+) {
+    for (i, row) in bytes.chunks(16).enumerate() {
+        let i = 16 * i;
+        match labels {
+            HexdumpLabels::None => (),
+            HexdumpLabels::Addr => {
+                print_styled!(out, "{:012x}: ", hex_offset, addr + i as u64);
             }
-            uwrite!(out, " ");
-            for j in 0..8 {
-                if i + j + 8 >= offset + size || i + j + 8 >= self.len() {
-                    print_styled!(out, "   ", hex_hex);
-                } else {
-                    let o = Offset((i + j) as u64);
-                    print_styled!(out, "{:02x} ", hex_hex, self.read_byte(o).unwrap());
-                }
+            HexdumpLabels::Zero => {
+                print_styled!(out, "{:04x}: ", hex_offset, i);
             }
-            uwrite!(out, "   ");
-            for j in 0..16 {
-                if i + j >= offset + size || i + j >= self.len() {
-                    break;
+        }
+
+        let w = 2 * width;
+        for j in (0..16).step_by(width) {
+            if j == 8 {
+                uwrite!(out, " ");
+            }
+            match row.get(j..j + width) {
+                Some(bytes) => {
+                    let value = endian.decode(bytes);
+                    print_styled!(out, "{value:0w$x} ", hex_hex);
                 }
-                let o = Offset((i + j) as u64);
-                let ch = self.read_byte(o).unwrap() as char;
-                if ch.is_ascii_graphic() {
-                    print_styled!(out, "{ch}", hex_ascii);
-                } else {
-                    print_styled!(out, ".", hex_ascii);
+                None => {
+                    print_styled!(out, "{:w$} ", hex_hex, "");
                 }
             }
-            uwriteln!(out);
-            i += 16;
-            if i >= offset + size || i >= self.len() {
-                break;
+        }
+        uwrite!(out, "   ");
+        for byte in row {
+            let ch = *byte as char;
+            if ch.is_ascii_graphic() {
+                print_styled!(out, "{ch}", hex_ascii);
+            } else {
+                print_styled!(out, ".", hex_ascii);
             }
         }
+        uwriteln!(out);
     }
 }
 
 pub struct Stream {
-    pub reader: &'static Reader,
+    pub reader: Reader,
     pub offset: Offset,
 }
 
 impl Stream {
-    pub fn new(reader: &'static Reader, offset: Offset) -> Self {
-        Stream { reader, offset }
+    pub fn new(reader: &Reader, offset: Offset) -> Self {
+        Stream {
+            reader: reader.clone(),
+            offset,
+        }
     }
 
+    #[allow(dead_code)]
     pub fn peek_byte(&mut self) -> Result<u8, Box<dyn Error>> {
         let byte = self.reader.read_byte(self.offset)?;
         Ok(byte)
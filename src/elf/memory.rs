@@ -0,0 +1,399 @@
+//! Reads memory from the cored process. Cores usually omit pages that can be re-read
+//! from the files the process mapped (e.g. text and read-only data) so reads fall back
+//! to those files and then to the sections of the exes and libraries that were loaded.
+use super::{ElfFile, ElfFiles, Endian, FileBytes, SectionType, VirtualAddr};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// Where bytes read from memory came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryLayer {
+    /// The bytes were saved in the core (or, for exes, were in a load segment).
+    Core,
+
+    /// The bytes were read from the file mapped at the address (using the NT_FILE offset).
+    MappedFile,
+
+    /// The bytes were read from a section in a loaded exe or library.
+    Section,
+}
+
+impl Display for MemoryLayer {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryLayer::Core => write!(fmt, "core"),
+            MemoryLayer::MappedFile => write!(fmt, "mapped file"),
+            MemoryLayer::Section => write!(fmt, "section"),
+        }
+    }
+}
+
+/// Why an address couldn't be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The address wasn't part of the process's address space.
+    Unmapped(VirtualAddr),
+
+    /// The address was mapped but its contents weren't saved (and couldn't be found in
+    /// a file).
+    NotInCore(VirtualAddr),
+}
+
+impl Display for MemoryError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryError::Unmapped(addr) => write!(fmt, "0x{:x} isn't mapped", addr.0),
+            MemoryError::NotInCore(addr) => write!(fmt, "0x{:x} isn't in the core", addr.0),
+        }
+    }
+}
+
+impl Error for MemoryError {}
+
+/// Bytes read from memory along with where they came from.
+pub struct MemoryRead {
+    pub bytes: Vec<u8>,
+
+    /// The number of bytes served by each layer, in address order.
+    pub layers: Vec<(usize, MemoryLayer)>,
+
+    /// Set if fewer bytes than requested were read.
+    pub error: Option<MemoryError>,
+}
+
+/// Reads the memory of a process. All reads use the target's byte order.
+pub trait Memory {
+    /// Returns as many contiguous bytes starting at addr as are available from one
+    /// place.
+    fn slice(&self, addr: VirtualAddr) -> Result<(FileBytes, MemoryLayer), MemoryError>;
+
+    fn endian(&self) -> Endian;
+
+    /// Size of a pointer in bytes.
+    fn addr_size(&self) -> usize;
+
+    /// Returns up to size bytes starting at addr, stopping at the first byte that can't
+    /// be read.
+    fn read_partial(&self, addr: VirtualAddr, size: usize) -> MemoryRead {
+        let mut result = MemoryRead {
+            bytes: Vec::with_capacity(size),
+            layers: Vec::new(),
+            error: None,
+        };
+        while result.bytes.len() < size {
+            let next = addr + result.bytes.len() as i64;
+            let (bytes, layer) = match self.slice(next) {
+                Ok(slice) => slice,
+                Err(err) => {
+                    result.error = Some(err);
+                    break;
+                }
+            };
+            let n = bytes.len().min(size - result.bytes.len());
+            result.bytes.extend_from_slice(&bytes[..n]);
+            match result.layers.last_mut() {
+                Some((count, last)) if *last == layer => *count += n,
+                _ => result.layers.push((n, layer)),
+            }
+        }
+        result
+    }
+
+    /// Returns size bytes starting at addr.
+    fn read(&self, addr: VirtualAddr, size: usize) -> Result<Vec<u8>, MemoryError> {
+        let result = self.read_partial(addr, size);
+        match result.error {
+            Some(err) => Err(err),
+            None => Ok(result.bytes),
+        }
+    }
+
+    /// Reads an unsigned integer of up to eight bytes.
+    fn read_uint(&self, addr: VirtualAddr, size: usize) -> Result<u64, MemoryError> {
+        self.read(addr, size)
+            .map(|bytes| self.endian().decode(&bytes))
+    }
+
+    fn read_u16(&self, addr: VirtualAddr) -> Result<u16, MemoryError> {
+        self.read_uint(addr, 2).map(|v| v as u16)
+    }
+
+    fn read_u32(&self, addr: VirtualAddr) -> Result<u32, MemoryError> {
+        self.read_uint(addr, 4).map(|v| v as u32)
+    }
+
+    fn read_u64(&self, addr: VirtualAddr) -> Result<u64, MemoryError> {
+        self.read_uint(addr, 8)
+    }
+
+    /// Reads a pointer sized value.
+    fn read_ptr(&self, addr: VirtualAddr) -> Result<u64, MemoryError> {
+        self.read_uint(addr, self.addr_size())
+    }
+
+    /// Reads a NUL terminated string of at most max_len bytes (not counting the NUL). If
+    /// the string is longer than that it's truncated.
+    fn read_cstring(&self, addr: VirtualAddr, max_len: usize) -> Result<String, MemoryError> {
+        let mut bytes = Vec::new();
+        while bytes.len() < max_len {
+            let (slice, _) = self.slice(addr + bytes.len() as i64)?;
+            let slice = &slice[..slice.len().min(max_len - bytes.len())];
+            match slice.iter().position(|b| *b == 0) {
+                Some(end) => {
+                    bytes.extend_from_slice(&slice[..end]);
+                    break;
+                }
+                None => bytes.extend_from_slice(slice),
+            }
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Reads the bytes in an ELF file's load segments, i.e. what was saved in a core or
+/// what an exe will have in memory when it starts.
+impl Memory for ElfFile {
+    fn slice(&self, addr: VirtualAddr) -> Result<(FileBytes, MemoryLayer), MemoryError> {
+        let load = self
+            .find_load_segment(addr)
+            .ok_or(MemoryError::Unmapped(addr))?;
+        let delta = (addr.0 - load.vbytes.start.0) as usize;
+        if delta >= load.obytes.size {
+            return Err(MemoryError::NotInCore(addr)); // not all of the segment was written
+        }
+        self.reader
+            .shared_slice(load.obytes.start + delta as i64, load.obytes.size - delta)
+            .map(|bytes| (bytes, MemoryLayer::Core))
+            .map_err(|_| MemoryError::NotInCore(addr)) // truncated file
+    }
+
+    fn endian(&self) -> Endian {
+        self.reader.endian
+    }
+
+    fn addr_size(&self) -> usize {
+        self.reader.addr_size()
+    }
+}
+
+/// Reads from the core, then from mapped files, then from sections.
+pub struct LayeredMemory<'a> {
+    files: &'a ElfFiles,
+    core: &'a ElfFile,
+}
+
+impl<'a> LayeredMemory<'a> {
+    pub fn new(files: &'a ElfFiles, core: &'a ElfFile) -> Self {
+        LayeredMemory { files, core }
+    }
+
+    fn mapped_slice(&self, addr: VirtualAddr) -> Option<FileBytes> {
+        let mappings = self.core.get_file_mappings().as_ref()?;
+        let mapping = mappings.iter().find(|m| m.vbytes.contains(addr))?;
+        let bytes = self
+            .files
+            .mapped_file_bytes(self.core, &mapping.file_name)?;
+        let delta = addr.0 - mapping.vbytes.start.0;
+        let offset = (mapping.offset + delta) as usize;
+        let end = bytes
+            .len()
+            .min(offset + mapping.vbytes.size - delta as usize);
+        bytes.slice(offset..end).filter(|b| !b.is_empty())
+    }
+
+    fn section_slice(&self, addr: VirtualAddr) -> Option<FileBytes> {
+        let exes = self
+            .files
+            .exes
+            .iter()
+            .filter_map(|exe| ElfFiles::load_bias(self.core, exe).map(|bias| (exe, bias)));
+        let symbols = self
+            .files
+            .symbol_files
+            .iter()
+            .filter_map(|s| s.bias.map(|bias| (&s.file, bias)));
+        for (file, bias) in exes.chain(symbols) {
+            let local = VirtualAddr(addr.0.wrapping_sub(bias));
+            if let Some(section) = file.sections.iter().find(|s| {
+                s.is_alloc()
+                    && s.stype != SectionType::NoBits
+                    && s.vbytes.start.0 != 0
+                    && s.vbytes.contains(local)
+            }) {
+                let delta = (local.0 - section.vbytes.start.0) as usize;
+                let size = section.obytes.size.min(section.vbytes.size);
+                if delta < size {
+                    return file
+                        .reader
+                        .shared_slice(section.obytes.start + delta as i64, size - delta)
+                        .ok();
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Memory for LayeredMemory<'_> {
+    fn slice(&self, addr: VirtualAddr) -> Result<(FileBytes, MemoryLayer), MemoryError> {
+        if let Ok(slice) = self.core.slice(addr) {
+            return Ok(slice);
+        }
+        if let Some(bytes) = self.mapped_slice(addr) {
+            return Ok((bytes, MemoryLayer::MappedFile));
+        }
+        if let Some(bytes) = self.section_slice(addr) {
+            return Ok((bytes, MemoryLayer::Section));
+        }
+        if self.core.find_load_segment(addr).is_some() {
+            Err(MemoryError::NotInCore(addr))
+        } else {
+            Err(MemoryError::Unmapped(addr))
+        }
+    }
+
+    fn endian(&self) -> Endian {
+        self.core.reader.endian
+    }
+
+    fn addr_size(&self) -> usize {
+        self.core.reader.addr_size()
+    }
+}
+
+/// Returns true if the bytes the core saved for the file match the bytes in data. This
+/// is used to avoid reading from a file that has changed since the core was created.
+pub fn matches_core(core: &ElfFile, file_name: &str, data: &[u8]) -> bool {
+    let Some(mappings) = core.get_file_mappings() else {
+        return false;
+    };
+    let mut checked = false;
+    for mapping in mappings.iter().filter(|m| m.file_name == file_name) {
+        let Some(load) = core.find_load_segment(mapping.vbytes.start) else {
+            continue;
+        };
+        let delta = (mapping.vbytes.start.0 - load.vbytes.start.0) as usize;
+        let size = load
+            .obytes
+            .size
+            .saturating_sub(delta)
+            .min(mapping.vbytes.size);
+        if size == 0 {
+            continue;
+        }
+        let Ok(saved) = core.reader.slice(load.obytes.start + delta as i64, size) else {
+            continue;
+        };
+        let offset = mapping.offset as usize;
+        if data.get(offset..offset + size) != Some(saved) {
+            return false;
+        }
+        checked = true;
+    }
+    checked
+}
+
+/// Memory maps a file mapped into the cored process, if it still exists.
+pub fn map_file(path: &Path) -> Option<FileBytes> {
+    FileBytes::map(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::core_builder::CoreBuilder;
+
+    /// Reads across the first two pages of a mapped file. The core has the first page
+    /// but not the second.
+    fn read_pages(name: &str, contents: &[u8]) -> MemoryRead {
+        let file = std::env::temp_dir().join(format!("udb-{}-{name}.so", std::process::id()));
+        std::fs::write(&file, contents).unwrap();
+        let file = file.to_string_lossy().to_string();
+
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        builder.mapped_files(&[(0x400000, 0x402000, 0, &file)]);
+        builder.load(0x400000, vec![1; 0x1000], 4);
+        builder.load_partial(0x401000, Vec::new(), 0x1000, 5);
+        let files = ElfFiles::new(vec![builder.write(name)]).unwrap();
+
+        let memory = LayeredMemory::new(&files, files.core().unwrap());
+        memory.read_partial(VirtualAddr(0x400ff0), 0x20)
+    }
+
+    #[test]
+    fn layers() {
+        let mut contents = vec![1; 0x1000];
+        contents.extend(vec![2; 0x1000]);
+        let read = read_pages("mapped", &contents);
+        assert_eq!(read.bytes[0x0f..0x11], [1, 2]);
+        assert_eq!(
+            read.layers,
+            vec![(0x10, MemoryLayer::Core), (0x10, MemoryLayer::MappedFile)]
+        );
+
+        // Files that no longer match the core aren't used.
+        contents[0] = 0;
+        let read = read_pages("changed", &contents);
+        assert_eq!(read.bytes.len(), 0x10);
+        assert_eq!(
+            read.error,
+            Some(MemoryError::NotInCore(VirtualAddr(0x401000)))
+        );
+    }
+
+    #[test]
+    fn files_checked_per_core() {
+        let file = std::env::temp_dir().join(format!("udb-{}-shared.so", std::process::id()));
+        let mut contents = vec![1; 0x1000];
+        contents.extend(vec![2; 0x1000]);
+        std::fs::write(&file, &contents).unwrap();
+        let file = file.to_string_lossy().to_string();
+
+        // Only the second core matches the file on disk.
+        let cores = [("stale-core", 0), ("fresh-core", 1)].map(|(name, byte)| {
+            let mut builder = CoreBuilder::new(0x3E, true, true);
+            builder.mapped_files(&[(0x400000, 0x402000, 0, &file)]);
+            builder.load(0x400000, vec![byte; 0x1000], 4);
+            builder.load_partial(0x401000, Vec::new(), 0x1000, 5);
+            builder.write(name)
+        });
+        let files = ElfFiles::new(cores.to_vec()).unwrap();
+
+        let addr = VirtualAddr(0x401000);
+        let stale = LayeredMemory::new(&files, &files.cores[0]);
+        assert_eq!(stale.read_partial(addr, 1).bytes, Vec::<u8>::new());
+        let fresh = LayeredMemory::new(&files, &files.cores[1]);
+        assert_eq!(fresh.read_partial(addr, 1).bytes, vec![2]);
+    }
+
+    #[test]
+    fn typed_reads() {
+        let mut builder = CoreBuilder::new(0x3E, true, false);
+        let mut e = builder.encoder();
+        e.half(0x1234).word(0xfffffffe).xword(1.5_f64.to_bits());
+        e.raw(b"hello\0world");
+        builder.load_partial(0x1000, e.bytes, 0x2000, 6);
+        let core = ElfFile::new(builder.write("typed-reads")).unwrap();
+
+        assert_eq!(core.read_u16(VirtualAddr(0x1000)), Ok(0x1234));
+        assert_eq!(core.read_u32(VirtualAddr(0x1002)), Ok(0xfffffffe));
+        assert_eq!(core.read_u64(VirtualAddr(0x1006)), Ok(1.5_f64.to_bits()));
+        assert_eq!(
+            core.read_cstring(VirtualAddr(0x100e), 100),
+            Ok("hello".to_string())
+        );
+        assert_eq!(
+            core.read_cstring(VirtualAddr(0x100e), 2),
+            Ok("he".to_string())
+        );
+        assert_eq!(
+            core.read_cstring(VirtualAddr(0x1014), 100),
+            Err(MemoryError::NotInCore(VirtualAddr(0x1019)))
+        );
+        assert_eq!(
+            core.read_u64(VirtualAddr(0x3000)),
+            Err(MemoryError::Unmapped(VirtualAddr(0x3000)))
+        );
+    }
+}
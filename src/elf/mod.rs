@@ -14,25 +14,34 @@
 //! memory. A program header has type, vaddr, offset, etc. Common types are:
 //! * Load - for a core file these are usually memory mapped files for the exe and DLLs, for an exe these are text (CPU instructions) and data (eg statics)
 //! * Note - variety of metadata, e.g. process and signal info.
+//! * Dynamic - info for the dynamic linker, e.g. the shared libraries that are needed.
 //! * TLS - thread local storage info.
 //!
 //! Section headers identify sections. Sections are used for static linking and don't
 //! appear in core files. Section headers have name, type, vaddr, offset, size, etc.
 //! There are a lot of types including for the symbol table, string table, etc.
+pub mod dynamic;
 pub mod elf_file;
 pub mod elf_files;
 pub mod header;
 pub mod io;
+pub mod memory;
 pub mod notes;
 pub mod primitives;
+pub mod regions;
 pub mod sections;
 pub mod segments;
+pub mod tls;
 
+pub use dynamic::*;
 pub use elf_file::*;
 pub use elf_files::*;
 pub use header::*;
 pub use io::*;
+pub use memory::*;
 pub use notes::*;
 pub use primitives::*;
+pub use regions::*;
 pub use sections::*;
 pub use segments::*;
+pub use tls::*;
@@ -2,6 +2,7 @@
 //! For exe's they provide information about how it was built. Not all may be present.
 use super::Stream;
 use crate::{
+    arch::Arch,
     elf::{Bytes, Offset, VirtualAddr},
     utils,
 };
@@ -18,6 +19,7 @@ pub enum NoteType {
     Core(CoreNoteType),
     Generic(GenericNoteType),
     Gnu(GnuNoteType),
+    Linux(LinuxNoteType),
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -68,6 +70,39 @@ pub enum GnuNoteType {
     PropType0,
 }
 
+/// Architecture specific notes, see NT_386_TLS and following in
+/// https://elixir.bootlin.com/linux/v6.6/source/include/uapi/linux/elf.h
+#[derive(Debug, Eq, PartialEq)]
+pub enum LinuxNoteType {
+    /// Floating point and SIMD state saved with XSAVE.
+    X86XState,
+
+    /// ARM thread pointer (TPIDR_EL0).
+    ArmTls,
+
+    /// Hardware breakpoint registers.
+    ArmHwBreak,
+
+    /// Hardware watchpoint registers.
+    ArmHwWatch,
+
+    /// The syscall number.
+    ArmSystemCall,
+
+    /// Scalable vector extension registers.
+    ArmSve,
+
+    /// The masks for the pointer authentication code bits within data and instruction
+    /// addresses.
+    ArmPacMask,
+
+    ArmPacEnabledKeys,
+
+    ArmTaggedAddrCtrl,
+
+    Other,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum GenericNoteType {
     Arch,
@@ -109,14 +144,23 @@ impl NoteType {
                     NoteType::Gnu(GnuNoteType::Other)
                 }
             }
+        } else if name == "LINUX" {
+            match value {
+                0x202 => NoteType::Linux(LinuxNoteType::X86XState),
+                0x401 => NoteType::Linux(LinuxNoteType::ArmTls),
+                0x402 => NoteType::Linux(LinuxNoteType::ArmHwBreak),
+                0x403 => NoteType::Linux(LinuxNoteType::ArmHwWatch),
+                0x404 => NoteType::Linux(LinuxNoteType::ArmSystemCall),
+                0x405 => NoteType::Linux(LinuxNoteType::ArmSve),
+                0x406 => NoteType::Linux(LinuxNoteType::ArmPacMask),
+                0x407 => NoteType::Linux(LinuxNoteType::ArmPacEnabledKeys),
+                0x409 => NoteType::Linux(LinuxNoteType::ArmTaggedAddrCtrl),
+                _ => NoteType::Linux(LinuxNoteType::Other), // there are a lot of these
+            }
         } else {
             match value {
                 1 => NoteType::Generic(GenericNoteType::Version),
                 2 => NoteType::Generic(GenericNoteType::Arch),
-
-                // TODO no idea what this one is though it is almost all zeros
-                // and contains "early_init.strnl" in the middle
-                514 => NoteType::Generic(GenericNoteType::Other),
                 0x100 => NoteType::Generic(GenericNoteType::GnuBuildAttrOpen),
                 0x101 => NoteType::Generic(GenericNoteType::GnuBuildAttrFunc),
                 _ => {
@@ -137,29 +181,36 @@ pub struct PrStatus {
     /// https://www.mkssoftware.com/docs/man5/siginfo_t.5.asp#Signal_Codes for more.
     pub signal_code: i32,
 
-    // /// If non-zero, the errno associated with the signal.
-    // pub errno: i32,
+    /// If non-zero, the errno associated with the signal (pr_info.si_errno).
+    pub errno: i32,
+
     /// The process ID of the process that generated this core file.
     pub pid: i32,
 
-    /// General purpose rehisters. For arm and x86 they are laid out as in pt_regs
-    /// in https://elixir.bootlin.com/linux/v4.9/source/arch/x86/include/uapi/asm/ptrace.h#L60
+    /// General purpose registers, the layout is determined by arch.
     pub registers: Vec<u64>,
+
+    /// The architecture of the process that generated the core.
+    pub arch: &'static dyn Arch,
+
+    /// Bits within code addresses used for pointer authentication (zero if pointer
+    /// authentication isn't used).
+    pub pac_mask: u64,
 }
 
-/// Similar to the signal info in PrStatus but with additional details.
+/// Similar to the signal info in PrStatus but with additional details. This is the
+/// siginfo_t the kernel used when delivering the signal.
 pub struct SigInfo {
-    // /// The signal that terminated the process.
-    // pub signal_num: i32,
+    /// The signal that terminated the process.
+    pub signal_num: i32,
+
+    /// If non-zero, the errno associated with the signal.
+    pub errno: i32,
 
-    // /// If non-zero, the errno associated with the signal.
-    // pub errno: i32,
+    /// Either why the kernel generated the signal (positive codes that depend upon the
+    /// signal, e.g. SEGV_MAPERR) or how a process sent it (SI_USER, SI_TKILL, etc).
+    pub signal_code: i32,
 
-    // /// Further details about the signal. For example, code can be SEGV_MAPERR (bad
-    // /// address) or SEGV_ACCERR (bad permessions) for the SIGSEGV signal. See
-    // /// https://www.mkssoftware.com/docs/man5/siginfo_t.5.asp#Signal_Codes for more.
-    // /// TODO is this right?
-    // pub signal_code: i32,
     /// Information associated with the specific signal that killed the process.
     pub details: SignalDetails,
 }
@@ -168,11 +219,10 @@ pub enum SignalDetails {
     Child(ChildSignal),
     Fault(FaultSignal),
     Kill(KillSignal),
-    MesgQ,
-    Poll,
+    Poll(PollSignal),
     Posix(PosixSignal),
-    Sys,
-    Timer,
+    Sys(SysSignal),
+    Timer(TimerSignal),
 }
 
 pub struct ChildSignal {
@@ -181,162 +231,278 @@ pub struct ChildSignal {
     pub exit_code: i32,
 }
 
+/// SIGILL, SIGTRAP, SIGBUS, SIGFPE, and SIGSEGV.
 pub struct FaultSignal {
     pub fault_addr: u64,
+
+    /// Least significant bit of the reported address, i.e. how much memory was
+    /// corrupted (BUS_MCEERR_AR and BUS_MCEERR_AO).
+    pub addr_lsb: Option<u16>,
+
+    /// Lower and upper bounds that were violated (SEGV_BNDERR).
+    pub bounds: Option<(u64, u64)>,
+
+    /// The protection key that caused the fault (SEGV_PKUERR).
+    pub pkey: Option<u32>,
 }
 
+/// kill, tkill, etc.
 pub struct KillSignal {
     pub sender_pid: i32,
     pub sender_uid: i32,
 }
 
+/// SIGPOLL (aka SIGIO).
+pub struct PollSignal {
+    /// POLLIN, POLLOUT, etc.
+    pub band: i64,
+
+    pub fd: i32,
+}
+
+/// sigqueue, POSIX message queues, and async IO.
 pub struct PosixSignal {
     pub sender_pid: i32,
     pub sender_uid: i32,
+
+    /// The sigval passed by the sender.
+    pub value: u64,
+}
+
+/// SIGSYS, e.g. from a seccomp filter.
+pub struct SysSignal {
+    /// Address of the syscall instruction.
+    pub call_addr: u64,
+
+    /// The syscall that was attempted.
+    pub syscall: i32,
+
+    /// AUDIT_ARCH_* value for the syscall convention that was used.
+    pub arch: u32,
+}
+
+/// POSIX timers (timer_create).
+pub struct TimerSignal {
+    pub timer_id: i32,
+
+    /// Number of extra timer expirations that happened before the signal was delivered.
+    pub overrun: i32,
+
+    /// The sigval from the timer's sigevent.
+    pub value: u64,
+}
+
+impl SigInfo {
+    pub fn signal(&self) -> String {
+        match sender_code(self.signal_code) {
+            Some((name, desc)) => format!(
+                "{}: {desc} ({name})",
+                signal_description(self.signal_num, 0)
+            ),
+            None => signal_description(self.signal_num, self.signal_code).to_string(),
+        }
+    }
+
+    /// True if a process sent the signal, false if the kernel generated it.
+    pub fn is_user_sent(&self) -> bool {
+        self.signal_code <= 0 // SI_FROMUSER
+    }
+}
+
+/// Returns the macro name and a description for si_code values that aren't specific
+/// to a signal. These are mostly used when a process sends a signal.
+fn sender_code(code: i32) -> Option<(&'static str, &'static str)> {
+    // see include/uapi/asm-generic/siginfo.h in the kernel
+    match code {
+        0 => Some(("SI_USER", "Sent by kill")),
+        0x80 => Some(("SI_KERNEL", "Sent by the kernel")),
+        -1 => Some(("SI_QUEUE", "Sent by sigqueue")),
+        -2 => Some(("SI_TIMER", "POSIX timer expired")),
+        -3 => Some(("SI_MESGQ", "POSIX message queue state changed")),
+        -4 => Some(("SI_ASYNCIO", "Async IO completed")),
+        -5 => Some(("SI_SIGIO", "Queued SIGIO")),
+        -6 => Some(("SI_TKILL", "Sent by tkill or tgkill")),
+        -7 => Some(("SI_DETHREAD", "Sent by execve killing subsidiary threads")),
+        -60 => Some(("SI_ASYNCNL", "Async name lookup completed")),
+        _ => None,
+    }
+}
+
+/// Returns a name for a seccomp AUDIT_ARCH_* value.
+pub fn audit_arch_name(arch: u32) -> Option<&'static str> {
+    // see include/uapi/linux/audit.h in the kernel
+    match arch {
+        0xc000003e => Some("x86_64"),
+        0x40000003 => Some("i386"),
+        0xc00000b7 => Some("aarch64"),
+        0x40000028 => Some("arm"),
+        0x00000008 => Some("mips"),
+        0x40000008 => Some("mipsel"),
+        0x00000014 => Some("ppc"),
+        0x80000015 => Some("ppc64"),
+        0xc0000015 => Some("ppc64le"),
+        0xc00000f3 => Some("riscv64"),
+        _ => None,
+    }
+}
+
+/// Returns the signal name along with a description of the kernel's si_code. Codes
+/// sent by processes aren't handled here, see SigInfo::signal.
+fn signal_description(signal_num: i32, signal_code: i32) -> &'static str {
+    match signal_num {
+        1 => "SIGHUP", // see https://man7.org/linux/man-pages/man7/signal.7.html
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => match signal_code {
+            // and https://sites.uclouvain.be/SystInfo/usr/include/bits/siginfo.h.html
+            1 => "SIGILL: Illegal opcode",          // ILL_ILLOPC
+            2 => "SIGILL: Illegal operand",         // ILL_ILLOPN
+            3 => "SIGILL: Illegal addressing mode", // ILL_ILLADR
+            4 => "SIGILL: Illegal trap",            // ILL_ILLTRP
+            5 => "SIGILL: Privileged opcode",       // ILL_PRVOPC
+            6 => "SIGILL: Privileged register",     // ILL_PRVREG
+            7 => "SIGILL: Coprocessor error",       // ILL_COPROC
+            8 => "SIGILL: Internal stack error",    // ILL_BADSTK
+            9 => "SIGILL: Unimplemented instruction address", // ILL_BADIADDR
+            _ => "SIGILL",
+        },
+        5 => match signal_code {
+            1 => "SIGTRAP: Process breakpoint",             // TRAP_BRKPT
+            2 => "SIGTRAP: Process trace trap",             // TRAP_TRACE
+            3 => "SIGTRAP: Process taken branch trap",      // TRAP_BRANCH
+            4 => "SIGTRAP: Hardware breakpoint/watchpoint", // TRAP_HWBKPT
+            5 => "SIGTRAP: Undiagnosed trap",               // TRAP_UNK
+            6 => "SIGTRAP: Perf event with sigtrap=1",      // TRAP_PERF
+            _ => "SIGTRAP",
+        },
+        6 => "SIGABRT",
+        7 => match signal_code {
+            1 => "SIGBUS: Invalid address alignment",      // BUS_ADRALN
+            2 => "SIGBUS: Non-existant physical address",  // BUS_ADRERR
+            3 => "SIGBUS: Object specific hardware error", // BUS_OBJERR
+            4 => "SIGBUS: Hardware memory error consumed on a machine check", // BUS_MCEERR_AR
+            5 => "SIGBUS: Hardware memory error detected in process but not consumed", // BUS_MCEERR_AO
+            _ => "SIGBUS",
+        },
+        8 => match signal_code {
+            1 => "SIGFPE: Integer divide by zero",           // FPE_INTDIV
+            2 => "SIGFPE: Integer overflow",                 // FPE_INTOVF
+            3 => "SIGFPE: Floating point divide by zero",    // FPE_FLTDIV
+            4 => "SIGFPE: Floating point overflow",          // FPE_FLTOVF
+            5 => "SIGFPE: Floating point underflow",         // FPE_FLTUND
+            6 => "SIGFPE: Floating point inexact result",    // FPE_FLTRES
+            7 => "SIGFPE: Floating point invalid operation", // FPE_FLTINV
+            8 => "SIGFPE: Subscript out of range",           // FPE_FLTSUB
+            14 => "SIGFPE: Undiagnosed floating point exception", // FPE_FLTUNK
+            15 => "SIGFPE: Trap on condition",               // FPE_CONDTRAP
+            _ => "SIGFPE",
+        },
+        9 => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => match signal_code {
+            1 => "SIGSEGV: Address not mapped to object", // SEGV_MAPERR
+            2 => "SIGSEGV: Invalid permissions for mapped object", // SEGV_ACCERR
+            3 => "SIGSEGV: Failed address bound checks",  // SEGV_BNDERR
+            4 => "SIGSEGV: Failed protection key checks", // SEGV_PKUERR
+            5 => "SIGSEGV: ADI not enabled for mapped object", // SEGV_ACCADI
+            6 => "SIGSEGV: Disrupting MCD error",         // SEGV_ADIDERR
+            7 => "SIGSEGV: Precise MCD exception",        // SEGV_ADIPERR
+            8 => "SIGSEGV: Asynchronous ARM MTE error",   // SEGV_MTEAERR
+            9 => "SIGSEGV: Synchronous ARM MTE exception", // SEGV_MTESERR
+            10 => "SIGSEGV: Control protection fault",    // SEGV_CPERR
+            _ => "SIGSEGV",
+        },
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        16 => "SIGSTKFLT",
+        17 => match signal_code {
+            1 => "SIGCHLD: Child has exited",            // CLD_EXITED
+            2 => "SIGCHLD: Child was killed",            // CLD_KILLED
+            3 => "SIGCHLD: Child terminated abnormally", // CLD_DUMPED
+            4 => "SIGCHLD: Traced child has trapped",    // CLD_TRAPPED
+            5 => "SIGCHLD: Child has stopped",           // CLD_STOPPED
+            6 => "SIGCHLD: Stopped child has continued", // CLD_CONTINUED
+            _ => "SIGCHLD",
+        },
+        18 => "SIGCONT",
+        19 => "SIGSTOP",
+        20 => "SIGTSTP",
+        21 => "SIGTTIN",
+        22 => "SIGTTOU",
+        23 => "SIGURG",
+        24 => "SIGXCPU",
+        25 => "SIGXFSZ",
+        26 => "SIGVTALRM",
+        27 => "SIGPROF",
+        28 => "SIGWINCH",
+        29 => match signal_code {
+            1 => "SIGIO: Data input available",          // POLL_IN
+            2 => "SIGIO: Output buffers available",      // POLL_OUT
+            3 => "SIGIO: Input message available",       // POLL_MSG
+            4 => "SIGIO: I/O error",                     // POLL_ERR
+            5 => "SIGIO: High priority input available", // POLL_PRI
+            6 => "SIGIO: Device disconnected",           // POLL_HUP
+            _ => "SIGIO",
+        },
+        30 => "SIGPWR",
+        31 => match signal_code {
+            1 => "SIGSYS: Seccomp triggered",     // SYS_SECCOMP
+            2 => "SIGSYS: Syscall user dispatch", // SYS_USER_DISPATCH
+            _ => "SIGSYS",
+        },
+        _ => "unknown signal",
+    }
 }
 
 impl PrStatus {
     pub fn signal(&self) -> &'static str {
-        match self.signal_num {
-            1 => "SIGHUP", // see https://man7.org/linux/man-pages/man7/signal.7.html
-            2 => "SIGINT",
-            3 => "SIGQUIT",
-            4 => match self.signal_code {
-                // and https://sites.uclouvain.be/SystInfo/usr/include/bits/siginfo.h.html
-                1 => "SIGILL: Illegal opcode",          // ILL_ILLOPC
-                2 => "SIGILL: Illegal operand",         // ILL_ILLOPN
-                3 => "SIGILL: Illegal addressing mode", // ILL_ILLADR
-                4 => "SIGILL: Illegal trap",            // ILL_ILLTRP
-                5 => "SIGILL: Privileged opcode",       // ILL_PRVOPC
-                6 => "SIGILL: Privileged register",     // ILL_PRVREG
-                7 => "SIGILL: Coprocessor error",       // ILL_COPROC
-                8 => "SIGILL: Internal stack error",    // ILL_BADSTK
-                _ => "SIGILL",
-            },
-            5 => match self.signal_code {
-                1 => "SIGTRAP: Process breakpoint", // TRAP_BRKPT
-                2 => "SIGTRAP: Process trace trap", // TRAP_TRACE
-                _ => "SIGTRAP",
-            },
-            6 => "SIGABRT",
-            7 => match self.signal_code {
-                1 => "SIGBUS: Invalid address alignment",      // BUS_ADRALN
-                2 => "SIGBUS: Non-existant physical address",  // BUS_ADRERR
-                3 => "SIGBUS: Object specific hardware error", // BUS_OBJERR
-                _ => "SIGBUS",
-            },
-            8 => match self.signal_code {
-                1 => "SIGFPE: Integer divide by zero",           // FPE_INTDIV
-                2 => "SIGFPE: Integer overflow",                 // FPE_INTOVF
-                3 => "SIGFPE: Floating point divide by zero",    // FPE_FLTDIV
-                4 => "SIGFPE: Floating point overflow",          // FPE_FLTOVF
-                5 => "SIGFPE: Floating point underflow",         // FPE_FLTUND
-                6 => "SIGFPE: Floating point inexact result",    // FPE_FLTRES
-                7 => "SIGFPE: Floating point invalid operation", // FPE_FLTINV
-                8 => "SIGFPE: Subscript out of range",           // FPE_FLTSUB
-                _ => "SIGFPE",
-            },
-            9 => "SIGKILL",
-            10 => "SIGUSR1",
-            11 => match self.signal_code {
-                1 => "SIGSEGV: Address not mapped to object", // SEGV_MAPERR
-                2 => "SIGSEGV: Invalid permissions for mapped object", // SEGV_ACCERR
-                _ => "SIGSEGV",
-            },
-            12 => "SIGUSR2",
-            13 => "SIGPIPE",
-            14 => "SIGALRM",
-            15 => "SIGTERM",
-            16 => "SIGSTKFLT",
-            17 => match self.signal_code {
-                1 => "SIGCHLD: Child has exited",            // CLD_EXITED
-                2 => "SIGCHLD: Child was killed",            // CLD_KILLED
-                3 => "SIGCHLD: Child terminated abnormally", // CLD_DUMPED
-                4 => "SIGCHLD: Traced child has trapped",    // CLD_TRAPPED
-                5 => "SIGCHLD: Child has stopped",           // CLD_STOPPED
-                6 => "SIGCHLD: Stopped child has continued", // CLD_CONTINUED
-                _ => "SIGCHLD",
-            },
-            18 => "SIGCONT",
-            19 => "SIGSTOP",
-            20 => "SIGTSTP",
-            21 => "SIGTTIN",
-            22 => "SIGTTOU",
-            23 => "SIGURG",
-            24 => "SIGXCPU",
-            25 => "SIGXFSZ",
-            26 => "SIGVTALRM",
-            27 => "SIGPROF",
-            28 => "SIGWINCH",
-            29 => "SIGIO",
-            30 => "SIGPWR",
-            31 => "SIGSYS",
-            _ => "unknown signal",
-        }
+        signal_description(self.signal_num, self.signal_code)
     }
 
     /// Returns the instruction address within the currently executing function.
     pub fn get_ip(&self) -> VirtualAddr {
-        VirtualAddr::from_raw(self.registers[16])
+        VirtualAddr::from_raw(self.registers[self.arch.ip_index()])
     }
 
     /// Points to after the end of locals on the stack and contains the callers stack top
-    /// (rbp). Returns garbage if -fomit-frame-pointer is used or for optimized builds
+    /// (e.g. rbp). Returns garbage if -fomit-frame-pointer is used or for optimized builds
     /// (when -fno-omit-frame-pointer isn't set).
     pub fn get_frame_stack_top(&self) -> VirtualAddr {
-        VirtualAddr::from_raw(self.registers[4])
+        VirtualAddr::from_raw(self.registers[self.arch.fp_index()])
     }
 
-    /// Points to the start of locals on the stack (rsp). Debug info has to be used to
+    /// Points to the start of locals on the stack (e.g. rsp). Debug info has to be used to
     /// figure out the amount of space locals take.
     pub fn get_frame_stack_bottom(&self) -> VirtualAddr {
-        VirtualAddr::from_raw(self.registers[19])
+        VirtualAddr::from_raw(self.registers[self.arch.sp_index()])
+    }
+
+    /// Returns the return address for architectures that have a link register.
+    pub fn get_link_register(&self) -> Option<VirtualAddr> {
+        self.arch
+            .lr_index()
+            .map(|i| self.strip_pac(VirtualAddr::from_raw(self.registers[i])))
+    }
+
+    /// Removes pointer authentication bits from a code address (e.g. a return address
+    /// saved on the stack).
+    pub fn strip_pac(&self, addr: VirtualAddr) -> VirtualAddr {
+        if self.pac_mask == 0 {
+            addr
+        } else {
+            VirtualAddr::from_raw(self.arch.strip_pac(addr.0, self.pac_mask))
+        }
     }
 
     /// Returns true for stuff like segment registers.
     pub fn is_rare_register(&self, n: usize) -> bool {
-        match n {
-            // TODO: good only for x86(?) and arm
-            17 => true, // cs
-            18 => true, // eflags
-            20 => true, // ss
-            22 => true, // ds
-            23 => true, // es
-            24 => true, // fs
-            25 => true, // gs
-            _ => false,
-        }
+        self.arch.is_rare_register(n)
     }
 
     pub fn register_name(&self, n: usize) -> &'static str {
-        match n {
-            // TODO: good only for x86(?) and arm
-            0 => "r15",
-            1 => "r14",
-            2 => "r13",
-            3 => "r12",
-            4 => "rbp",
-            5 => "rbx",
-            6 => "r11",
-            7 => "r10",
-            8 => "r9",
-            9 => "r8",
-            10 => "rax",
-            11 => "rcx",
-            12 => "rdx",
-            13 => "rsi",
-            14 => "rdi",
-            16 => "rip",
-            17 => "cs",
-            18 => "eflags",
-            19 => "rsp",
-            20 => "ss",
-            22 => "ds", // TODO not sure these last few are correct
-            23 => "es",
-            24 => "fs",
-            25 => "gs",
-            _ => "?",
-        }
+        self.arch.register_name(n)
     }
 }
 
@@ -344,8 +510,9 @@ pub struct MemoryMappedFile {
     /// Addressing for the bytes as they were loaded into memory.
     pub vbytes: Bytes<VirtualAddr>,
 
-    // /// Offset into the file used when memory mapping.
-    // pub offset: u64,
+    /// Offset into the file of the first mapped byte.
+    pub offset: u64,
+
     /// The name of the file.
     pub file_name: String,
 }
@@ -3,7 +3,7 @@ use std::fmt;
 use std::ops::{Add, AddAssign, Sub};
 
 /// Index into the section table.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub struct SectionIndex(pub u32);
 
 /// Index into a string table.
@@ -16,7 +16,7 @@ pub struct Offset(pub u64);
 
 /// The address of a byte in the process that cored. These are normally associated with
 /// one of the load segments in the core file.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub struct VirtualAddr(pub u64);
 
 /// An address in an exe file. These will be relative to a memory mapped segment in the
@@ -38,19 +38,22 @@ where
 
 /// Points to a null-terminated string with an unspecified encoding in an ELF file. To
 /// avoid allocations we avoid trying to convert these to a String.
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct StringView {
-    reader: &'static Reader,
+    reader: Reader,
     offset: Offset,
 }
 
 impl StringView {
-    pub fn new(reader: &'static Reader, offset: Offset) -> Self {
-        StringView { reader, offset }
+    pub fn new(reader: &Reader, offset: Offset) -> Self {
+        StringView {
+            reader: reader.clone(),
+            offset,
+        }
     }
 
     // pub fn new(
-    //     reader: &'static Reader,
+    //     reader: &Reader,
     //     offset: Offset,
     // ) -> Result<Self, Box<dyn std::error::Error>> {
     //     let mut len = 0;
@@ -212,11 +215,7 @@ impl Add<i64> for VirtualAddr {
     type Output = VirtualAddr;
 
     fn add(self, rhs: i64) -> Self::Output {
-        if rhs < 0 {
-            VirtualAddr(self.0 - (-rhs) as u64)
-        } else {
-            VirtualAddr(self.0 + rhs as u64)
-        }
+        VirtualAddr(self.0.wrapping_add_signed(rhs))
     }
 }
 
@@ -0,0 +1,268 @@
+//! A map of the cored process's address space built from the load segments, NT_FILE,
+//! and the other notes in a core file.
+use super::{Bytes, ElfFile, MemoryMappedFile, Offset, ProgramHeader, VirtualAddr, WRITE_FLAG};
+use std::fmt::{self, Display};
+
+/// AT_SYSINFO_EHDR, the address of the vDSO.
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+/// AT_ENTRY, the exe's entry point.
+const AT_ENTRY: u64 = 9;
+
+/// The kernel randomizes where brk starts by up to this much (on arm64, x86-64 uses 32 MiB).
+const MAX_BRK_OFFSET: u64 = 0x40000000;
+
+/// Where the kernel maps the legacy vsyscall page on x86-64.
+const VSYSCALL_ADDR: u64 = 0xffffffffff600000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Memory mapped from a file, e.g. an exe or shared library.
+    File,
+
+    /// The sbrk heap used by malloc's main arena.
+    Heap,
+
+    /// The stack for the thread with this id.
+    Stack(i32),
+
+    /// Shared library the kernel maps into every process to speed up some system calls.
+    Vdso,
+
+    /// Kernel data read by the vDSO.
+    Vvar,
+
+    /// Legacy page used for system calls on x86-64.
+    Vsyscall,
+
+    /// Memory that isn't backed by a file, e.g. from mmap or for non-main malloc arenas.
+    Anonymous,
+}
+
+impl Display for RegionKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionKind::File => write!(fmt, "file"),
+            RegionKind::Heap => write!(fmt, "[heap]"),
+            RegionKind::Stack(tid) => write!(fmt, "[stack {tid}]"),
+            RegionKind::Vdso => write!(fmt, "[vdso]"),
+            RegionKind::Vvar => write!(fmt, "[vvar]"),
+            RegionKind::Vsyscall => write!(fmt, "[vsyscall]"),
+            RegionKind::Anonymous => write!(fmt, "anonymous"),
+        }
+    }
+}
+
+/// A contiguous range of the address space with the same permissions.
+pub struct MemoryRegion {
+    /// Addressing for the bytes as they were in memory.
+    pub vbytes: Bytes<VirtualAddr>,
+
+    /// Addressing for the bytes written to the core. This may be smaller than vbytes (or
+    /// empty), e.g. cores usually omit read-only pages that can be re-read from a file.
+    pub obytes: Bytes<Offset>,
+
+    /// Readable, writeable, and/or executable.
+    pub flags: u32,
+
+    /// The file the region was mapped from and the offset into it of the first byte.
+    pub file: Option<(String, u64)>,
+
+    pub kind: RegionKind,
+}
+
+impl MemoryRegion {
+    pub fn flags(&self) -> String {
+        ProgramHeader::flags(self.flags)
+    }
+
+    /// "all", "none", or "partial" depending on how much of the region is in the core.
+    pub fn presence(&self) -> &'static str {
+        if self.obytes.size >= self.vbytes.size {
+            "all"
+        } else if self.obytes.size == 0 {
+            "none"
+        } else {
+            "partial"
+        }
+    }
+}
+
+/// Returns a region for each load segment in the core. heap_top is the top chunk of
+/// malloc's main arena (if known) and is used to find the sbrk heap. If it's not known
+/// then the heap is found using where brk starts.
+pub fn find_regions(core: &ElfFile, heap_top: Option<VirtualAddr>) -> Vec<MemoryRegion> {
+    let mappings = core.get_file_mappings().as_deref().unwrap_or_default();
+    let stacks: Vec<_> = core
+        .find_prstatuses()
+        .iter()
+        .map(|s| (s.pid, s.get_frame_stack_bottom()))
+        .collect();
+    let vdso = core.find_auxv(AT_SYSINFO_EHDR).map(VirtualAddr);
+
+    let mut regions: Vec<MemoryRegion> = core
+        .loads
+        .iter()
+        .map(|load| {
+            let file = mappings
+                .iter()
+                .find(|m| m.vbytes.contains(load.vbytes.start))
+                .map(|m| {
+                    let delta = load.vbytes.start.0 - m.vbytes.start.0;
+                    (m.file_name.clone(), m.offset + delta)
+                });
+            let kind = if file.as_ref().is_some_and(|(name, _)| name == "[heap]") {
+                RegionKind::Heap
+            } else if file.is_some() {
+                RegionKind::File
+            } else if let Some((tid, _)) = stacks.iter().find(|(_, sp)| load.vbytes.contains(*sp)) {
+                RegionKind::Stack(*tid)
+            } else if heap_top.is_some_and(|top| load.vbytes.contains(top)) {
+                RegionKind::Heap
+            } else if vdso == Some(load.vbytes.start) {
+                RegionKind::Vdso
+            } else if load.vbytes.start.0 == VSYSCALL_ADDR {
+                RegionKind::Vsyscall
+            } else {
+                RegionKind::Anonymous
+            };
+            MemoryRegion {
+                vbytes: load.vbytes,
+                obytes: load.obytes,
+                flags: load.flags,
+                file: file.filter(|_| kind == RegionKind::File),
+                kind,
+            }
+        })
+        .collect();
+
+    if !regions.iter().any(|r| r.kind == RegionKind::Heap)
+        && let Some(start) = brk_start(core, mappings)
+    {
+        // The brk heap is the first anonymous region after the exe's data, it's before
+        // any shared libraries. Processes that never called malloc won't have one.
+        let next_file = mappings
+            .iter()
+            .map(|m| m.vbytes.start)
+            .filter(|&addr| addr >= start)
+            .min();
+        if let Some(region) = regions.iter_mut().find(|r| {
+            r.vbytes.start >= start
+                && r.vbytes.start.0 - start.0 <= MAX_BRK_OFFSET
+                && next_file.is_none_or(|addr| r.vbytes.start < addr)
+                && r.flags & WRITE_FLAG != 0
+                && r.kind == RegionKind::Anonymous
+        }) {
+            region.kind = RegionKind::Heap;
+        }
+    }
+
+    // vvar is read-only and immediately precedes the vDSO.
+    if let Some(i) = regions.iter().position(|r| r.kind == RegionKind::Vdso)
+        && i > 0
+    {
+        let vdso = regions[i].vbytes.start;
+        let prev = &mut regions[i - 1];
+        if prev.kind == RegionKind::Anonymous
+            && prev.vbytes.end() == vdso
+            && prev.flags & WRITE_FLAG == 0
+        {
+            prev.kind = RegionKind::Vvar;
+        }
+    }
+    regions
+}
+
+/// brk starts just after the end of the exe's last mapping (plus a random offset).
+fn brk_start(core: &ElfFile, mappings: &[MemoryMappedFile]) -> Option<VirtualAddr> {
+    let exe = exe_mapping(core, mappings)?;
+    mappings
+        .iter()
+        .filter(|m| m.file_name == exe.file_name)
+        .map(|m| m.vbytes.end())
+        .max()
+}
+
+/// Returns the exe's first mapping. The exe is the file with the entry point or, failing
+/// that, the first mapped file.
+pub fn exe_mapping<'a>(
+    core: &ElfFile,
+    mappings: &'a [MemoryMappedFile],
+) -> Option<&'a MemoryMappedFile> {
+    let entry = core.find_auxv(AT_ENTRY).map(VirtualAddr);
+    let exe = entry
+        .and_then(|addr| mappings.iter().find(|m| m.vbytes.contains(addr)))
+        .or_else(|| mappings.first())?;
+    mappings.iter().find(|m| m.file_name == exe.file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::core_builder::CoreBuilder;
+
+    #[test]
+    fn regions() {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        let mut regs = vec![0; 27];
+        regs[19] = 0x7ff100; // rsp
+        builder.prstatus(11, 1, 100, &regs);
+        regs[19] = 0x7fe100;
+        builder.prstatus(0, 0, 101, &regs);
+        builder.mapped_files(&[
+            (0x400000, 0x401000, 0, "/bin/app"),
+            (0x401000, 0x403000, 1, "/bin/app"),
+        ]);
+        builder.auxv(&[(6, 0x1000), (AT_SYSINFO_EHDR, 0x7fff2000)]);
+
+        builder.load_partial(0x400000, vec![0; 0x800], 0x1000, 4);
+        builder.load_partial(0x401000, Vec::new(), 0x2000, 5);
+        builder.load(0x500000, vec![0; 0x1000], 6);
+        builder.load(0x7fe000, vec![0; 0x1000], 6);
+        builder.load(0x7ff000, vec![0; 0x1000], 6);
+        builder.load(0x7fff0000, vec![0; 0x2000], 4);
+        builder.load(0x7fff2000, vec![0; 0x1000], 5);
+        let core = ElfFile::new(builder.write("regions")).unwrap();
+
+        let s: String = find_regions(&core, Some(VirtualAddr(0x500100)))
+            .iter()
+            .map(|r| {
+                format!(
+                    "{:x} {:x} {} {} {} {:?}\n",
+                    r.vbytes.start.0,
+                    r.vbytes.size,
+                    r.flags(),
+                    r.presence(),
+                    r.kind,
+                    r.file
+                )
+            })
+            .collect();
+        insta::assert_snapshot!(s);
+
+        // Without malloc's arena the heap is found using brk.
+        let kinds = |regions: Vec<MemoryRegion>| -> Vec<RegionKind> {
+            regions.iter().map(|r| r.kind).collect()
+        };
+        assert_eq!(
+            kinds(find_regions(&core, None)),
+            kinds(find_regions(&core, Some(VirtualAddr(0x500100))))
+        );
+    }
+
+    #[test]
+    fn heap_mapping() {
+        let mut builder = CoreBuilder::new(0x3E, true, true);
+        builder.mapped_files(&[
+            (0x400000, 0x401000, 0, "/bin/app"),
+            (0x500000, 0x501000, 0, "[heap]"),
+        ]);
+        builder.load(0x400000, vec![0; 0x1000], 4);
+        builder.load(0x500000, vec![0; 0x1000], 6);
+        let core = ElfFile::new(builder.write("heap-mapping")).unwrap();
+
+        let regions = find_regions(&core, None);
+        assert_eq!(regions[1].kind, RegionKind::Heap);
+        assert!(regions[1].file.is_none());
+    }
+}
@@ -1,6 +1,7 @@
 //! Used by the linker and debugger. Also see segments.
 use super::{Reader, Stream};
 use crate::{
+    arch::{Arch, RelocationType},
     elf::{Bytes, Offset, SectionIndex, StringIndex, VirtualAddr},
     utils,
 };
@@ -141,6 +142,11 @@ impl SectionType {
 }
 
 impl SectionHeader {
+    /// True if the section occupies memory when the file is loaded (SHF_ALLOC).
+    pub fn is_alloc(&self) -> bool {
+        self.flags & ALLOC_FLAG != 0
+    }
+
     pub fn flags(flags: u64) -> String {
         let mut result = Vec::new();
         if flags & WRITE_FLAG != 0 {
@@ -190,7 +196,7 @@ impl SectionHeader {
 }
 
 impl SectionHeader {
-    pub fn new(reader: &'static Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
+    pub fn new(reader: &Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
         let mut s = Stream::new(reader, offset);
         if reader.sixty_four_bit {
             let name = s.read_word()?;
@@ -240,118 +246,77 @@ impl SectionHeader {
     }
 }
 
+/// Where a relocation is applied.
+#[derive(Clone, Copy, Debug)]
+pub enum RelocationOffset {
+    /// Offset into the target section, used by relocatable (ET_REL) files.
+    Section(u64),
+
+    /// Virtual address (before the load bias), used by executables and shared objects.
+    Vaddr(VirtualAddr),
+}
+
 // see https://intezer.com/blog/executable-and-linkable-format-101-part-3-relocations/
 #[derive(Debug)]
 pub struct Relocation {
-    pub offset: u64, // offset or vaddr TODO should probably use an enum or different structs
+    pub offset: RelocationOffset,
+
+    /// True if the symbol is in the dynamic symbol table.
     pub dynamic: bool,
+
+    /// Index of the symbol within symbol_table.
     pub symbol: u32,
-    pub rtype: RelocationX86_64,
-    pub addend: Option<i64>,
-}
 
-#[derive(Debug)]
-pub enum RelocationX86_64 {
-    // name        val  field   calculation
-    None,       // 0	None	None
-    SixtyFour,  // 1	qword	S + A
-    Pc32,       // 2	dword	S + A – P
-    Got32,      // 3	dword	G + A
-    Plt32,      // 4	dword	L + A – P
-    Copy,       // 5	None	Value is copied directly from shared object
-    GlobDat,    // 6	qword	S
-    JumpSlot,   // 7	qword	S
-    Relative,   // 8	qword	B + A
-    GotPcRel,   // 9	dword	G + GOT + A – P
-    ThirtyTwo,  // 10	dword	S + A
-    ThirtyTwoS, // 11	dword	S + A
-    Sixteen,    // 12	word	S + A
-    Pc16,       // 13	word	S + A – P
-    Eight,      // 14	word8	S + A
-    Pc8,        // 15	word8	S + A – P
-    Pc64,       // 24	qword	S + A – P
-    GoTOoff64,  // 25	qword	S + A – GOT
-    GotPc32,    // 26	dword	GOT + A – P
-    Size32,     // 32	dword	Z + A
-    Size64,     // 33	qword	Z + A
-}
+    /// The symbol table used by the relocation (the relocation section's sh_link).
+    pub symbol_table: SectionIndex,
 
-impl Relocation {
-    pub fn with_no_addend(
-        reader: &'static Reader,
-        offset: Offset,
-        dynamic: bool,
-    ) -> Result<Self, Box<dyn Error>> {
-        Relocation::new(reader, offset, false, dynamic)
-    }
+    /// The section the relocation applies to (sh_info), None if there isn't one.
+    pub target: Option<SectionIndex>,
 
-    pub fn with_addend(
-        reader: &'static Reader,
-        offset: Offset,
-        dynamic: bool,
-    ) -> Result<Self, Box<dyn Error>> {
-        Relocation::new(reader, offset, true, dynamic)
-    }
+    pub rtype: RelocationType,
+    pub addend: Option<i64>,
+}
 
-    fn new(
-        reader: &'static Reader,
+impl Relocation {
+    /// Reads the entry at offset within section (which should be a SHT_RELA or SHT_REL
+    /// section).
+    pub fn new(
+        reader: &Reader,
+        arch: &'static dyn Arch,
         offset: Offset,
-        has_addend: bool,
+        section: &SectionHeader,
+        relocatable: bool,
         dynamic: bool,
     ) -> Result<Self, Box<dyn Error>> {
         let mut s = Stream::new(reader, offset);
         let offset = s.read_addr()?;
-        let info = s.read_xword()?;
-        let addend = if has_addend {
-            Some(s.read_sxword()?)
-        } else {
-            None
+        let info = s.read_ulong()?;
+        let addend = match (section.stype, reader.sixty_four_bit) {
+            (SectionType::RelocationsWith, true) => Some(s.read_sxword()?),
+            (SectionType::RelocationsWith, false) => Some(s.read_int()? as i64),
+            _ => None,
         };
-        if reader.sixty_four_bit {
-            Ok(Relocation {
-                offset,
-                symbol: (info >> 32) as u32,
-                rtype: RelocationX86_64::from_u64(info & 0xffffffff)?,
-                addend,
-                dynamic,
-            })
+        let (symbol, rtype) = if reader.sixty_four_bit {
+            ((info >> 32) as u32, (info & 0xffffffff) as u32)
         } else {
-            Ok(Relocation {
-                offset,
-                symbol: (info >> 8) as u32,
-                rtype: RelocationX86_64::from_u64(info & 0xff)?,
-                addend,
-                dynamic,
-            })
-        }
-    }
-}
-
-impl RelocationX86_64 {
-    fn from_u64(rtype: u64) -> Result<Self, Box<dyn Error>> {
-        match rtype {
-            0 => Ok(RelocationX86_64::None),
-            1 => Ok(RelocationX86_64::SixtyFour),
-            2 => Ok(RelocationX86_64::Pc32),
-            3 => Ok(RelocationX86_64::Got32),
-            4 => Ok(RelocationX86_64::Plt32),
-            5 => Ok(RelocationX86_64::Copy),
-            6 => Ok(RelocationX86_64::GlobDat),
-            7 => Ok(RelocationX86_64::JumpSlot),
-            8 => Ok(RelocationX86_64::Relative),
-            9 => Ok(RelocationX86_64::GotPcRel),
-            10 => Ok(RelocationX86_64::ThirtyTwo),
-            11 => Ok(RelocationX86_64::ThirtyTwoS),
-            12 => Ok(RelocationX86_64::Sixteen),
-            13 => Ok(RelocationX86_64::Pc16),
-            14 => Ok(RelocationX86_64::Eight),
-            15 => Ok(RelocationX86_64::Pc8),
-            24 => Ok(RelocationX86_64::Pc64),
-            25 => Ok(RelocationX86_64::GoTOoff64),
-            26 => Ok(RelocationX86_64::GotPc32),
-            32 => Ok(RelocationX86_64::Size32),
-            33 => Ok(RelocationX86_64::Size64),
-            _ => Err(format!("bad x86 64 relocation type: {rtype}").into()),
-        }
+            ((info >> 8) as u32, (info & 0xff) as u32)
+        };
+        Ok(Relocation {
+            offset: if relocatable {
+                RelocationOffset::Section(offset)
+            } else {
+                RelocationOffset::Vaddr(VirtualAddr(offset))
+            },
+            dynamic,
+            symbol,
+            symbol_table: section.link,
+            target: if section.info != 0 {
+                Some(SectionIndex(section.info))
+            } else {
+                None
+            },
+            rtype: arch.relocation_type(rtype)?,
+            addend,
+        })
     }
 }
@@ -6,9 +6,9 @@ use crate::{
 };
 use std::error::Error;
 
-const EXECUTE_FLAG: u32 = 0x1;
-const WRITE_FLAG: u32 = 0x2;
-const READ_FLAG: u32 = 0x4;
+pub const EXECUTE_FLAG: u32 = 0x1;
+pub const WRITE_FLAG: u32 = 0x2;
+pub const READ_FLAG: u32 = 0x4;
 
 /// Describes a segment. Usually LoadSegment or Note will be used instead of this.
 pub struct ProgramHeader {
@@ -94,15 +94,6 @@ pub struct LoadSegment {
 }
 
 impl LoadSegment {
-    pub fn to_offset(&self, vaddr: VirtualAddr) -> Option<Offset> {
-        if self.vbytes.contains(vaddr) {
-            let delta = vaddr.0 - self.vbytes.start.0;
-            Some(Offset(self.obytes.start.0 + delta))
-        } else {
-            None
-        }
-    }
-
     pub fn executable(&self) -> bool {
         self.flags & EXECUTE_FLAG != 0
     }
@@ -121,7 +112,7 @@ impl LoadSegment {
 }
 
 impl ProgramHeader {
-    pub fn new(reader: &'static Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
+    pub fn new(reader: &Reader, offset: Offset) -> Result<Self, Box<dyn Error>> {
         // Field sizes and order differ between 32-bit and 64-bit ELF files,
         // see https://llvm.org/doxygen/BinaryFormat_2ELF_8h_source.html.
         let mut s = Stream::new(reader, offset);
@@ -0,0 +1,101 @@
+//! Thread local storage. Each object with a PT_TLS segment is a TLS module and each
+//! thread has a block of memory for every module. The thread pointer points to the
+//! thread control block (TCB) which points to the dynamic thread vector (DTV), an
+//! array with the address of each of the thread's blocks. See "ELF Handling For
+//! Thread-Local Storage" by Ulrich Drepper.
+use crate::elf::{Memory, MemoryError, VirtualAddr};
+
+const PT_TLS: u64 = 7;
+
+/// The layout of a module's TLS block.
+#[derive(Clone, Copy, Debug)]
+pub struct TlsSegment {
+    /// Size of the initialization image, the rest of the block is zeroed.
+    pub file_size: u64,
+
+    /// Size of the block.
+    pub mem_size: u64,
+
+    pub align: u64,
+}
+
+/// A module with TLS along with where the selected thread's block is.
+pub struct TlsModule {
+    /// Index into the DTV, these start at one.
+    pub id: usize,
+
+    /// Path to the object, empty for the exe.
+    pub name: String,
+
+    /// The object's load bias.
+    pub bias: u64,
+
+    pub segment: TlsSegment,
+
+    /// Address of the thread's block, None if it wasn't allocated (glibc allocates
+    /// blocks for dlopened modules lazily).
+    pub block: Option<VirtualAddr>,
+}
+
+/// Finds the PT_TLS program header using the ELF header mapped at addr.
+pub fn read_tls_segment(
+    memory: &impl Memory,
+    addr: VirtualAddr,
+) -> Result<Option<TlsSegment>, MemoryError> {
+    let (ph_offset, entry_size, count) = if memory.addr_size() == 8 {
+        (
+            memory.read_u64(addr + 0x20)?,
+            memory.read_u16(addr + 0x36)?,
+            memory.read_u16(addr + 0x38)?,
+        )
+    } else {
+        (
+            memory.read_u32(addr + 0x1c)? as u64,
+            memory.read_u16(addr + 0x2a)?,
+            memory.read_u16(addr + 0x2c)?,
+        )
+    };
+
+    for i in 0..count as u64 {
+        let ph = addr + (ph_offset + i * entry_size as u64) as i64;
+        if memory.read_u32(ph)? as u64 != PT_TLS {
+            continue;
+        }
+        let segment = if memory.addr_size() == 8 {
+            TlsSegment {
+                file_size: memory.read_u64(ph + 32)?,
+                mem_size: memory.read_u64(ph + 40)?,
+                align: memory.read_u64(ph + 48)?,
+            }
+        } else {
+            TlsSegment {
+                file_size: memory.read_u32(ph + 16)? as u64,
+                mem_size: memory.read_u32(ph + 20)? as u64,
+                align: memory.read_u32(ph + 28)? as u64,
+            }
+        };
+        return Ok(Some(segment));
+    }
+    Ok(None)
+}
+
+/// Returns the address of module id's block using the DTV. glibc's DTV pointer points
+/// at the generation count which is preceded by the number of entries.
+pub fn read_dtv_block(
+    memory: &impl Memory,
+    dtv: VirtualAddr,
+    id: usize,
+) -> Result<Option<VirtualAddr>, MemoryError> {
+    // Entries are a pointer and an is_static flag.
+    let entry_size = 2 * memory.addr_size() as i64;
+    let count = memory.read_ptr(dtv + -entry_size)?;
+    if id as u64 > count {
+        return Ok(None);
+    }
+    let block = memory.read_ptr(dtv + id as i64 * entry_size)?;
+    if block == 0 || block == u64::MAX >> (64 - 8 * memory.addr_size()) {
+        Ok(None) // TLS_DTV_UNALLOCATED
+    } else {
+        Ok(Some(VirtualAddr(block)))
+    }
+}
@@ -0,0 +1,613 @@
+//! Model of glibc's malloc (ptmalloc2). Layouts are for glibc 2.30 and later, see
+//! https://sourceware.org/git/?p=glibc.git;a=blob;f=malloc/malloc.c
+use crate::elf::{ElfFile, ElfFiles, Memory, VirtualAddr};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+const NFASTBINS: usize = 10;
+const NBINS: usize = 128;
+const NSMALLBINS: usize = 64;
+const TCACHE_MAX_BINS: usize = 64;
+
+const PREV_INUSE: u64 = 0x1;
+const IS_MMAPPED: u64 = 0x2;
+const NON_MAIN_ARENA: u64 = 0x4;
+const SIZE_BITS: u64 = 0x7;
+
+/// Guards against cycles in corrupted arena and free lists.
+const MAX_LIST_LEN: usize = 100_000;
+
+/// The free list a chunk is on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Bin {
+    Tcache(usize),
+    Fast(usize),
+    Unsorted,
+    Small(usize),
+    Large(usize),
+}
+
+impl fmt::Display for Bin {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bin::Tcache(i) => write!(fmt, "tcache[{i}]"),
+            Bin::Fast(i) => write!(fmt, "fastbin[{i}]"),
+            Bin::Unsorted => write!(fmt, "unsorted"),
+            Bin::Small(i) => write!(fmt, "smallbin[{i}]"),
+            Bin::Large(i) => write!(fmt, "largebin[{i}]"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChunkState {
+    InUse,
+    Free(Bin),
+    Top,
+}
+
+impl fmt::Display for ChunkState {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChunkState::InUse => write!(fmt, "in use"),
+            ChunkState::Free(bin) => write!(fmt, "free ({bin})"),
+            ChunkState::Top => write!(fmt, "top"),
+        }
+    }
+}
+
+/// A malloc_chunk found by walking an arena's heap.
+pub struct Chunk {
+    pub addr: VirtualAddr,
+
+    /// The raw size field, i.e. including the flag bits.
+    pub size_field: u64,
+
+    pub state: ChunkState,
+
+    /// Address of the arena the chunk belongs to.
+    pub arena: VirtualAddr,
+}
+
+impl Chunk {
+    pub fn size(&self) -> u64 {
+        self.size_field & !SIZE_BITS
+    }
+
+    /// Returns true if the address is within the chunk (including its header).
+    pub fn contains(&self, addr: VirtualAddr) -> bool {
+        addr.0 >= self.addr.0 && addr.0 < self.addr.0 + self.size()
+    }
+
+    /// P if the previous chunk is in use, M if mmapped, A if not in the main arena.
+    pub fn flags(&self) -> String {
+        let mut s = String::new();
+        s.push(if self.size_field & PREV_INUSE != 0 {
+            'P'
+        } else {
+            '-'
+        });
+        s.push(if self.size_field & IS_MMAPPED != 0 {
+            'M'
+        } else {
+            '-'
+        });
+        s.push(if self.size_field & NON_MAIN_ARENA != 0 {
+            'A'
+        } else {
+            '-'
+        });
+        s
+    }
+}
+
+/// A malloc_state struct.
+pub struct Arena {
+    pub addr: VirtualAddr,
+    pub main: bool,
+    pub fastbins: Vec<u64>,
+    pub top: u64,
+    pub last_remainder: u64,
+
+    /// fd and bk for each bin, index 0 is unused.
+    pub bins: Vec<(u64, u64)>,
+    pub next: u64,
+    pub system_mem: u64,
+
+    /// Offset of the bins array.
+    bins_offset: u64,
+
+    /// Address just past the struct.
+    end: VirtualAddr,
+}
+
+impl Arena {
+    fn new(core: &ElfFile, addr: VirtualAddr, main: bool) -> Result<Self, Box<dyn Error>> {
+        let ptr = core.reader.addr_size() as u64;
+        let read = |offset: u64| {
+            core.read_ptr(VirtualAddr(addr.0 + offset))
+                .map_err(|_| format!("couldn't read the arena at 0x{:x}", addr.0))
+        };
+
+        // mutex, flags, and have_fastchunks are ints
+        let fastbins_offset = 12_u64.next_multiple_of(ptr);
+        let fastbins = (0..NFASTBINS as u64)
+            .map(|i| read(fastbins_offset + i * ptr))
+            .collect::<Result<Vec<_>, _>>()?;
+        let top_offset = fastbins_offset + NFASTBINS as u64 * ptr;
+        let top = read(top_offset)?;
+        let last_remainder = read(top_offset + ptr)?;
+
+        let bins_offset = top_offset + 2 * ptr;
+        let mut bins = vec![(0, 0)];
+        for i in 1..NBINS as u64 {
+            let offset = bins_offset + 2 * (i - 1) * ptr;
+            bins.push((read(offset)?, read(offset + ptr)?));
+        }
+
+        // bins has NBINS*2 - 2 entries and is followed by the binmap (four ints)
+        let next_offset = bins_offset + (2 * NBINS as u64 - 2) * ptr + 16;
+        let next = read(next_offset)?;
+        let system_mem = read(next_offset + 3 * ptr)?; // skip next_free and attached_threads
+        let end = VirtualAddr(addr.0 + next_offset + 5 * ptr);
+
+        Ok(Arena {
+            addr,
+            main,
+            fastbins,
+            top,
+            last_remainder,
+            bins,
+            next,
+            system_mem,
+            bins_offset,
+            end,
+        })
+    }
+
+    /// The address of the fake chunk used as the head of bin i.
+    fn bin_at(&self, i: usize, ptr: u64) -> u64 {
+        self.addr.0 + self.bins_offset + 2 * (i as u64 - 1) * ptr - 2 * ptr
+    }
+}
+
+/// A free list within an arena (or a thread's tcache).
+pub struct FreeList {
+    pub arena: VirtualAddr,
+    pub bin: Bin,
+
+    /// Addresses of the chunks on the list.
+    pub chunks: Vec<VirtualAddr>,
+}
+
+/// Everything we know about the glibc heap within a core.
+pub struct Heap {
+    pub arenas: Vec<Arena>,
+
+    /// Chunks found by walking each arena's heap.
+    pub chunks: Vec<Chunk>,
+
+    /// Non-empty free lists.
+    pub lists: Vec<FreeList>,
+
+    /// Corrupted sizes, links, etc.
+    pub problems: Vec<String>,
+}
+
+impl Heap {
+    /// main_arena is the address of the main_arena global in libc.
+    pub fn new(core: &ElfFile, main_arena: VirtualAddr) -> Result<Self, Box<dyn Error>> {
+        let mut heap = Heap {
+            arenas: Vec::new(),
+            chunks: Vec::new(),
+            lists: Vec::new(),
+            problems: Vec::new(),
+        };
+        heap.read_arenas(core, main_arena)?;
+
+        let mut states = HashMap::new();
+        for arena in heap.arenas.iter() {
+            heap.problems
+                .extend(find_free_lists(core, arena, &mut heap.lists, &mut states));
+        }
+
+        let mut chunks = Vec::new();
+        for arena in heap.arenas.iter() {
+            heap.problems
+                .extend(walk_chunks(core, arena, &states, &mut chunks));
+        }
+        heap.chunks = chunks;
+        let problems = heap.find_tcache(core);
+        heap.problems.extend(problems);
+        for list in heap.lists.iter() {
+            for chunk in list.chunks.iter() {
+                check_size(core, list, *chunk, &mut heap.problems);
+            }
+        }
+        heap.check_free_chunks();
+        Ok(heap)
+    }
+
+    /// Returns the chunk that addr is within.
+    pub fn find_chunk(&self, addr: VirtualAddr) -> Option<&Chunk> {
+        self.chunks.iter().find(|c| c.contains(addr))
+    }
+
+    fn read_arenas(
+        &mut self,
+        core: &ElfFile,
+        main_arena: VirtualAddr,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut addr = main_arena;
+        loop {
+            let arena = Arena::new(core, addr, addr == main_arena)?;
+            let next = VirtualAddr(arena.next);
+            self.arenas.push(arena);
+            if next == main_arena || next.0 == 0 {
+                break;
+            }
+            if self.arenas.len() > MAX_LIST_LEN || self.arenas.iter().any(|a| a.addr == next) {
+                self.problems
+                    .push(format!("arena list loops back to 0x{:x}", next.0));
+                break;
+            }
+            addr = next;
+        }
+        Ok(())
+    }
+
+    /// Each thread has a tcache_perthread_struct which is allocated as the first chunk
+    /// within the arena the thread uses.
+    fn find_tcache(&mut self, core: &ElfFile) -> Vec<String> {
+        let mut problems = Vec::new();
+        let ptr = core.reader.addr_size() as u64;
+        let tcache_size = (TCACHE_MAX_BINS as u64 * 2 + TCACHE_MAX_BINS as u64 * ptr + ptr)
+            .next_multiple_of(2 * ptr);
+
+        let mut states = HashMap::new();
+        let mut lists = Vec::new();
+        for arena in self.arenas.iter() {
+            let Some(first) = self.chunks.iter().find(|c| c.arena == arena.addr) else {
+                continue;
+            };
+            if first.size() != tcache_size || first.state != ChunkState::InUse {
+                continue;
+            }
+
+            let counts = first.addr.0 + 2 * ptr;
+            let entries = counts + 2 * TCACHE_MAX_BINS as u64;
+            for i in 0..TCACHE_MAX_BINS {
+                let count = core.read_u16(VirtualAddr(counts + 2 * i as u64));
+                let head = core.read_ptr(VirtualAddr(entries + i as u64 * ptr));
+                let (Ok(count), Ok(head)) = (count, head) else {
+                    problems.push(format!("couldn't read the tcache at 0x{:x}", first.addr.0));
+                    break;
+                };
+                if head == 0 && count == 0 {
+                    continue;
+                }
+
+                // Entries point to user data and the links are at the start of the data.
+                let bin = Bin::Tcache(i);
+                let mut list = FreeList {
+                    arena: arena.addr,
+                    bin,
+                    chunks: Vec::new(),
+                };
+                let mut mem = head;
+                while mem != 0 {
+                    let Some(chunk) = mem.checked_sub(2 * ptr).map(VirtualAddr) else {
+                        problems.push(format!("{bin} has a corrupted tcache link (0x{mem:x})"));
+                        break;
+                    };
+                    if !check_list_chunk(core, &list, chunk, &mut problems) {
+                        break;
+                    }
+                    list.chunks.push(chunk);
+                    states.insert(chunk, bin);
+                    match next_link(core, VirtualAddr(mem), 2 * ptr) {
+                        Ok(next) => mem = next,
+                        Err(err) => {
+                            problems.push(format!("{bin} chunk at 0x{:x} {err}", chunk.0));
+                            break;
+                        }
+                    }
+                }
+                if list.chunks.len() != count as usize {
+                    problems.push(format!(
+                        "{bin} has a count of {count} but {} chunks",
+                        list.chunks.len()
+                    ));
+                }
+                lists.push(list);
+            }
+        }
+
+        for chunk in self.chunks.iter_mut() {
+            if let Some(bin) = states.get(&chunk.addr) {
+                chunk.state = ChunkState::Free(*bin);
+            }
+        }
+        self.lists.splice(0..0, lists);
+        problems
+    }
+
+    /// Free chunks that aren't in the tcache or a fastbin should have their next
+    /// chunk's PREV_INUSE bit cleared.
+    fn check_free_chunks(&mut self) {
+        for (chunk, next) in self.chunks.iter().zip(self.chunks.iter().skip(1)) {
+            if chunk.arena != next.arena || chunk.addr.0 + chunk.size() != next.addr.0 {
+                continue;
+            }
+            let prev_inuse = next.size_field & PREV_INUSE != 0;
+            match chunk.state {
+                ChunkState::Free(Bin::Unsorted | Bin::Small(_) | Bin::Large(_)) if prev_inuse => {
+                    self.problems.push(format!(
+                        "free chunk at 0x{:x} but the next chunk has PREV_INUSE set",
+                        chunk.addr.0
+                    ))
+                }
+                ChunkState::InUse if !prev_inuse => self.problems.push(format!(
+                    "chunk at 0x{:x} isn't in a free list but the next chunk has PREV_INUSE cleared",
+                    chunk.addr.0
+                )),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Returns the address of main_arena using the symbols of a loaded libc, a libc debug
+/// file, or libc from the link map.
+pub fn find_main_arena(files: &ElfFiles, core: &ElfFile) -> Result<VirtualAddr, Box<dyn Error>> {
+    for exe in files.exes.iter() {
+        if let Some(symbol) = exe.find_symbol("main_arena")
+            && let Some(bias) = ElfFiles::load_bias(core, exe)
+        {
+            return Ok(VirtualAddr(symbol.value.wrapping_add(bias)));
+        }
+    }
+    for symbols in files.symbol_files.iter() {
+        if let Some(bias) = symbols.bias
+            && let Some(symbol) = symbols.file.find_symbol("main_arena")
+        {
+            return Ok(VirtualAddr(symbol.value.wrapping_add(bias)));
+        }
+    }
+    for (object, bias) in files.link_map_objects(core) {
+        if let Some(symbol) = object.find_symbol("main_arena") {
+            return Ok(VirtualAddr(symbol.value.wrapping_add(bias)));
+        }
+    }
+    Err("couldn't find main_arena: load libc with symbols (e.g. with add-symbol-file) or use --arena".into())
+}
+
+/// Returns the top chunk of main_arena, this is within the sbrk heap.
+pub fn find_main_heap_top(files: &ElfFiles, core: &ElfFile) -> Option<VirtualAddr> {
+    let addr = find_main_arena(files, core).ok()?;
+    let arena = Arena::new(core, addr, true).ok()?;
+    Some(VirtualAddr(arena.top))
+}
+
+/// Follows the fastbins and the doubly linked bins for an arena.
+fn find_free_lists(
+    core: &ElfFile,
+    arena: &Arena,
+    lists: &mut Vec<FreeList>,
+    states: &mut HashMap<VirtualAddr, Bin>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let ptr = core.reader.addr_size() as u64;
+
+    for (i, head) in arena.fastbins.iter().enumerate() {
+        let bin = Bin::Fast(i);
+        let mut list = FreeList {
+            arena: arena.addr,
+            bin,
+            chunks: Vec::new(),
+        };
+        let mut addr = *head;
+        while addr != 0 {
+            let chunk = VirtualAddr(addr);
+            if !check_list_chunk(core, &list, chunk, &mut problems) {
+                break;
+            }
+            list.chunks.push(chunk);
+            match next_link(core, VirtualAddr(addr + 2 * ptr), 2 * ptr) {
+                Ok(next) => addr = next,
+                Err(err) => {
+                    problems.push(format!("{bin} chunk at 0x{addr:x} {err}"));
+                    break;
+                }
+            }
+        }
+        add_list(list, lists, states);
+    }
+
+    for i in 1..NBINS {
+        let bin = match i {
+            1 => Bin::Unsorted,
+            2..NSMALLBINS => Bin::Small(i),
+            _ => Bin::Large(i),
+        };
+        let mut list = FreeList {
+            arena: arena.addr,
+            bin,
+            chunks: Vec::new(),
+        };
+        let head = arena.bin_at(i, ptr);
+        let mut prev = head;
+        let mut addr = arena.bins[i].0;
+        while addr != head {
+            let chunk = VirtualAddr(addr);
+            if !check_list_chunk(core, &list, chunk, &mut problems) {
+                break;
+            }
+            let fd = core.read_ptr(VirtualAddr(addr + 2 * ptr));
+            let bk = core.read_ptr(VirtualAddr(addr + 3 * ptr));
+            let (Ok(fd), Ok(bk)) = (fd, bk) else {
+                problems.push(format!("{bin} chunk at 0x{addr:x} isn't in the core"));
+                break;
+            };
+            if bk != prev {
+                problems.push(format!(
+                    "{bin} chunk at 0x{addr:x} has bk 0x{bk:x} but should be 0x{prev:x}"
+                ));
+            }
+            list.chunks.push(chunk);
+            prev = addr;
+            addr = fd;
+        }
+        add_list(list, lists, states);
+    }
+    problems
+}
+
+fn add_list(list: FreeList, lists: &mut Vec<FreeList>, states: &mut HashMap<VirtualAddr, Bin>) {
+    if !list.chunks.is_empty() {
+        for chunk in list.chunks.iter() {
+            states.insert(*chunk, list.bin);
+        }
+        lists.push(list);
+    }
+}
+
+/// Returns false if chunk shouldn't be added to list.
+fn check_list_chunk(
+    core: &ElfFile,
+    list: &FreeList,
+    chunk: VirtualAddr,
+    problems: &mut Vec<String>,
+) -> bool {
+    let ptr = core.reader.addr_size() as u64;
+    if core.read_ptr(VirtualAddr(chunk.0 + ptr)).is_err() {
+        problems.push(format!(
+            "{} has a link to 0x{:x} which isn't in the core",
+            list.bin, chunk.0
+        ));
+        false
+    } else if list.chunks.contains(&chunk) {
+        problems.push(format!("{} loops back to 0x{:x}", list.bin, chunk.0));
+        false
+    } else if list.chunks.len() >= MAX_LIST_LEN {
+        problems.push(format!("{} is too long", list.bin));
+        false
+    } else {
+        true
+    }
+}
+
+/// Checks that the size of a chunk in a free list matches its bin.
+fn check_size(core: &ElfFile, list: &FreeList, addr: VirtualAddr, problems: &mut Vec<String>) {
+    let ptr = core.reader.addr_size() as u64;
+    let Ok(size) = core.read_ptr(VirtualAddr(addr.0 + ptr)) else {
+        return;
+    };
+    let size = size & !SIZE_BITS;
+    let expected = match list.bin {
+        Bin::Tcache(i) | Bin::Fast(i) => Some(4 * ptr + i as u64 * 2 * ptr),
+        Bin::Small(i) => Some(i as u64 * 2 * ptr),
+        Bin::Unsorted | Bin::Large(_) => None,
+    };
+    if let Some(expected) = expected
+        && size != expected
+    {
+        problems.push(format!(
+            "{} chunk at 0x{:x} has size 0x{size:x} but should be 0x{expected:x}",
+            list.bin, addr.0
+        ));
+    }
+}
+
+/// Reads a singly linked list pointer. Starting with glibc 2.32 these are mangled
+/// using the address they are stored at ("safe-linking") so we try both ways.
+fn next_link(core: &ElfFile, field: VirtualAddr, align: u64) -> Result<u64, String> {
+    let raw = core
+        .read_ptr(field)
+        .map_err(|_| "has a next link that isn't in the core".to_string())?;
+    let revealed = (field.0 >> 12) ^ raw;
+    let valid = |addr: u64| addr.is_multiple_of(align) && core.read_ptr(VirtualAddr(addr)).is_ok();
+    if raw == 0 || revealed == 0 {
+        Ok(0)
+    } else if valid(revealed) {
+        Ok(revealed)
+    } else if valid(raw) {
+        Ok(raw)
+    } else {
+        Err(format!("has a corrupted next link (0x{raw:x})"))
+    }
+}
+
+/// Walks the chunks from the start of the arena's heap to its top chunk.
+fn walk_chunks(
+    core: &ElfFile,
+    arena: &Arena,
+    states: &HashMap<VirtualAddr, Bin>,
+    chunks: &mut Vec<Chunk>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let ptr = core.reader.addr_size() as u64;
+    let align = 2 * ptr;
+
+    // The main arena uses sbrk so its heap is a segment of its own. Other arenas are
+    // allocated at the start of a heap created with mmap.
+    let start = if arena.main {
+        match core.find_load_segment(VirtualAddr(arena.top)) {
+            Some(load) => load.vbytes.start.0.next_multiple_of(align),
+            None => {
+                problems.push(format!(
+                    "top chunk for arena 0x{:x} (0x{:x}) isn't in the core",
+                    arena.addr.0, arena.top
+                ));
+                return problems;
+            }
+        }
+    } else {
+        arena.end.0.next_multiple_of(align)
+    };
+
+    let mut addr = start;
+    let mut seen = HashSet::new();
+    loop {
+        let Ok(size_field) = core.read_ptr(VirtualAddr(addr + ptr)) else {
+            problems.push(format!("chunk at 0x{addr:x} isn't in the core"));
+            break;
+        };
+        let chunk = Chunk {
+            addr: VirtualAddr(addr),
+            size_field,
+            state: if addr == arena.top {
+                ChunkState::Top
+            } else {
+                states
+                    .get(&VirtualAddr(addr))
+                    .map_or(ChunkState::InUse, |b| ChunkState::Free(*b))
+            },
+            arena: arena.addr,
+        };
+        let size = chunk.size();
+        if addr == arena.top {
+            chunks.push(chunk);
+            break;
+        }
+        if size < 4 * ptr
+            || !size.is_multiple_of(align)
+            || addr.checked_add(size).is_none_or(|end| end > arena.top)
+            || !seen.insert(addr)
+        {
+            problems.push(format!(
+                "chunk at 0x{addr:x} has a corrupted size (0x{size_field:x})"
+            ));
+            break;
+        }
+        if (size_field & NON_MAIN_ARENA != 0) == arena.main {
+            problems.push(format!(
+                "chunk at 0x{addr:x} has the wrong NON_MAIN_ARENA bit"
+            ));
+        }
+        chunks.push(chunk);
+        addr += size;
+    }
+    problems
+}
@@ -0,0 +1,4 @@
+//! Support for inspecting the malloc heap within a core.
+pub mod glibc;
+
+pub use glibc::*;
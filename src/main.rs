@@ -1,10 +1,12 @@
+mod arch;
 mod commands;
 mod debug;
 mod elf;
+mod heap;
 mod repl;
 mod utils;
 use crate::elf::ElfFiles;
-use crate::repl::ElfAction;
+use crate::repl::{ElfAction, HeapAction, ProcessAction};
 use clap::Parser;
 use clap_repl::ClapEditor;
 use clap_repl::reedline::{
@@ -22,18 +24,19 @@ use std::{io, process};
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// paths to a core and/or exe file
+    /// paths to core and/or exe files
     paths: Vec<PathBuf>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     utils::generate_style_file();
+    utils::install_interrupt_handler();
 
     let cli = Cli::parse();
-    if cli.paths.is_empty() || cli.paths.len() > 2 {
-        return Err("expected a path to a core and/or exe file".into());
+    let mut files = ElfFiles::new(cli.paths)?;
+    if files.is_empty() {
+        println!("No files are loaded: use the file and core commands to load them.");
     }
-    let files = ElfFiles::new(cli.paths)?;
 
     // left prompt                    before what the user types
     // highlighter                    this is for what the user types
@@ -51,11 +54,17 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     use repl::MainCommand::*;
     rl.repl(|repl: Repl| match repl.command {
-        Bt => commands::backtrace(io::stdout(), &files),
+        AddSymbolFile(args) => commands::add_symbol_file(io::stdout(), &mut files, &args),
+        Analyze(args) => commands::analyze(io::stdout(), &files, &args),
+        Bt(args) if args.scan => commands::backtrace_scan(io::stdout(), &files, &args),
+        Bt(_) => commands::backtrace(io::stdout(), &files),
+        Compare(args) => commands::compare(io::stdout(), &files, &args),
+        Core(args) => commands::core(io::stdout(), &mut files, &args),
         Elf(info) => match info.action {
             ElfAction::Abbreviations(args) => {
                 commands::elf_abbreviations(io::stdout(), &files, &args)
             }
+            ElfAction::Dynamic(args) => commands::elf_dynamic(io::stdout(), &files, &args),
             ElfAction::Header(args) => commands::elf_header(io::stdout(), &files, &args),
             ElfAction::Line(args) => commands::elf_line(io::stdout(), &files, &args),
             ElfAction::Loads(args) => commands::elf_loads(io::stdout(), &files, &args),
@@ -67,16 +76,37 @@ fn main() -> Result<(), Box<dyn Error>> {
             ElfAction::Symbols(args) => commands::elf_symbols(io::stdout(), &files, &args),
             ElfAction::Types(args) => commands::elf_types(io::stdout(), &files, &args),
         },
+        File(args) => commands::file(io::stdout(), &mut files, &args),
         Find(args) => commands::find(io::stdout(), &files, &args),
+        Heap(heap) => match heap.action {
+            HeapAction::Arenas(args) => commands::heap_arenas(io::stdout(), &files, &args),
+            HeapAction::Bins(args) => commands::heap_bins(io::stdout(), &files, &args),
+            HeapAction::Chunk(args) => commands::heap_chunk(io::stdout(), &files, &args),
+            HeapAction::Chunks(args) => commands::heap_chunks(io::stdout(), &files, &args),
+        },
         Info(info) => match info.action {
+            InfoAction::Address(args) => commands::info_address(io::stdout(), &files, &args),
             InfoAction::Line(args) => commands::info_line(io::stdout(), &files, &args),
             InfoAction::Mapped(args) => commands::info_mapped(io::stdout(), &files, &args),
-            InfoAction::Process(args) => commands::info_process(io::stdout(), &files, &args),
+            InfoAction::Process(process) => match process.action {
+                Some(ProcessAction::Mappings(args)) => {
+                    commands::info_proc_mappings(io::stdout(), &files, &args)
+                }
+                None => commands::info_process(io::stdout(), &files, &process.args),
+            },
             InfoAction::Registers(args) => commands::info_registers(io::stdout(), &files, &args),
+            InfoAction::SharedLibrary(args) => {
+                commands::info_shared_library(io::stdout(), &files, &args)
+            }
             InfoAction::Signals(args) => commands::info_signals(io::stdout(), &files, &args),
+            InfoAction::Symbol(args) => commands::info_symbol(io::stdout(), &files, &args),
+            InfoAction::Threads(args) => commands::info_threads(io::stdout(), &files, &args),
+            InfoAction::Tls(args) => commands::info_tls(io::stdout(), &files, &args),
         },
         Hexdump(args) => commands::hexdump(io::stdout(), &files, &args),
+        Print(args) => commands::print(io::stdout(), &files, &args),
         Quit => process::exit(0),
+        Unload(args) => commands::unload(io::stdout(), &mut files, &args),
     });
     Ok(())
 }
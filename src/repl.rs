@@ -1,7 +1,9 @@
 //! Definitions for the commands that are used interactively, e.g.
 //! `bt` and `info registers`.
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use regex::Regex;
 use std::fmt;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)] // TODO about?
@@ -13,14 +15,32 @@ pub struct Repl {
 
 #[derive(Subcommand)]
 pub enum MainCommand {
+    /// Load a file with debug info, e.g. a separate .debug file for a stripped exe
+    AddSymbolFile(AddSymbolFileArgs),
+
+    /// Explain why the process crashed, e.g. a NULL dereference or stack overflow
+    Analyze(AnalyzeArgs),
+
     /// Show backtrace for the current thread
-    Bt,
+    Bt(BacktraceArgs),
+
+    /// Compare signals, backtraces, and globals across the loaded cores
+    Compare(CompareArgs),
+
+    /// List the loaded cores, select a core, or load a new core
+    Core(CoreArgs),
 
     /// Show low level information about the core and exe files
     Elf(ElfCommand),
 
+    /// Load an exe
+    File(FileArgs),
+
     /// Search memory for a bit pattern
-    Find(FindArgs),
+    Find(Box<FindArgs>),
+
+    /// Inspect the glibc malloc heap
+    Heap(HeapCommand),
 
     /// Show higher level information about the cored process
     Info(InfoCommand),
@@ -28,8 +48,14 @@ pub enum MainCommand {
     /// Print memory range as hex and ascii
     Hexdump(HexdumpArgs),
 
+    /// Print the value of a global or thread local variable, e.g. errno
+    Print(PrintArgs),
+
     /// Exit udb
     Quit,
+
+    /// Unload a file or, if no path is given, all files
+    Unload(UnloadArgs),
 }
 
 #[derive(Args)]
@@ -38,6 +64,21 @@ pub struct ElfCommand {
     pub action: ElfAction,
 }
 
+#[derive(Args)]
+pub struct HeapCommand {
+    #[clap(subcommand)]
+    pub action: HeapAction,
+}
+
+#[derive(Args)]
+pub struct ProcessCommand {
+    #[clap(subcommand)]
+    pub action: Option<ProcessAction>,
+
+    #[clap(flatten)]
+    pub args: ExplainArgs,
+}
+
 #[derive(Args)]
 pub struct InfoCommand {
     #[clap(subcommand)]
@@ -49,6 +90,9 @@ pub enum ElfAction {
     /// Show ELF .debug_abbrev section
     Abbreviations(EntriesArgs),
 
+    /// Show the dynamic section, e.g. needed libraries
+    Dynamic(TableArgs),
+
     /// Show ELF header
     Header(ExplainArgs),
 
@@ -80,8 +124,26 @@ pub enum ElfAction {
     Types(EntriesArgs),
 }
 
+#[derive(Subcommand)]
+pub enum HeapAction {
+    /// Show the malloc arenas
+    Arenas(HeapArgs),
+
+    /// Show the tcache, fastbins, and other free lists
+    Bins(HeapArgs),
+
+    /// Show the chunk an address is within
+    Chunk(HeapChunkArgs),
+
+    /// Show all the chunks within the arena heaps
+    Chunks(HeapArgs),
+}
+
 #[derive(Subcommand)]
 pub enum InfoAction {
+    /// Find the runtime address of symbols, e.g. "info address 'main_*'"
+    Address(AddressArgs),
+
     /// Print file and line number for a virtual address
     Line(LineArgs),
 
@@ -89,13 +151,33 @@ pub enum InfoAction {
     Mapped(TableArgs),
 
     /// Show information the process associated with the core file
-    Process(ExplainArgs),
+    #[command(alias = "proc")]
+    Process(ProcessCommand),
 
     /// Show general purpose registers
     Registers(RegistersArgs),
 
+    /// Show the shared libraries the dynamic linker loaded
+    #[command(alias = "shared")]
+    SharedLibrary(TableArgs),
+
     /// Show information about signals
     Signals(TableArgs),
+
+    /// Print the symbol a virtual address is within
+    Symbol(LineArgs),
+
+    /// Show the threads along with the syscalls they were in
+    Threads(TableArgs),
+
+    /// Show where the thread local storage blocks are for a thread
+    Tls(TlsArgs),
+}
+
+#[derive(Subcommand)]
+pub enum ProcessAction {
+    /// Show the memory regions of the process
+    Mappings(TableArgs),
 }
 
 #[derive(Args)]
@@ -109,19 +191,25 @@ pub struct ExplainArgs {
     pub explain: bool,
 }
 
-// TODO should be able to search for other stuff like ints (need to account for endian)
-// TODO provide a way to restrict search area?
-#[derive(Args)]
+#[derive(Args, Default)]
 pub struct FindArgs {
-    /// Default is to search virtual memory in the core file. When this is enabled all
-    /// the bytes in both the exe and the core are searched.
-    #[arg(long)]
+    /// Default is to search the cored process's memory (including pages read from mapped
+    /// files). When this is enabled all the bytes in both the exe and the core are searched.
+    #[arg(long, conflicts_with_all = ["range", "segment", "writable", "executable", "refs_to"])]
     pub all: bool,
 
     /// Search for an UTF-8 string e.g. "the brown fox"
     #[arg(long, group = "filter")]
     pub string: Option<String>,
 
+    /// Search for a UTF-16 string (using the core's byte order)
+    #[arg(long, group = "filter")]
+    pub utf16: Option<String>,
+
+    /// Ignore ASCII case when searching for strings
+    #[arg(short, long)]
+    pub ignore_case: bool,
+
     /// Optionally hexdump count bytes for each address found
     #[arg(short, long, default_value_t = 0)]
     pub count: usize,
@@ -130,11 +218,128 @@ pub struct FindArgs {
     #[arg(long, group = "filter")]
     pub hex: Option<String>,
 
+    /// Search for a 16-bit unsigned integer
+    #[arg(long, group = "filter", value_parser = parse_u64_expr)]
+    pub u16: Option<u64>,
+
+    /// Search for a 32-bit unsigned integer
+    #[arg(long, group = "filter", value_parser = parse_u64_expr)]
+    pub u32: Option<u64>,
+
+    /// Search for a 64-bit unsigned integer
+    #[arg(long, group = "filter", value_parser = parse_u64_expr)]
+    pub u64: Option<u64>,
+
+    /// Search for a 64-bit signed integer
+    #[arg(long, group = "filter", allow_negative_numbers = true)]
+    pub i64: Option<i64>,
+
+    /// Search for a pointer sized value, e.g. an address
+    #[arg(long, group = "filter", value_parser = parse_u64_expr)]
+    pub ptr: Option<u64>,
+
+    /// Search for a 32-bit float
+    #[arg(long, group = "filter", allow_negative_numbers = true)]
+    pub float: Option<f32>,
+
+    /// Search for a 64-bit float
+    #[arg(long, group = "filter", allow_negative_numbers = true)]
+    pub double: Option<f64>,
+
+    /// Search writable memory and registers for pointers to an address or into a
+    /// start..end range
+    #[arg(long, group = "filter", value_parser = parse_addr_or_range)]
+    pub refs_to: Option<(u64, u64)>,
+
+    /// Floats match if they are within this amount of the value
+    #[arg(long, default_value_t = 0.0)]
+    pub tolerance: f64,
+
+    /// Only search addresses within start..end
+    #[arg(long, value_parser = parse_range)]
+    pub range: Option<(u64, u64)>,
+
+    /// Only search the load segment containing this address
+    #[arg(long, value_parser = parse_u64_expr)]
+    pub segment: Option<u64>,
+
+    /// Only search writable load segments
+    #[arg(long)]
+    pub writable: bool,
+
+    /// Only search executable load segments
+    #[arg(long)]
+    pub executable: bool,
+
     /// Max number of results to report, 0 for unlimited
     #[arg(short, long, default_value_t = 10, requires = "filter")]
     pub max_results: usize,
 }
 
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Explain the cause and fields
+    #[arg(short, long)]
+    pub explain: bool,
+}
+
+#[derive(Args)]
+pub struct BacktraceArgs {
+    /// Search the stack for return addresses instead of following frame pointers
+    #[arg(long)]
+    pub scan: bool,
+
+    /// Explain the --scan columns
+    #[arg(short, long)]
+    pub explain: bool,
+
+    /// Add column headers to the --scan table
+    #[arg(short, long)]
+    pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Explain columns, fields, etc.
+    #[arg(short, long)]
+    pub explain: bool,
+
+    /// Name of a global variable to compare, can be used multiple times
+    #[arg(short, long = "global", name = "NAME")]
+    pub globals: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct CoreArgs {
+    /// Index of a loaded core to select or the path of a core to load
+    pub target: Option<String>,
+}
+
+#[derive(Args)]
+pub struct AddSymbolFileArgs {
+    /// Path to an ELF file with debug info
+    pub path: PathBuf,
+
+    /// Address the .text section was loaded at, defaults to the exe's
+    #[arg(value_parser = parse_u64_expr)]
+    pub addr: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct FileArgs {
+    /// Path to an exe
+    pub path: PathBuf,
+}
+
+#[derive(Args)]
+pub struct UnloadArgs {
+    /// Path of the file to unload
+    pub path: Option<PathBuf>,
+}
+
 #[derive(Args)]
 pub struct EntriesArgs {
     /// Maximum number of entries to print, 0 for unlimited.
@@ -164,6 +369,45 @@ pub struct TableArgs {
     /// Add column headers
     #[arg(short, long)]
     pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+/// Row filtering and ordering options shared by the table commands.
+#[derive(Args, Clone, Default)]
+pub struct FilterArgs {
+    /// Only show rows whose name matches this regex
+    #[arg(long = "name", id = "filter_name", value_parser = parse_regex)]
+    pub name: Option<Regex>,
+
+    /// Only show rows with this type, e.g. func or object
+    #[arg(long = "type")]
+    pub stype: Option<String>,
+
+    /// Only show rows with this binding, e.g. global or weak
+    #[arg(long)]
+    pub binding: Option<String>,
+
+    /// Only show rows in this section, e.g. .text
+    #[arg(long)]
+    pub section: Option<String>,
+
+    /// Only show rows whose address is within start..end
+    #[arg(long, value_parser = parse_range)]
+    pub range: Option<(u64, u64)>,
+
+    /// Sort the rows using this column
+    #[arg(long)]
+    pub sort: Option<String>,
+
+    /// Reverse the order of the rows
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Maximum number of rows to print
+    #[arg(long)]
+    pub limit: Option<usize>,
 }
 
 #[derive(Args)]
@@ -183,6 +427,9 @@ pub struct RegistersArgs {
     /// Add column headers
     #[arg(short, long)]
     pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
 }
 
 #[derive(Args)]
@@ -192,6 +439,84 @@ pub struct LineArgs {
     pub addr: u64,
 }
 
+#[derive(Args)]
+pub struct AddressArgs {
+    /// Symbol name, may use * and ? wildcards
+    pub name: String,
+
+    /// Treat the name as a regular expression
+    #[arg(short, long)]
+    pub regex: bool,
+
+    /// Explain columns, fields, etc.
+    #[arg(short, long)]
+    pub explain: bool,
+
+    /// Add column headers
+    #[arg(short, long)]
+    pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Args)]
+pub struct PrintArgs {
+    /// Name of the variable
+    pub name: String,
+
+    /// Thread to use for thread local variables, defaults to the thread that crashed
+    #[arg(long)]
+    pub tid: Option<i32>,
+}
+
+#[derive(Args)]
+pub struct TlsArgs {
+    /// Thread to use, defaults to the thread that crashed
+    #[arg(long)]
+    pub tid: Option<i32>,
+
+    /// Explain columns, fields, etc.
+    #[arg(short, long)]
+    pub explain: bool,
+
+    /// Add column headers
+    #[arg(short, long)]
+    pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Args)]
+pub struct HeapArgs {
+    /// Address of main_arena, defaults to the main_arena symbol in libc
+    #[arg(long, value_parser = parse_u64_expr)]
+    pub arena: Option<u64>,
+
+    /// Explain columns, fields, etc.
+    #[arg(short, long)]
+    pub explain: bool,
+
+    /// Add column headers
+    #[arg(short, long)]
+    pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Args)]
+pub struct HeapChunkArgs {
+    /// Address of main_arena, defaults to the main_arena symbol in libc
+    #[arg(long, value_parser = parse_u64_expr)]
+    pub arena: Option<u64>,
+
+    /// An address within the chunk
+    #[arg(value_parser = parse_u64_expr)]
+    pub addr: u64,
+}
+
 #[derive(Args)]
 pub struct HexdumpArgs {
     /// Dump the exe instead of the core file
@@ -212,6 +537,11 @@ pub struct HexdumpArgs {
     #[arg(long)]
     pub offset: bool,
 
+    /// Group bytes into integers of this size using the file's byte order
+    #[arg(short, long, default_value_t = 1)]
+    #[arg(value_parser = parse_width)]
+    pub width: usize,
+
     /// Defaults to an address
     #[arg(value_parser = parse_u64_expr)]
     pub value: u64,
@@ -263,6 +593,38 @@ fn parse_u64_expr(s: &str) -> Result<u64, String> {
     }
 }
 
+fn parse_range(s: &str) -> Result<(u64, u64), String> {
+    let Some((start, end)) = s.split_once("..") else {
+        return Err(format!("`{s}` should be of the form start..end"));
+    };
+    let (start, end) = (parse_u64_expr(start)?, parse_u64_expr(end)?);
+    if start < end {
+        Ok((start, end))
+    } else {
+        Err(format!("`{s}` is empty"))
+    }
+}
+
+fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+fn parse_addr_or_range(s: &str) -> Result<(u64, u64), String> {
+    if s.contains("..") {
+        parse_range(s)
+    } else {
+        let addr = parse_u64_expr(s)?;
+        Ok((addr, addr.saturating_add(1)))
+    }
+}
+
+fn parse_width(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(width @ (1 | 2 | 4 | 8)) => Ok(width),
+        _ => Err(format!("`{s}` should be 1, 2, 4, or 8")),
+    }
+}
+
 // use the open crate to launch off-line docs?
 //    maybe a --doc option?
 //    would this also be useful for visualization?
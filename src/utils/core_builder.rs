@@ -0,0 +1,547 @@
+//! Builds small core files for unit tests. This lets us test architectures, word
+//! sizes, and byte orders that we don't have real cores for.
+use crate::elf::{ElfFiles, FileBytes, Offset, Reader, Stream};
+use std::path::PathBuf;
+
+/// Appends integers using the target's word size and byte order.
+pub struct Encoder {
+    pub sixty_four_bit: bool,
+    pub little_endian: bool,
+    pub bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new(sixty_four_bit: bool, little_endian: bool) -> Self {
+        Encoder {
+            sixty_four_bit,
+            little_endian,
+            bytes: Vec::new(),
+        }
+    }
+
+    pub fn uint(&mut self, value: u64, size: usize) -> &mut Self {
+        for i in 0..size {
+            let shift = if self.little_endian { i } else { size - 1 - i };
+            self.bytes.push((value >> (8 * shift)) as u8);
+        }
+        self
+    }
+
+    pub fn byte(&mut self, value: u8) -> &mut Self {
+        self.uint(value as u64, 1)
+    }
+
+    pub fn half(&mut self, value: u16) -> &mut Self {
+        self.uint(value as u64, 2)
+    }
+
+    pub fn word(&mut self, value: u32) -> &mut Self {
+        self.uint(value as u64, 4)
+    }
+
+    pub fn xword(&mut self, value: u64) -> &mut Self {
+        self.uint(value, 8)
+    }
+
+    /// Four or eight bytes depending on the word size, e.g. for addresses and longs.
+    pub fn ulong(&mut self, value: u64) -> &mut Self {
+        let size = if self.sixty_four_bit { 8 } else { 4 };
+        self.uint(value, size)
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn align(&mut self, alignment: usize) -> &mut Self {
+        while !self.bytes.len().is_multiple_of(alignment) {
+            self.bytes.push(0);
+        }
+        self
+    }
+
+    /// Returns a stream positioned at the encoded bytes, e.g. for testing the parsers
+    /// for individual sections. The bytes follow a minimal ELF header.
+    pub fn stream(&self) -> Stream {
+        let mut data = vec![0x7f, b'E', b'L', b'F'];
+        data.push(if self.sixty_four_bit { 2 } else { 1 });
+        data.push(if self.little_endian { 1 } else { 2 });
+        data.push(1);
+        data.resize(0x10, 0);
+        data.extend(if self.little_endian { [4, 0] } else { [0, 4] }); // ET_CORE
+        data.resize(0x40, 0);
+        data.extend(&self.bytes);
+
+        let reader = Reader::new(FileBytes::from_vec(data)).unwrap();
+        Stream::new(&reader, Offset(0x40))
+    }
+}
+
+struct Load {
+    vaddr: u64,
+    bytes: Vec<u8>,
+    mem_size: usize,
+    flags: u32,
+}
+
+struct Note {
+    name: String,
+    ntype: u32,
+    desc: Vec<u8>,
+}
+
+pub struct CoreBuilder {
+    machine: u16,
+    sixty_four_bit: bool,
+    little_endian: bool,
+    notes: Vec<Note>,
+    loads: Vec<Load>,
+}
+
+impl CoreBuilder {
+    pub fn new(machine: u16, sixty_four_bit: bool, little_endian: bool) -> Self {
+        CoreBuilder {
+            machine,
+            sixty_four_bit,
+            little_endian,
+            notes: Vec::new(),
+            loads: Vec::new(),
+        }
+    }
+
+    pub fn encoder(&self) -> Encoder {
+        Encoder::new(self.sixty_four_bit, self.little_endian)
+    }
+
+    pub fn note(&mut self, name: &str, ntype: u32, desc: Vec<u8>) -> &mut Self {
+        self.notes.push(Note {
+            name: name.to_string(),
+            ntype,
+            desc,
+        });
+        self
+    }
+
+    /// Adds an elf_prstatus note.
+    pub fn prstatus(&mut self, signal: i32, code: i32, pid: i32, registers: &[u64]) -> &mut Self {
+        let mut e = self.encoder();
+        e.word(signal as u32).word(code as u32).word(0); // elf_siginfo
+        e.half(signal as u16).half(0); // pr_cursig
+        e.ulong(0).ulong(0); // pr_sigpend, pr_sighold
+        e.word(pid as u32).word(1).word(pid as u32).word(pid as u32);
+        for _ in 0..8 {
+            e.ulong(0); // pr_utime, pr_stime, pr_cutime, pr_cstime
+        }
+        for r in registers {
+            e.ulong(*r);
+        }
+        e.word(0); // pr_fpvalid
+        e.align(if self.sixty_four_bit { 8 } else { 4 });
+        let desc = e.bytes;
+        self.note("CORE", 1, desc)
+    }
+
+    pub fn load(&mut self, vaddr: u64, bytes: Vec<u8>, flags: u32) -> &mut Self {
+        let mem_size = bytes.len();
+        self.load_partial(vaddr, bytes, mem_size, flags)
+    }
+
+    /// Adds a load segment where only the first bytes.len() bytes are in the core.
+    pub fn load_partial(
+        &mut self,
+        vaddr: u64,
+        bytes: Vec<u8>,
+        mem_size: usize,
+        flags: u32,
+    ) -> &mut Self {
+        self.loads.push(Load {
+            vaddr,
+            bytes,
+            mem_size,
+            flags,
+        });
+        self
+    }
+
+    /// Adds an NT_FILE note, entries are start, end, page offset, and file name.
+    pub fn mapped_files(&mut self, entries: &[(u64, u64, u64, &str)]) -> &mut Self {
+        let mut e = self.encoder();
+        e.ulong(entries.len() as u64).ulong(0x1000);
+        for (start, end, offset, _) in entries {
+            e.ulong(*start).ulong(*end).ulong(*offset);
+        }
+        for (_, _, _, name) in entries {
+            e.raw(name.as_bytes()).byte(0);
+        }
+        let desc = e.bytes;
+        self.note("CORE", 0x46494c45, desc)
+    }
+
+    /// Adds an NT_AUXV note with the key value pairs.
+    pub fn auxv(&mut self, entries: &[(u64, u64)]) -> &mut Self {
+        let mut e = self.encoder();
+        for (key, value) in entries {
+            e.ulong(*key).ulong(*value);
+        }
+        e.ulong(0).ulong(0); // AT_NULL
+        let desc = e.bytes;
+        self.note("CORE", 6, desc)
+    }
+
+    /// Writes the core to a temporary file and returns its path.
+    pub fn write(&self, name: &str) -> PathBuf {
+        let (header_size, ph_size) = if self.sixty_four_bit {
+            (64, 56)
+        } else {
+            (52, 32)
+        };
+        let num_ph = 1 + self.loads.len();
+
+        let mut notes = self.encoder();
+        for note in self.notes.iter() {
+            notes.word(note.name.len() as u32 + 1);
+            notes.word(note.desc.len() as u32);
+            notes.word(note.ntype);
+            notes.raw(note.name.as_bytes()).byte(0).align(4);
+            notes.raw(&note.desc).align(4);
+        }
+
+        let notes_offset = header_size + num_ph * ph_size;
+        let mut load_offset = notes_offset + notes.bytes.len();
+
+        let mut e = self.encoder();
+        e.raw(&[0x7f, b'E', b'L', b'F']);
+        e.byte(if self.sixty_four_bit { 2 } else { 1 });
+        e.byte(if self.little_endian { 1 } else { 2 });
+        e.byte(1); // EI_VERSION
+        e.byte(0); // EI_OSABI
+        e.align(16);
+        e.half(4); // ET_CORE
+        e.half(self.machine);
+        e.word(1); // e_version
+        e.ulong(0); // e_entry
+        e.ulong(header_size as u64); // e_phoff
+        e.ulong(0); // e_shoff
+        e.word(0); // e_flags
+        e.half(header_size as u16);
+        e.half(ph_size as u16);
+        e.half(num_ph as u16);
+        e.half(0); // e_shentsize
+        e.half(0); // e_shnum
+        e.half(0); // e_shstrndx
+
+        let size = notes.bytes.len();
+        self.program_header(&mut e, 4, 0, notes_offset, 0, size, size);
+        for load in self.loads.iter() {
+            let size = load.bytes.len();
+            self.program_header(
+                &mut e,
+                1,
+                load.flags,
+                load_offset,
+                load.vaddr,
+                size,
+                load.mem_size,
+            );
+            load_offset += size;
+        }
+
+        e.raw(&notes.bytes);
+        for load in self.loads.iter() {
+            e.raw(&load.bytes);
+        }
+
+        let path = std::env::temp_dir().join(format!("udb-{}-{name}.core", std::process::id()));
+        std::fs::write(&path, &e.bytes).unwrap();
+        path
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn program_header(
+        &self,
+        e: &mut Encoder,
+        ptype: u32,
+        flags: u32,
+        offset: usize,
+        vaddr: u64,
+        file_size: usize,
+        mem_size: usize,
+    ) {
+        if self.sixty_four_bit {
+            e.word(ptype).word(flags);
+            e.xword(offset as u64).xword(vaddr).xword(0);
+            e.xword(file_size as u64).xword(mem_size as u64).xword(1);
+        } else {
+            e.word(ptype).word(offset as u32).word(vaddr as u32).word(0);
+            e.word(file_size as u32)
+                .word(mem_size as u32)
+                .word(flags)
+                .word(1);
+        }
+    }
+}
+
+/// A minimal core for one architecture: the registers for a single thread plus a stack
+/// for the unwinder to walk.
+pub struct ArchCore {
+    pub name: &'static str,
+    pub machine: u16,
+    pub sixty_four_bit: bool,
+    pub little_endian: bool,
+    pub signal: i32,
+    pub code: i32,
+    pub pid: i32,
+    pub registers: Vec<u64>,
+
+    /// Extra notes, the descriptions are encoded using the word size.
+    pub notes: Vec<(&'static str, u32, Vec<u64>)>,
+
+    /// The stack is encoded using the word size and loaded at stack_addr.
+    pub stack_addr: u64,
+    pub stack: Vec<u64>,
+}
+
+impl ArchCore {
+    /// Returns count registers with distinct values, except for the ones in set.
+    pub fn registers(count: u64, step: u64, set: &[(usize, u64)]) -> Vec<u64> {
+        let mut registers: Vec<u64> = (0..count).map(|i| i * step).collect();
+        for &(index, value) in set {
+            registers[index] = value;
+        }
+        registers
+    }
+
+    pub fn files(&self) -> ElfFiles {
+        let mut builder = CoreBuilder::new(self.machine, self.sixty_four_bit, self.little_endian);
+        builder.prstatus(self.signal, self.code, self.pid, &self.registers);
+        for (name, ntype, words) in self.notes.iter() {
+            let mut e = builder.encoder();
+            for &word in words {
+                e.ulong(word);
+            }
+            builder.note(name, *ntype, e.bytes);
+        }
+
+        let mut e = builder.encoder();
+        for &word in self.stack.iter() {
+            e.ulong(word);
+        }
+        builder.load(self.stack_addr, e.bytes, 6);
+
+        let path = builder.write(self.name);
+        ElfFiles::new(vec![path]).unwrap()
+    }
+}
+
+/// Builds a 64-bit little endian position independent object with a PT_TLS segment,
+/// thread local symbols, functions, and data symbols. The whole file is one load
+/// segment at address zero.
+pub struct ObjectBuilder {
+    tls_size: u64,
+    tls_symbols: Vec<(String, u64, u64)>,
+    functions: Vec<(String, u64)>,
+    data_symbols: Vec<(String, u64)>,
+    r_debug: Option<u64>,
+}
+
+impl ObjectBuilder {
+    pub fn new(tls_size: u64) -> Self {
+        ObjectBuilder {
+            tls_size,
+            tls_symbols: Vec::new(),
+            functions: Vec::new(),
+            data_symbols: Vec::new(),
+            r_debug: None,
+        }
+    }
+
+    /// Adds a thread local symbol at offset within the TLS block.
+    pub fn tls_symbol(&mut self, name: &str, offset: u64, size: u64) -> &mut Self {
+        self.tls_symbols.push((name.to_string(), offset, size));
+        self
+    }
+
+    /// Adds a function to the .text section, the code is all zeros.
+    pub fn function(&mut self, name: &str, size: u64) -> &mut Self {
+        self.functions.push((name.to_string(), size));
+        self
+    }
+
+    /// Adds a zeroed variable to the .data section.
+    pub fn data_symbol(&mut self, name: &str, size: u64) -> &mut Self {
+        self.data_symbols.push((name.to_string(), size));
+        self
+    }
+
+    /// Adds a dynamic segment with DT_DEBUG set to r_debug, as the dynamic linker does
+    /// for the exe.
+    pub fn debug(&mut self, r_debug: u64) -> &mut Self {
+        self.r_debug = Some(r_debug);
+        self
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        const HEADER_SIZE: u64 = 64;
+        const PH_SIZE: u64 = 56;
+        const SH_SIZE: u64 = 64;
+        let num_ph = if self.r_debug.is_some() { 3 } else { 2 };
+
+        let dynamic_offset = HEADER_SIZE + num_ph * PH_SIZE;
+        let dynamic_size = if self.r_debug.is_some() { 32 } else { 0 };
+        let text_offset = dynamic_offset + dynamic_size;
+        let text_size: u64 = self.functions.iter().map(|(_, size)| size).sum();
+        let data_offset = text_offset + text_size;
+        let data_size: u64 = self.data_symbols.iter().map(|(_, size)| size).sum();
+
+        // Symbols are name, info, section index, value, and size.
+        let mut symbols = vec![(String::new(), 0, 0, 0, 0)];
+        for (name, offset, size) in self.tls_symbols.iter() {
+            symbols.push((name.clone(), (1 << 4) | 6, 1, *offset, *size)); // STT_TLS
+        }
+        let mut addr = text_offset;
+        for (name, size) in self.functions.iter() {
+            symbols.push((name.clone(), (1 << 4) | 2, 2, addr, *size)); // STT_FUNC
+            addr += size;
+        }
+        for (name, size) in self.data_symbols.iter() {
+            symbols.push((name.clone(), (1 << 4) | 1, 3, addr, *size)); // STT_OBJECT
+            addr += size;
+        }
+
+        let mut strings = Encoder::new(true, true);
+        let mut table = Encoder::new(true, true);
+        strings.byte(0);
+        for (name, info, index, value, size) in symbols {
+            if name.is_empty() {
+                table.word(0);
+            } else {
+                table.word(strings.bytes.len() as u32);
+                strings.raw(name.as_bytes()).byte(0);
+            }
+            table
+                .byte(info)
+                .byte(0)
+                .half(index)
+                .xword(value)
+                .xword(size); // STB_GLOBAL
+        }
+        let section_names = b"\0.tbss\0.text\0.data\0.symtab\0.strtab\0.shstrtab\0";
+
+        let symbols_offset = (data_offset + data_size).next_multiple_of(8);
+        let strings_offset = symbols_offset + table.bytes.len() as u64;
+        let names_offset = strings_offset + strings.bytes.len() as u64;
+        let sections_offset = (names_offset + section_names.len() as u64).next_multiple_of(8);
+        let size = sections_offset + 7 * SH_SIZE;
+
+        let mut e = Encoder::new(true, true);
+        e.raw(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]).align(16);
+        e.half(3).half(0x3E).word(1); // ET_DYN, x86_64, e_version
+        e.xword(0).xword(HEADER_SIZE).xword(sections_offset); // e_entry, e_phoff, e_shoff
+        e.word(0).half(HEADER_SIZE as u16); // e_flags, e_ehsize
+        e.half(PH_SIZE as u16).half(num_ph as u16);
+        e.half(SH_SIZE as u16).half(7).half(6); // e_shstrndx
+
+        e.word(1).word(7).xword(0).xword(0).xword(0); // PT_LOAD
+        e.xword(size).xword(size).xword(0x1000);
+        e.word(7).word(4).xword(size).xword(size).xword(0); // PT_TLS, all of it is .tbss
+        e.xword(0).xword(self.tls_size).xword(8);
+        if let Some(r_debug) = self.r_debug {
+            e.word(2).word(6); // PT_DYNAMIC
+            e.xword(dynamic_offset).xword(dynamic_offset).xword(0);
+            e.xword(dynamic_size).xword(dynamic_size).xword(8);
+            e.xword(21).xword(r_debug).xword(0).xword(0); // DT_DEBUG, DT_NULL
+        }
+        e.raw(&vec![0; (text_size + data_size) as usize]).align(8);
+        e.raw(&table.bytes).raw(&strings.bytes).raw(section_names);
+        e.align(8);
+
+        // Name, type, flags, offset, size, link, info, and entry size. The types are
+        // NOBITS, PROGBITS, SYMTAB, and STRTAB and the flags are write, alloc, exec, and
+        // TLS.
+        let symbols_size = table.bytes.len() as u64;
+        let strings_size = strings.bytes.len() as u64;
+        let names_size = section_names.len() as u64;
+        let headers = [
+            (0, 0, 0, 0, 0, 0, 0, 0),
+            (1, 8, 0x403, size, self.tls_size, 0, 0, 0), // .tbss
+            (7, 1, 6, text_offset, text_size, 0, 0, 0),  // .text
+            (13, 1, 3, data_offset, data_size, 0, 0, 0), // .data
+            (19, 2, 0, symbols_offset, symbols_size, 5, 1, 24), // .symtab
+            (27, 3, 0, strings_offset, strings_size, 0, 0, 0), // .strtab
+            (35, 3, 0, names_offset, names_size, 0, 0, 0), // .shstrtab
+        ];
+        for (name, stype, flags, offset, size, link, info, entry_size) in headers {
+            let addr = if flags & 2 != 0 { offset } else { 0 }; // SHF_ALLOC
+            e.word(name).word(stype).xword(flags).xword(addr);
+            e.xword(offset).xword(size).word(link).word(info);
+            e.xword(1).xword(entry_size);
+        }
+        e.bytes
+    }
+
+    /// Writes the object to a temporary file and returns its path.
+    pub fn write(&self, name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("udb-{}-{name}.so", std::process::id()));
+        std::fs::write(&path, self.bytes()).unwrap();
+        path
+    }
+}
+
+/// A core with two threads, an exe with a tls_var thread local, and a library with
+/// errno and lib_counter. The second thread hasn't allocated its block for the library.
+pub fn tls_files() -> ElfFiles {
+    const EXE: u64 = 0x400000;
+    const LIBRARY: u64 = 0x7f0000000000;
+    const DATA: u64 = 0x600000;
+
+    let exe = ObjectBuilder::new(0x10)
+        .tls_symbol("tls_var", 8, 4)
+        .debug(DATA)
+        .write("tls-exe");
+    let library = ObjectBuilder::new(4)
+        .tls_symbol("errno", 0, 4)
+        .data_symbol("lib_counter", 8)
+        .write("tls-lib");
+    let exe_name = exe.to_string_lossy().to_string();
+    let library_name = library.to_string_lossy().to_string();
+
+    let mut data = vec![0; 0x500];
+    let mut put = |offset: usize, value: u64| {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    };
+    put(0x8, DATA + 0x10); // r_debug.r_map
+    put(0x10, EXE); // the exe's link_map, its name is null
+    put(0x18, 0);
+    put(0x28, DATA + 0x30);
+    put(0x30, LIBRARY); // the library's link_map
+    put(0x38, DATA + 0x200);
+    put(0x100, 2); // first thread's DTV has two entries
+    put(0x120, DATA + 0x300);
+    put(0x130, DATA + 0x340);
+    put(0x150, 1); // second thread's DTV only has the exe's block
+    put(0x170, DATA + 0x380);
+    put(0x400, DATA + 0x400); // first thread's TCB
+    put(0x408, DATA + 0x110);
+    put(0x420, DATA + 0x420); // second thread's TCB
+    put(0x428, DATA + 0x160);
+    put(0x308, 0x1234); // tls_var
+    put(0x340, 2); // errno, ENOENT
+    put(0x388, 0x5678); // tls_var
+    data[0x200..0x200 + library_name.len()].copy_from_slice(library_name.as_bytes());
+
+    let mut builder = CoreBuilder::new(0x3E, true, true);
+    for (pid, tcb) in [(100, DATA + 0x400), (101, DATA + 0x420)] {
+        let mut registers = vec![0; 27];
+        registers[21] = tcb; // fs_base
+        builder.prstatus(if pid == 100 { 11 } else { 0 }, 0, pid, &registers);
+    }
+    builder.mapped_files(&[
+        (EXE, EXE + 0x1000, 0, &exe_name),
+        (LIBRARY, LIBRARY + 0x1000, 0, &library_name),
+    ]);
+    builder.load(EXE, std::fs::read(&exe).unwrap(), 6);
+    builder.load(LIBRARY, std::fs::read(&library).unwrap(), 6);
+    builder.load(DATA, data, 6);
+    ElfFiles::new(vec![builder.write("tls"), exe]).unwrap()
+}
@@ -0,0 +1,27 @@
+//! Lets long running commands notice when the user hits Ctrl-C.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+fn flag() -> &'static Arc<AtomicBool> {
+    INTERRUPTED.get_or_init(|| Arc::new(AtomicBool::new(false)))
+}
+
+/// Catch SIGINT instead of letting it kill the process. The line editor reads Ctrl-C as
+/// a key so this only matters while a command is running.
+pub fn install_interrupt_handler() {
+    if let Err(err) = signal_hook::flag::register(signal_hook::consts::SIGINT, flag().clone()) {
+        super::warn(&format!("couldn't install a Ctrl-C handler: {err}"));
+    }
+}
+
+/// Call this before starting work that checks for interrupts.
+pub fn clear_interrupt() {
+    flag().store(false, Ordering::Relaxed);
+}
+
+/// True if the user hit Ctrl-C since the last clear_interrupt.
+pub fn interrupted() -> bool {
+    flag().load(Ordering::Relaxed)
+}
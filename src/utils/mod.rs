@@ -1,6 +1,12 @@
+#[cfg(test)]
+pub mod core_builder;
+pub mod errno;
+pub mod interrupt;
 pub mod key_map;
 pub mod styles;
 
+pub use errno::*;
+pub use interrupt::*;
 pub use key_map::*;
 pub use styles::*;
 
@@ -43,7 +49,6 @@ pub fn align_to_word(n: u32) -> u32 {
 }
 
 /// Remove escape sequences from the string (e.g. for colors).
-#[cfg(test)]
 pub fn strip_escapes(s: &str) -> String {
     // The other way to do this is to change styles.rs to not emit escape sequences for
     // unit tests (and maybe also if some sort of --no-color flag is used). That worked